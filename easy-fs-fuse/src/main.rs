@@ -1,9 +1,11 @@
 use clap::{App, Arg};
-use easy_fs::{BlockDevice, EasyFileSystem};
+use easy_fs::{set_clock, BlockDevice, EasyFileSystem, Inode};
 use std::fs::{read_dir, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const BLOCK_SZ: usize = 512;
 const TOTAL_BLOCKS: usize = 0x4000;
@@ -26,6 +28,34 @@ impl BlockDevice for BlockFile {
     }
 }
 
+// the packed files are stamped with the time of the host
+fn host_clock() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+// copy the host directory tree under dir into the easy-fs directory parent
+fn pack_tree(dir: &Path, parent: &Arc<Inode>) -> std::io::Result<()> {
+    for dir_entry in read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().into_string().unwrap();
+        if dir_entry.file_type()?.is_dir() {
+            let child = parent
+                .find(name.as_str())
+                .or_else(|| parent.mkdir(name.as_str()))
+                .unwrap();
+            pack_tree(&dir_entry.path(), &child)?;
+        } else {
+            let mut all_data: Vec<u8> = Vec::new();
+            File::open(dir_entry.path())?.read_to_end(&mut all_data)?;
+            let inode = parent.create(name.as_str()).unwrap();
+            inode.write_at(0, all_data.as_slice());
+        }
+    }
+    Ok(())
+}
+
 fn easy_fs_pack() -> std::io::Result<()> {
     let matches = App::new("EasyFileSystem packer")
         .arg(
@@ -42,6 +72,13 @@ fn easy_fs_pack() -> std::io::Result<()> {
                 .takes_value(true)
                 .help("Executable target dir(with backslash)"),
         )
+        .arg(
+            Arg::with_name("root")
+                .short("r")
+                .long("root")
+                .takes_value(true)
+                .help("Dir copied as it is into the root of the image"),
+        )
         .get_matches();
     let src_path = matches.value_of("source").unwrap();
     let target_path = matches.value_of("target").unwrap();
@@ -55,6 +92,7 @@ fn easy_fs_pack() -> std::io::Result<()> {
         f.set_len((TOTAL_BLOCKS * 512) as u64).unwrap();
         f
     })));
+    set_clock(host_clock);
     // 4MiB, at most 4095 files
     let efs = EasyFileSystem::create(block_file.clone(), TOTAL_BLOCKS as u32, 1);
     let root_inode = Arc::new(EasyFileSystem::root_inode(&efs));
@@ -76,6 +114,11 @@ fn easy_fs_pack() -> std::io::Result<()> {
         let inode = root_inode.create(app.as_str()).unwrap();
         // write data to easy-fs
         inode.write_at(0, all_data.as_slice());
+        // the apps are executable by everyone
+        inode.chmod(0o755);
+    }
+    if let Some(root_path) = matches.value_of("root") {
+        pack_tree(Path::new(root_path), &root_inode)?;
     }
     // list apps
     for app in root_inode.ls() {
@@ -91,6 +134,15 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use easy_fs::{DiskInode, DiskInodeType, DiskTime};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // a clock which ticks once a reading, so that every stamp is later
+    fn tick_clock() -> u64 {
+        static NOW: AtomicU64 = AtomicU64::new(1);
+        NOW.fetch_add(1, Ordering::Relaxed)
+    }
+
     #[test]
     fn efs_test() -> std::io::Result<()> {
         // create a virtual block device on a file and initialize easy-fs on it
@@ -100,9 +152,10 @@ mod tests {
                 .write(true)
                 .create(true)
                 .open("target/fs.img")?;
-            f.set_len(TOTAL_BLOCKS * 512).unwrap();
+            f.set_len((TOTAL_BLOCKS * 512) as u64).unwrap();
             f
         })));
+        set_clock(tick_clock);
         EasyFileSystem::create(block_file.clone(), 4096, 1);
         // open the fs on a block device
         let efs = EasyFileSystem::open(block_file.clone());
@@ -146,6 +199,156 @@ mod tests {
         assert_eq!(nlink, 1);
         let len = filec.read_at(0, &mut buffer);
         assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap(),);
+        assert!(root_inode.find("fileb").is_none());
+        // a held file outlives its last link, and is freed with the last hold
+        let fileh = root_inode.create("fileh").unwrap();
+        let fileh_id = fileh.inode_id();
+        fileh.hold();
+        assert_eq!(root_inode.unlinkat("fileh", 0), 0);
+        let filei = root_inode.create("filei").unwrap();
+        assert_ne!(filei.inode_id(), fileh_id);
+        fileh.write_at(0, greet_str.as_bytes());
+        let len = fileh.read_at(0, &mut buffer);
+        assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap(),);
+        fileh.release();
+        assert_eq!(root_inode.unlinkat("filei", 0), 0);
+        let filej = root_inode.create("filej").unwrap();
+        assert_eq!(filej.inode_id(), fileh_id);
+        assert_eq!(root_inode.unlinkat("filej", 0), 0);
+
+        // ==== directory test ====
+        let root_inode = Arc::new(root_inode);
+        let dira = root_inode.mkdir("dira").unwrap();
+        assert!(root_inode.mkdir("dira").is_none());
+        let filed = dira.create("filed").unwrap();
+        filed.write_at(0, greet_str.as_bytes());
+        let found = root_inode.find_path("/dira/./filed").unwrap();
+        assert_eq!(found.inode_id(), filed.inode_id());
+        let found = dira.find_path("../dira/../filec").unwrap();
+        assert_eq!(found.inode_id(), filec.inode_id());
+        assert_eq!(root_inode.find_path("..").unwrap().inode_id(), 0);
+        assert!(root_inode.find_path("filec/filed").is_none());
+        assert_eq!(root_inode.name_of(dira.inode_id()).unwrap(), "dira");
+        let dirents = dira.dirents(0);
+        let names: Vec<&str> = dirents.iter().map(|(_, dirent, _)| dirent.name()).collect();
+        assert_eq!(names, vec![".", "..", "filed"]);
+        assert!(dirents[1].1.inode_number() == root_inode.inode_id());
+        assert!(dirents[1].2 == DiskInodeType::Directory);
+        assert!(dirents[2].2 == DiskInodeType::File);
+        let (slot, dirent, _) = &dira.dirents(dirents[1].0 + 1)[0];
+        assert_eq!((*slot, dirent.name()), (2, "filed"));
+        assert_eq!(dira.nlink(), 2);
+        assert_eq!(root_inode.rmdir("dira"), -1);
+        assert_eq!(dira.unlinkat("filed", 0), 0);
+        assert_eq!(dira.ls(), vec![".", ".."]);
+        assert_eq!(root_inode.rmdir("dira"), 0);
+        assert!(root_inode.find("dira").is_none());
+
+        // ==== rename test ====
+        let file1 = root_inode.create("file1").unwrap();
+        file1.write_at(0, greet_str.as_bytes());
+        let file1_id = file1.inode_id();
+        assert_eq!(root_inode.rename("file1", &root_inode, "file2", false), 0);
+        assert!(root_inode.find("file1").is_none());
+        assert_eq!(root_inode.find("file2").unwrap().inode_id(), file1_id);
+        assert_eq!(root_inode.rename("file1", &root_inode, "file3", false), -1);
+        // replace a file, which is freed with its last link
+        let file3 = root_inode.create("file3").unwrap();
+        let file3_id = file3.inode_id();
+        assert_eq!(root_inode.rename("file2", &root_inode, "file3", false), 0);
+        assert!(root_inode.find("file2").is_none());
+        assert_eq!(root_inode.find("file3").unwrap().inode_id(), file1_id);
+        assert_eq!(file3.nlink(), 0);
+        let reused = root_inode.create("file4").unwrap();
+        assert_eq!(reused.inode_id(), file3_id);
+        // move a directory, its `..` and the link counts follow
+        let dirb = root_inode.mkdir("dirb").unwrap();
+        let dirc = root_inode.mkdir("dirc").unwrap();
+        let root_nlink = root_inode.nlink();
+        assert_eq!(root_inode.rename("dirc", &dirb, "dird", false), 0);
+        assert_eq!(dirc.find("..").unwrap().inode_id(), dirb.inode_id());
+        assert_eq!(root_inode.nlink(), root_nlink - 1);
+        assert_eq!(dirb.nlink(), 3);
+        assert_eq!(
+            root_inode.find_path("dirb/dird").unwrap().inode_id(),
+            dirc.inode_id()
+        );
+        // swap a file and a directory in two directories
+        assert_eq!(root_inode.rename("file3", &dirb, "dird", true), 0);
+        assert_eq!(
+            root_inode.find("file3").unwrap().inode_id(),
+            dirc.inode_id()
+        );
+        assert_eq!(dirb.find("dird").unwrap().inode_id(), file1_id);
+        assert_eq!(dirc.find("..").unwrap().inode_id(), root_inode.inode_id());
+        assert_eq!(root_inode.nlink(), root_nlink);
+        assert_eq!(dirb.nlink(), 2);
+        assert_eq!(root_inode.rename("file5", &dirb, "dird", true), -1);
+        // replace an empty directory
+        assert_eq!(dirb.unlinkat("dird", 0), 0);
+        assert_eq!(root_inode.rename("file3", &root_inode, "dirb", false), 0);
+        assert!(root_inode.find("file3").is_none());
+        assert_eq!(root_inode.find("dirb").unwrap().inode_id(), dirc.inode_id());
+        assert_eq!(root_inode.nlink(), root_nlink - 1);
+        assert_eq!(root_inode.rmdir("dirb"), 0);
+        assert_eq!(root_inode.unlinkat("file4", 0), 0);
+
+        // ==== symlink test ====
+        let link = root_inode.symlink("link", "dire/filef").unwrap();
+        assert!(root_inode.symlink("link", "filea").is_none());
+        assert!(link.is_symlink());
+        assert!(link.mode() == DiskInodeType::Symlink);
+        assert_eq!(link.readlink().unwrap(), "dire/filef");
+        assert!(filea.readlink().is_none());
+        let dirents = root_inode.dirents(0);
+        let (_, _, mode) = dirents
+            .iter()
+            .find(|(_, dirent, _)| dirent.name() == "link")
+            .unwrap();
+        assert!(*mode == DiskInodeType::Symlink);
+        // a symlink can be hard linked, and is freed with its last link
+        assert_eq!(root_inode.link("link2", &link), 0);
+        assert_eq!(root_inode.unlinkat("link", 0), 0);
+        assert_eq!(
+            root_inode.find("link2").unwrap().readlink().unwrap(),
+            "dire/filef"
+        );
+        assert_eq!(root_inode.unlinkat("link2", 0), 0);
+        assert_eq!(link.nlink(), 0);
+
+        // ==== owner, permission bits and times test ====
+        let filet = root_inode.create("filet").unwrap();
+        let dirt = root_inode.mkdir("dirt").unwrap();
+        assert_eq!((filet.perm(), dirt.perm()), (0o644, 0o755));
+        assert_eq!((filet.uid(), filet.gid()), (0, 0));
+        let (atime, mtime, ctime) = filet.times();
+        assert!(atime == mtime && mtime == ctime && atime > DiskTime::default());
+        // the directory is changed by the new entries
+        assert!(root_inode.times().1 > mtime);
+        filet.write_at(0, b"times");
+        let (atime1, mtime1, ctime1) = filet.times();
+        assert!(atime1 == atime && mtime1 > mtime && ctime1 == mtime1);
+        filet.read_at(0, &mut buffer);
+        let (atime2, mtime2, ctime2) = filet.times();
+        assert!(atime2 > mtime1 && mtime2 == mtime1 && ctime2 == ctime1);
+        // nothing changed since, a second read leaves the times alone
+        filet.read_at(0, &mut buffer);
+        assert!(filet.times() == (atime2, mtime2, ctime2));
+        filet.chmod(0o4711);
+        filet.chown(1000, 100);
+        assert_eq!(filet.perm(), 0o4711);
+        assert!(filet.mode() == DiskInodeType::File);
+        assert_eq!((filet.uid(), filet.gid()), (1000, 100));
+        let (_, mtime3, ctime3) = filet.times();
+        assert!(mtime3 == mtime2 && ctime3 > ctime2);
+        let stamp = DiskTime { sec: 7, nsec: 9 };
+        filet.set_times(None, Some(stamp));
+        let (atime4, mtime4, ctime4) = filet.times();
+        assert!(atime4 == atime2 && mtime4 == stamp && ctime4 > ctime3);
+        // four inodes a block
+        assert_eq!(core::mem::size_of::<DiskInode>(), 128);
+        assert_eq!(root_inode.unlinkat("filet", 0), 0);
+        assert_eq!(root_inode.rmdir("dirt"), 0);
 
         // random string test
         let mut random_str_test = |len: usize| {
@@ -181,6 +384,70 @@ mod tests {
         random_str_test(1000 * BLOCK_SZ);
         random_str_test(2000 * BLOCK_SZ);
 
+        // ==== truncate test ====
+        // the sizes cross the direct, indirect1 and indirect2 bounds, the
+        // blocks freed on the way are needed by the later rounds
+        let data: Vec<u8> = (0..1500 * BLOCK_SZ).map(|i| (i % 251) as u8).collect();
+        for &size in [
+            1000 * BLOCK_SZ + 7,
+            200 * BLOCK_SZ,
+            100 * BLOCK_SZ + BLOCK_SZ / 3,
+            20 * BLOCK_SZ + 1,
+            0,
+        ]
+        .iter()
+        {
+            filea.clear();
+            filea.write_at(0, &data);
+            filea.truncate(size as u32);
+            assert_eq!(filea.size(), size);
+            let mut read_data = vec![0u8; data.len()];
+            assert_eq!(filea.read_at(0, &mut read_data), size);
+            assert_eq!(&read_data[..size], &data[..size]);
+            // grow again, with zeros past the old end
+            filea.truncate((size + BLOCK_SZ) as u32);
+            assert_eq!(filea.read_at(0, &mut read_data), size + BLOCK_SZ);
+            assert_eq!(&read_data[..size], &data[..size]);
+            assert!(read_data[size..size + BLOCK_SZ].iter().all(|&b| b == 0));
+        }
+        filea.clear();
+
+        // ==== decrease_size test ====
+        // (data blocks before, size after, blocks freed), 18 direct blocks and
+        // 128 ids an index block, the index blocks are freed with the last
+        // block they point to
+        let device: Arc<dyn BlockDevice> = block_file.clone();
+        for &(from, to, freed) in [
+            // within indirect2, one of its three indirect1 blocks goes
+            (407, 300 * BLOCK_SZ, 108),
+            // down to the end of indirect1, all of indirect2 goes
+            (407, 146 * BLOCK_SZ, 265),
+            (407, 146 * BLOCK_SZ + 1, 262),
+            // from indirect2 or indirect1 down to the direct blocks
+            (300, 18 * BLOCK_SZ, 286),
+            (147, 18 * BLOCK_SZ + 1, 130),
+            (19, 18 * BLOCK_SZ, 2),
+            (407, 0, 412),
+            // within the last block, nothing is freed
+            (18, 17 * BLOCK_SZ + 5, 0),
+        ]
+        .iter()
+        {
+            filea.clear();
+            filea.truncate((from * BLOCK_SZ) as u32);
+            let blocks = filea.blocks();
+            let freed_blocks =
+                filea.modify_disk_inode(|disk_inode| disk_inode.decrease_size(to as u32, &device));
+            assert_eq!(freed_blocks.len(), freed);
+            assert_eq!(filea.blocks(), blocks - freed);
+            assert_eq!(filea.size(), to);
+            let mut fs = efs.lock();
+            for block in freed_blocks {
+                fs.dealloc_data(block);
+            }
+        }
+        filea.clear();
+
         Ok(())
     }
 }
@@ -0,0 +1,19 @@
+use spin::Mutex;
+
+// nanoseconds from some fixed point, the times of inodes are stamped with it
+static CLOCK: Mutex<fn() -> u64> = Mutex::new(no_clock);
+
+fn no_clock() -> u64 {
+    0
+}
+
+/// Set the clock which stamps the times of inodes, it returns nanoseconds.
+/// All the times are zero until it is set.
+pub fn set_clock(clock: fn() -> u64) {
+    *CLOCK.lock() = clock;
+}
+
+pub(crate) fn now() -> u64 {
+    let clock = *CLOCK.lock();
+    clock()
+}
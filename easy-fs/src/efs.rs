@@ -1,9 +1,10 @@
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use spin::Mutex;
 
 use crate::{get_block_cache, layout::SuperBlock};
 
-use super::{block_cache_sync_all, Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode, BLOCK_SZ};
+use super::{Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode, BLOCK_SZ};
 
 type DataBlock = [u8; BLOCK_SZ];
 
@@ -13,6 +14,9 @@ pub struct EasyFileSystem {
     pub data_bitmap: Bitmap,
     inode_area_start_block: u32,
     data_area_start_block: u32,
+    /// the holds on the inodes in use, which are not freed with their last
+    /// link but with the last hold
+    pub(crate) busy: BTreeMap<u32, usize>,
 }
 
 impl EasyFileSystem {
@@ -41,6 +45,7 @@ impl EasyFileSystem {
             data_bitmap,
             inode_area_start_block: 1 + inode_bitmap_blocks,
             data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            busy: BTreeMap::new(),
         };
         // clear all blocks
         for i in 0..total_blocks {
@@ -74,8 +79,10 @@ impl EasyFileSystem {
             .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
                 disk_inode.initialize(0, DiskInodeType::Directory);
             });
-        block_cache_sync_all();
-        Arc::new(Mutex::new(efs))
+        let efs = Arc::new(Mutex::new(efs));
+        // add `.` and `..` to the root directory
+        Self::root_inode(&efs).init_root();
+        efs
     }
 
     // open an exsisted easy-fs from a block device
@@ -97,6 +104,7 @@ impl EasyFileSystem {
                     ),
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                    busy: BTreeMap::new(),
                 };
                 Arc::new(Mutex::new(efs))
             })
@@ -126,8 +134,9 @@ impl EasyFileSystem {
         self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
     }
 
-    pub fn dealloc_inode(&mut self) {
-        unimplemented!()
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize)
     }
 
     pub fn dealloc_data(&mut self, block_id: u32) {
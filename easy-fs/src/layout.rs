@@ -1,22 +1,31 @@
-use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use super::{clock, get_block_cache, BlockDevice, BLOCK_SZ};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 const EFS_MAGIC: u32 = 0x3b800001;
-const NAME_LENGTH_LIMIT: usize = 27;
-const INODE_DIRECT_COUNT: usize = 28;
+// bumped whenever the on-disk layout changes, 2 adds the owner, the
+// permission bits and the times to DiskInode
+const EFS_VERSION: u32 = 2;
+pub const NAME_LENGTH_LIMIT: usize = 27;
+// so that DiskInode fills 128 bytes
+const INODE_DIRECT_COUNT: usize = 18;
 pub const DIRENT_SZ: usize = 32;
 const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
 const INODE_INDIRECT2_COUNT: usize = INODE_INDIRECT1_COUNT * INODE_INDIRECT1_COUNT;
 const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
 const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT1_COUNT;
 const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
+/// The largest size of a file, with all its blocks indexed
+pub const MAX_FILE_SIZE: usize = INDIRECT2_BOUND * BLOCK_SZ;
+// a read stamps the access time at least once a day, see atime_stale
+const RELATIME_SEC: u32 = 24 * 60 * 60;
 type IndirectBlock = [u32; BLOCK_SZ / 4];
 type DataBlock = [u8; BLOCK_SZ];
 
 #[repr(C)]
 pub struct SuperBlock {
     magic: u32,
+    version: u32,
     pub total_blocks: u32,
     pub inode_bitmap_blocks: u32,
     pub inode_area_blocks: u32,
@@ -35,6 +44,7 @@ impl SuperBlock {
     ) {
         *self = Self {
             magic: EFS_MAGIC,
+            version: EFS_VERSION,
             total_blocks,
             inode_bitmap_blocks,
             inode_area_blocks,
@@ -44,14 +54,39 @@ impl SuperBlock {
     }
 
     pub fn is_valid(&self) -> bool {
-        self.magic == EFS_MAGIC
+        self.magic == EFS_MAGIC && self.version == EFS_VERSION
     }
 }
 
-#[derive(PartialEq)]
+/// A time on disk as seconds and nanoseconds, counted from wherever the
+/// clock given to `set_clock` counts from.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, PartialOrd, Debug)]
+pub struct DiskTime {
+    pub sec: u32,
+    pub nsec: u32,
+}
+
+impl DiskTime {
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self {
+            sec: (nanos / 1_000_000_000) as u32,
+            nsec: (nanos % 1_000_000_000) as u32,
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::from_nanos(clock::now())
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum DiskInodeType {
     File,
     Directory,
+    /// The target path is the data of the inode
+    Symlink,
 }
 
 #[repr(C)]
@@ -62,11 +97,22 @@ pub struct DiskInode {
     pub indirect1: u32,
     pub indirect2: u32,
     pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    /// last read of the data
+    pub atime: DiskTime,
+    /// last change of the data
+    pub mtime: DiskTime,
+    /// last change of the data or of the inode itself
+    pub ctime: DiskTime,
+    /// the rwx bits of the owner, the group and the others, with
+    /// setuid, setgid and sticky above them
+    pub perm: u16,
     type_: DiskInodeType,
 }
 
 impl DiskInode {
-    // initialize a empty DiskInode
+    // initialize a empty DiskInode, owned by root
     pub fn initialize(&mut self, inode_id: u32, type_: DiskInodeType) {
         self.inode_id = inode_id;
         self.size = 0;
@@ -74,15 +120,50 @@ impl DiskInode {
         self.indirect1 = 0;
         self.indirect2 = 0;
         self.nlink = 1;
+        self.uid = 0;
+        self.gid = 0;
+        let now = DiskTime::now();
+        self.atime = now;
+        self.mtime = now;
+        self.ctime = now;
+        self.perm = match type_ {
+            DiskInodeType::File => 0o644,
+            DiskInodeType::Directory => 0o755,
+            DiskInodeType::Symlink => 0o777,
+        };
         self.type_ = type_;
     }
 
     pub fn inc_nlink(&mut self) {
         self.nlink += 1;
+        self.touch_ctime();
     }
 
     pub fn dec_nlink(&mut self) {
         self.nlink -= 1;
+        self.touch_ctime();
+    }
+
+    pub fn touch_atime(&mut self) {
+        self.atime = DiskTime::now();
+    }
+
+    // relatime, a read only stamps the access time if it is older than the
+    // last change or than a day, so that most reads leave the inode clean
+    pub fn atime_stale(&self) -> bool {
+        self.atime <= self.mtime
+            || self.atime <= self.ctime
+            || DiskTime::now().sec >= self.atime.sec.saturating_add(RELATIME_SEC)
+    }
+
+    // the data changes, and so does the inode
+    pub fn touch_mtime(&mut self) {
+        self.mtime = DiskTime::now();
+        self.ctime = self.mtime;
+    }
+
+    pub fn touch_ctime(&mut self) {
+        self.ctime = DiskTime::now();
     }
 
     pub fn is_dir(&self) -> bool {
@@ -93,6 +174,14 @@ impl DiskInode {
         self.type_ == DiskInodeType::File
     }
 
+    pub fn is_symlink(&self) -> bool {
+        self.type_ == DiskInodeType::Symlink
+    }
+
+    pub fn type_(&self) -> DiskInodeType {
+        self.type_
+    }
+
     pub fn get_block_id(&self, inner_id: u32, block_device: &Arc<dyn BlockDevice>) -> u32 {
         let inner_id = inner_id as usize;
         if inner_id < INODE_DIRECT_COUNT {
@@ -300,6 +389,59 @@ impl DiskInode {
         v
     }
 
+    /// Shrink size to new_size and return blocks that should be deallocated,
+    /// the data blocks past the new end and the index blocks left empty.
+    pub fn decrease_size(
+        &mut self,
+        new_size: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Vec<u32> {
+        assert!(new_size <= self.size);
+        let mut v: Vec<u32> = Vec::new();
+        let data_blocks = self.data_blocks() as usize;
+        let new_data_blocks = Self::_data_blocks(new_size) as usize;
+        // the rest of the last block must read as zeros once the file grows again
+        let tail = new_size as usize % BLOCK_SZ;
+        if tail != 0 {
+            let block_id = self.get_block_id(new_data_blocks as u32 - 1, block_device);
+            get_block_cache(block_id as usize, Arc::clone(block_device))
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    data_block[tail..].iter_mut().for_each(|p| *p = 0);
+                });
+        }
+        for inner_id in new_data_blocks..data_blocks {
+            v.push(self.get_block_id(inner_id as u32, block_device));
+        }
+        for block in self.direct.iter_mut().skip(new_data_blocks) {
+            *block = 0;
+        }
+        // low-level indirect1 blocks under indirect2
+        let indirect1_under_indirect2 = |blocks: usize| {
+            (blocks.saturating_sub(INDIRECT1_BOUND) + INODE_INDIRECT1_COUNT - 1)
+                / INODE_INDIRECT1_COUNT
+        };
+        let a0 = indirect1_under_indirect2(new_data_blocks);
+        let a1 = indirect1_under_indirect2(data_blocks);
+        if a0 < a1 {
+            get_block_cache(self.indirect2 as usize, Arc::clone(block_device))
+                .lock()
+                .read(0, |indirect2: &IndirectBlock| {
+                    v.extend_from_slice(&indirect2[a0..a1]);
+                });
+        }
+        if data_blocks > INDIRECT1_BOUND && new_data_blocks <= INDIRECT1_BOUND {
+            v.push(self.indirect2);
+            self.indirect2 = 0;
+        }
+        if data_blocks > DIRECT_BOUND && new_data_blocks <= DIRECT_BOUND {
+            v.push(self.indirect1);
+            self.indirect1 = 0;
+        }
+        self.size = new_size;
+        v
+    }
+
     // read from offset in current disk inode to buf
     pub fn read_at(
         &self,
@@ -377,6 +519,7 @@ impl DiskInode {
             start_block += 1;
             start = end_current_block;
         }
+        self.touch_mtime();
         write_size
     }
 }
@@ -421,4 +564,9 @@ impl DirEntry {
     pub fn inode_number(&self) -> u32 {
         self.inode_number
     }
+
+    // entries with an empty name are free
+    pub fn is_free(&self) -> bool {
+        self.name[0] == 0
+    }
 }
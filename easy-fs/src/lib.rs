@@ -5,6 +5,7 @@ extern crate alloc;
 mod bitmap;
 mod block_cache;
 mod block_dev;
+mod clock;
 mod efs;
 mod layout;
 mod vfs;
@@ -14,6 +15,7 @@ pub const AT_FDCWD: i32 = -100;
 use bitmap::Bitmap;
 pub use block_cache::{block_cache_sync_all, get_block_cache};
 pub use block_dev::BlockDevice;
+pub use clock::set_clock;
 pub use efs::EasyFileSystem;
 pub use layout::*;
 pub use vfs::Inode;
@@ -5,7 +5,7 @@ use spin::{Mutex, MutexGuard};
 
 use super::{
     block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    DiskTime, EasyFileSystem, DIRENT_SZ,
 };
 
 pub struct Inode {
@@ -39,15 +39,103 @@ impl Inode {
         self.read_disk_inode(|disk_node| disk_node.nlink as usize)
     }
 
-    // we only have two Inode type for now
+    pub fn size(&self) -> usize {
+        self.read_disk_inode(|disk_node| disk_node.size as usize)
+    }
+
     pub fn mode(&self) -> DiskInodeType {
-        self.read_disk_inode(|disk_node| {
-            if disk_node.is_dir() {
-                DiskInodeType::Directory
-            } else {
-                DiskInodeType::File
+        self.read_disk_inode(|disk_node| disk_node.type_())
+    }
+
+    /// The blocks taken by the data and the index blocks.
+    pub fn blocks(&self) -> usize {
+        self.read_disk_inode(|disk_node| DiskInode::total_blocks(disk_node.size) as usize)
+    }
+
+    pub fn perm(&self) -> u16 {
+        self.read_disk_inode(|disk_node| disk_node.perm)
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.read_disk_inode(|disk_node| disk_node.uid)
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.read_disk_inode(|disk_node| disk_node.gid)
+    }
+
+    /// The times of the last access, modification and status change.
+    pub fn times(&self) -> (DiskTime, DiskTime, DiskTime) {
+        self.read_disk_inode(|disk_node| (disk_node.atime, disk_node.mtime, disk_node.ctime))
+    }
+
+    /// Set the permission bits, the type of the inode is kept.
+    pub fn chmod(&self, perm: u16) {
+        let _fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            disk_inode.perm = perm & 0o7777;
+            disk_inode.touch_ctime();
+        });
+        block_cache_sync_all();
+    }
+
+    pub fn chown(&self, uid: u32, gid: u32) {
+        let _fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            disk_inode.uid = uid;
+            disk_inode.gid = gid;
+            disk_inode.touch_ctime();
+        });
+        block_cache_sync_all();
+    }
+
+    /// Set the access and modification times, None leaves one as it is.
+    pub fn set_times(&self, atime: Option<DiskTime>, mtime: Option<DiskTime>) {
+        let _fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            if let Some(atime) = atime {
+                disk_inode.atime = atime;
             }
-        })
+            if let Some(mtime) = mtime {
+                disk_inode.mtime = mtime;
+            }
+            disk_inode.touch_ctime();
+        });
+        block_cache_sync_all();
+    }
+
+    /// Keep the inode from being freed while it is open or a working
+    /// directory, each hold is undone by a release.
+    pub fn hold(&self) {
+        let inode_id = self.inode_id();
+        *self.fs.lock().busy.entry(inode_id).or_insert(0) += 1;
+    }
+
+    /// Undo a hold, the inode is freed with the last one if no name is left.
+    pub fn release(&self) {
+        let inode_id = self.inode_id();
+        let mut fs = self.fs.lock();
+        let holds = fs.busy.get_mut(&inode_id).unwrap();
+        *holds -= 1;
+        if *holds > 0 {
+            return;
+        }
+        fs.busy.remove(&inode_id);
+        drop(fs);
+        if self.nlink() == 0 {
+            self.free();
+        }
+    }
+
+    // free the data blocks and the inode, which has lost its last name,
+    // unless it is held, then the last release does
+    fn free(&self) {
+        let inode_id = self.inode_id();
+        if self.fs.lock().busy.contains_key(&inode_id) {
+            return;
+        }
+        self.clear();
+        self.fs.lock().dealloc_inode(inode_id);
     }
 
     pub fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
@@ -62,6 +150,25 @@ impl Inode {
             .modify(self.block_offset, f)
     }
 
+    pub fn is_dir(&self) -> bool {
+        self.read_disk_inode(|disk_node| disk_node.is_dir())
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.read_disk_inode(|disk_node| disk_node.is_symlink())
+    }
+
+    /// The target path of a symbolic link, None for the other inodes.
+    pub fn readlink(&self) -> Option<String> {
+        if !self.is_symlink() {
+            return None;
+        }
+        let mut buf = alloc::vec![0u8; self.size()];
+        let len = self.read_at(0, &mut buf);
+        buf.truncate(len);
+        String::from_utf8(buf).ok()
+    }
+
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
         let fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
@@ -77,9 +184,26 @@ impl Inode {
         })
     }
 
+    /// Look up a `/` separated path relative to this directory.
+    /// `.` and `..` are ordinary entries, so they need no special care.
+    pub fn find_path(self: &Arc<Self>, path: &str) -> Option<Arc<Inode>> {
+        let mut inode = self.clone();
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            if !inode.is_dir() {
+                return None;
+            }
+            inode = inode.find(name)?;
+        }
+        Some(inode)
+    }
+
     fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
         // assert it is a directory
         assert!(disk_inode.is_dir());
+        // empty names mark free entries
+        if name.is_empty() {
+            return None;
+        }
         let file_count = (disk_inode.size as usize) / DIRENT_SZ;
         let mut dirent = DirEntry::new_zeros();
         for i in 0..file_count {
@@ -94,6 +218,29 @@ impl Inode {
         None
     }
 
+    /// Return the name of the entry pointing to inode_id, except `.` and `..`.
+    pub fn name_of(&self, inode_id: u32) -> Option<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            let mut dirent = DirEntry::new_zeros();
+            for i in 0..file_count {
+                assert_eq!(
+                    disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device),
+                    DIRENT_SZ,
+                );
+                if !dirent.is_free()
+                    && dirent.inode_number() == inode_id
+                    && dirent.name() != "."
+                    && dirent.name() != ".."
+                {
+                    return Some(String::from(dirent.name()));
+                }
+            }
+            None
+        })
+    }
+
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
@@ -105,26 +252,156 @@ impl Inode {
                     disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device),
                     DIRENT_SZ
                 );
-                v.push(String::from(dirent.name()));
+                if !dirent.is_free() {
+                    v.push(String::from(dirent.name()));
+                }
             }
             v
         })
     }
 
-    // assume it can only be called by the root Inode
-    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+    // put dirent into the first free entry of the directory, or append it
+    fn add_dirent(
+        &self,
+        dir_inode: &mut DiskInode,
+        dirent: &DirEntry,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let mut slot = DirEntry::new_zeros();
+        for i in 0..file_count {
+            dir_inode.read_at(i * DIRENT_SZ, slot.as_bytes_mut(), &self.block_device);
+            if slot.is_free() {
+                dir_inode.write_at(i * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+                return;
+            }
+        }
+        let new_size = (file_count + 1) * DIRENT_SZ;
+        // increase size
+        self.increase_size(new_size as u32, dir_inode, fs);
+        dir_inode.write_at(
+            file_count * DIRENT_SZ,
+            dirent.as_bytes(),
+            &self.block_device,
+        );
+    }
+
+    // clear the entry called name, return the inode id it pointed to
+    fn remove_dirent(&self, name: &str, dir_inode: &mut DiskInode) -> Option<u32> {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let mut dirent = DirEntry::new_zeros();
+        for i in 0..file_count {
+            dir_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+            if !dirent.is_free() && dirent.name() == name {
+                dir_inode.write_at(
+                    i * DIRENT_SZ,
+                    DirEntry::new_zeros().as_bytes(),
+                    &self.block_device,
+                );
+                return Some(dirent.inode_number());
+            }
+        }
+        None
+    }
+
+    // put dirent in place of the entry called name, return false if there is none
+    fn rewrite_dirent(&self, name: &str, dirent: &DirEntry, dir_inode: &mut DiskInode) -> bool {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let mut slot = DirEntry::new_zeros();
+        for i in 0..file_count {
+            dir_inode.read_at(i * DIRENT_SZ, slot.as_bytes_mut(), &self.block_device);
+            if !slot.is_free() && slot.name() == name {
+                dir_inode.write_at(i * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+                return true;
+            }
+        }
+        false
+    }
+
+    // add `.` and `..` to a new directory
+    fn init_dir_entries(
+        &self,
+        dir_inode: &mut DiskInode,
+        parent_inode_id: u32,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) {
+        let inode_id = dir_inode.inode_id;
+        self.add_dirent(dir_inode, &DirEntry::new(".", inode_id), fs);
+        self.add_dirent(dir_inode, &DirEntry::new("..", parent_inode_id), fs);
+        dir_inode.inc_nlink();
+    }
+
+    // the root directory is its own parent
+    pub(crate) fn init_root(&self) {
         let mut fs = self.fs.lock();
-        if self
-            .read_disk_inode(|root_inode| {
-                // assert it is a directory
-                assert!(root_inode.is_dir());
-                // has the file been created?
-                self.find_inode_id(name, root_inode)
+        self.modify_disk_inode(|root_inode| {
+            self.init_dir_entries(root_inode, 0, &mut fs);
+        });
+        block_cache_sync_all();
+    }
+
+    /// The used entries of this directory from slot on, with their slots and inode types.
+    pub fn dirents(&self, slot: usize) -> Vec<(usize, DirEntry, DiskInodeType)> {
+        let fs = self.fs.lock();
+        let dirents = self.read_disk_inode(|disk_inode| {
+            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
+            let mut v = Vec::new();
+            for i in slot..file_count {
+                let mut dirent = DirEntry::new_zeros();
+                assert_eq!(
+                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device),
+                    DIRENT_SZ
+                );
+                if !dirent.is_free() {
+                    v.push((i, dirent));
+                }
+            }
+            v
+        });
+        // the inodes may be in the same block as this one, so look at them
+        // after it is released
+        dirents
+            .into_iter()
+            .map(|(i, dirent)| {
+                let (block_id, block_offset) = fs.get_disk_inode_pos(dirent.inode_number());
+                let mode = get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+                    .lock()
+                    .read(block_offset, |inode: &DiskInode| inode.type_());
+                (i, dirent, mode)
             })
-            .is_some()
-        {
+            .collect()
+    }
+
+    /// Create a regular file in this directory.
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+
+    /// Create a symbolic link to target in this directory, the target does
+    /// not need to exist.
+    pub fn symlink(&self, name: &str, target: &str) -> Option<Arc<Inode>> {
+        let inode = self.create_inode(name, DiskInodeType::Symlink)?;
+        inode.write_at(0, target.as_bytes());
+        Some(inode)
+    }
+
+    /// Create a directory with `.` and `..` in this directory.
+    pub fn mkdir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory)
+    }
+
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        if self.read_disk_inode(|dir_inode| {
+            // assert it is a directory
+            assert!(dir_inode.is_dir());
+            // has the file been created, or the directory removed?
+            dir_inode.nlink == 0 || self.find_inode_id(name, dir_inode).is_some()
+        }) {
             return None;
         }
+        let is_dir = type_ == DiskInodeType::Directory;
+        let parent_inode_id = self.read_disk_inode(|dir_inode| dir_inode.inode_id);
         // create a new file, alloc a inode id
         let new_inode_id = fs.alloc_inode();
         let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
@@ -132,21 +409,23 @@ impl Inode {
         get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(new_inode_id, DiskInodeType::File);
+                new_inode.initialize(new_inode_id, type_);
             });
+        if is_dir {
+            get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+                .lock()
+                .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                    self.init_dir_entries(new_inode, parent_inode_id, &mut fs);
+                });
+        }
         // append file in the dirent
-        self.modify_disk_inode(|root_inode| {
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
+        self.modify_disk_inode(|dir_inode| {
             let dirent = DirEntry::new(name, new_inode_id);
-            root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
+            self.add_dirent(dir_inode, &dirent, &mut fs);
+            // `..` of the new directory links to us
+            if is_dir {
+                dir_inode.inc_nlink();
+            }
         });
         let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
         assert_eq!(
@@ -164,11 +443,34 @@ impl Inode {
         // release efs lock automatically by compiler
     }
 
+    /// Remove the empty directory `name`, return -1 if there is no such one.
+    pub fn rmdir(&self, name: &str) -> isize {
+        let dir = match self.find(name) {
+            Some(dir) if dir.is_dir() => dir,
+            _ => return -1,
+        };
+        if dir.ls().iter().any(|name| name != "." && name != "..") {
+            return -1;
+        }
+        let fs = self.fs.lock();
+        self.modify_disk_inode(|dir_inode| {
+            self.remove_dirent(name, dir_inode);
+            // the `..` of the removed directory
+            dir_inode.dec_nlink();
+        });
+        drop(fs);
+        dir.modify_disk_inode(|dir_inode| dir_inode.nlink = 0);
+        dir.free();
+        block_cache_sync_all();
+        0
+    }
+
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
         self.modify_disk_inode(|disk_inode| {
             let size = disk_inode.size;
             let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            disk_inode.touch_mtime();
             assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
             for data_block in data_blocks_dealloc.into_iter() {
                 fs.dealloc_data(data_block);
@@ -177,9 +479,37 @@ impl Inode {
         block_cache_sync_all();
     }
 
+    /// Set the size of the file, the blocks past the new end are freed and
+    /// a file which grows reads as zeros there.
+    pub fn truncate(&self, new_size: u32) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            disk_inode.touch_mtime();
+            if new_size >= size {
+                self.increase_size(new_size, disk_inode, &mut fs);
+                return;
+            }
+            let data_blocks_dealloc = disk_inode.decrease_size(new_size, &self.block_device);
+            assert!(
+                data_blocks_dealloc.len()
+                    == (DiskInode::total_blocks(size) - DiskInode::total_blocks(new_size)) as usize
+            );
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+        block_cache_sync_all();
+    }
+
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         let _fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+        let size =
+            self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device));
+        if size > 0 && self.read_disk_inode(DiskInode::atime_stale) {
+            self.modify_disk_inode(DiskInode::touch_atime);
+        }
+        size
     }
 
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
@@ -192,105 +522,171 @@ impl Inode {
         size
     }
 
-    // assume it can only be called by the root Inode
+    // hard link oldpath to newpath, both are names in this directory
     pub fn linkat(&self, oldpath: &str, newpath: &str, flags: u32) -> isize {
         // for now just support AT_FDCWD
         assert_eq!(flags, 0);
-        // if the newpath already exsist, return -1
-        if self
-            .read_disk_inode(|root_inode| {
-                assert!(root_inode.is_dir());
-                self.find_inode_id(newpath, root_inode)
-            })
-            .is_some()
-        {
-            return -1;
-        }
         // if the oldpath not exist, return -1, otherwise hard link it with new path
-        match self.read_disk_inode(|root_inode| {
-            assert!(root_inode.is_dir());
-            self.find_inode_id(oldpath, root_inode)
-        }) {
-            Some(old_inode_id) => {
-                // make sure oldpath is a file
-                self.find(oldpath)
-                    .unwrap()
-                    .read_disk_inode(|old_disk_node| {
-                        assert_eq!(old_disk_node.is_file(), true);
-                    });
-
-                // update link number here for the fs.lock()
-                self.find(oldpath).unwrap().modify_disk_inode(|disk_inode| {
-                    disk_inode.inc_nlink();
-                });
-                let mut fs = self.fs.lock();
-                let dirent = DirEntry::new(newpath, old_inode_id);
-                self.modify_disk_inode(|root_inode| {
-                    let file_count = (root_inode.size as usize) / DIRENT_SZ;
-                    let new_size = (file_count + 1) * DIRENT_SZ;
-                    // increase size
-                    self.increase_size(new_size as u32, root_inode, &mut fs);
-                    root_inode.write_at(
-                        file_count * DIRENT_SZ,
-                        dirent.as_bytes(),
-                        &self.block_device,
-                    );
-                });
-                // block_cache_sync_all();
-                0
-            }
+        match self.find(oldpath) {
+            Some(old_inode) => self.link(newpath, &old_inode),
             None => -1,
         }
     }
 
-    // assume it can only be called by the root Inode
-    pub fn unlinkat(&self, path: &str, flags: u32) -> isize {
-        assert_eq!(flags, 0);
-        match self.read_disk_inode(|root_inode| {
-            assert!(root_inode.is_dir());
-            self.find_inode_id(path, root_inode)
+    /// Add an entry called name for the regular file inode, which may live in
+    /// another directory. Return -1 if name already exists.
+    pub fn link(&self, name: &str, inode: &Inode) -> isize {
+        if self.read_disk_inode(|dir_inode| {
+            assert!(dir_inode.is_dir());
+            dir_inode.nlink == 0 || self.find_inode_id(name, dir_inode).is_some()
         }) {
-            Some(_) => {
-                // not delete the inode, nor free the data, just update the link number...
-                // TODO implement the file delete function
-                let mut dirent = DirEntry::new_zeros();
-                let res = self.modify_disk_inode(|root_inode| {
-                    let file_count = (root_inode.size as usize) / DIRENT_SZ;
-                    for i in 0..file_count {
-                        assert_eq!(
-                            root_inode.read_at(
-                                DIRENT_SZ * i,
-                                dirent.as_bytes_mut(),
-                                &self.block_device,
-                            ),
-                            DIRENT_SZ,
-                        );
-                        if dirent.name() == path {
-                            // write a empty block to cover the old one
-                            root_inode.write_at(
-                                file_count * DIRENT_SZ,
-                                DirEntry::new_zeros().as_bytes(),
-                                &self.block_device,
-                            );
-                            return 0;
-                        }
-                    }
-                    return -1;
-                });
-                if res == 0 {
-                    // ==== update link number ====
-                    let target_inode = self.find(path).unwrap();
-                    target_inode.modify_disk_inode(|target_inode| {
-                        target_inode.dec_nlink();
-                    });
+            return -1;
+        }
+        // update link number here for the fs.lock()
+        let inode_id = inode.modify_disk_inode(|disk_inode| {
+            // make sure it is not a directory
+            assert!(!disk_inode.is_dir());
+            disk_inode.inc_nlink();
+            disk_inode.inode_id
+        });
+        let mut fs = self.fs.lock();
+        let dirent = DirEntry::new(name, inode_id);
+        self.modify_disk_inode(|dir_inode| {
+            self.add_dirent(dir_inode, &dirent, &mut fs);
+        });
+        block_cache_sync_all();
+        0
+    }
+
+    /// Move the entry old_name of this directory to new_name in new_dir, or
+    /// swap the two entries if exchange is set. A new_name already there is
+    /// replaced and freed once it has no link or hold left, so it must not be
+    /// a non-empty directory. Return -1 if old_name does not exist, or
+    /// new_name does not for exchange.
+    pub fn rename(&self, old_name: &str, new_dir: &Inode, new_name: &str, exchange: bool) -> isize {
+        let old_inode = match self.find(old_name) {
+            Some(inode) => inode,
+            None => return -1,
+        };
+        let new_inode = new_dir.find(new_name);
+        if exchange && new_inode.is_none() {
+            return -1;
+        }
+        let old_id = old_inode.inode_id();
+        let new_id = new_inode.as_ref().map(|inode| inode.inode_id());
+        // two links of the same file, or the same entry
+        if new_id == Some(old_id) {
+            return 0;
+        }
+        let self_id = self.inode_id();
+        let new_dir_id = new_dir.inode_id();
+        let same_dir = self_id == new_dir_id;
+        let old_is_dir = old_inode.is_dir();
+        let new_is_dir = new_inode.as_ref().map_or(false, |inode| inode.is_dir());
+        let mut fs = self.fs.lock();
+        if exchange {
+            let new_id = new_id.unwrap();
+            self.modify_disk_inode(|dir_inode| {
+                self.rewrite_dirent(old_name, &DirEntry::new(old_name, new_id), dir_inode)
+            });
+            new_dir.modify_disk_inode(|dir_inode| {
+                new_dir.rewrite_dirent(new_name, &DirEntry::new(new_name, old_id), dir_inode)
+            });
+            if !same_dir {
+                // the directories swapped follow their new parents
+                if old_is_dir {
+                    old_inode.set_parent(new_dir_id);
+                    self.modify_disk_inode(|dir_inode| dir_inode.dec_nlink());
+                    new_dir.modify_disk_inode(|dir_inode| dir_inode.inc_nlink());
+                }
+                if new_is_dir {
+                    new_inode.as_ref().unwrap().set_parent(self_id);
+                    new_dir.modify_disk_inode(|dir_inode| dir_inode.dec_nlink());
+                    self.modify_disk_inode(|dir_inode| dir_inode.inc_nlink());
                 }
-                res
             }
-            None => {
-                // if the path not exsist, return -1
-                -1
+            drop(fs);
+            block_cache_sync_all();
+            return 0;
+        }
+        let dirent = DirEntry::new(new_name, old_id);
+        if same_dir {
+            // the entry is renamed where it is
+            self.modify_disk_inode(|dir_inode| {
+                if new_id.is_some() {
+                    self.remove_dirent(new_name, dir_inode);
+                }
+                self.rewrite_dirent(old_name, &dirent, dir_inode);
+            });
+        } else {
+            new_dir.modify_disk_inode(|dir_inode| {
+                if !new_dir.rewrite_dirent(new_name, &dirent, dir_inode) {
+                    new_dir.add_dirent(dir_inode, &dirent, &mut fs);
+                }
+            });
+            self.modify_disk_inode(|dir_inode| {
+                self.remove_dirent(old_name, dir_inode);
+            });
+            if old_is_dir {
+                old_inode.set_parent(new_dir_id);
+                self.modify_disk_inode(|dir_inode| dir_inode.dec_nlink());
+                new_dir.modify_disk_inode(|dir_inode| dir_inode.inc_nlink());
             }
         }
+        if new_is_dir {
+            // the `..` of the replaced directory
+            new_dir.modify_disk_inode(|dir_inode| dir_inode.dec_nlink());
+        }
+        drop(fs);
+        // ==== free the replaced one with its last link ====
+        if let Some(target_inode) = new_inode {
+            let nlink = target_inode.modify_disk_inode(|target_inode| {
+                if new_is_dir {
+                    target_inode.nlink = 0;
+                } else {
+                    target_inode.dec_nlink();
+                }
+                target_inode.nlink
+            });
+            if nlink == 0 {
+                target_inode.free();
+            }
+        }
+        block_cache_sync_all();
+        0
+    }
+
+    // point `..` of this directory to parent_id
+    fn set_parent(&self, parent_id: u32) {
+        self.modify_disk_inode(|dir_inode| {
+            self.rewrite_dirent("..", &DirEntry::new("..", parent_id), dir_inode)
+        });
+    }
+
+    // remove the name from this directory, the file is freed once
+    // it has no link or hold left
+    pub fn unlinkat(&self, path: &str, flags: u32) -> isize {
+        assert_eq!(flags, 0);
+        let target_inode = match self.find(path) {
+            Some(inode) => inode,
+            // if the path not exsist, return -1
+            None => return -1,
+        };
+        let fs = self.fs.lock();
+        self.modify_disk_inode(|dir_inode| {
+            self.remove_dirent(path, dir_inode);
+        });
+        drop(fs);
+        // ==== update link number ====
+        let nlink = target_inode.modify_disk_inode(|target_inode| {
+            target_inode.dec_nlink();
+            target_inode.nlink
+        });
+        if nlink == 0 {
+            target_inode.free();
+        }
+        block_cache_sync_all();
+        0
     }
 
     fn increase_size(
@@ -1,5 +1,8 @@
 pub const CLOCK_FREQ: usize = 12500000;
 
-pub const MMIO: &[(usize, usize)] = &[(0x10001000, 0x1000)];
+// the goldfish rtc
+pub const RTC_BASE: usize = 0x101000;
+
+pub const MMIO: &[(usize, usize)] = &[(RTC_BASE, 0x1000), (0x10001000, 0x1000)];
 
 pub type BlockDeviceImpl = crate::drivers::block::VirtIOBlock;
@@ -1,11 +1,17 @@
-pub const USER_STACK_SIZE: usize = 4096 * 2;
+// only the touched pages of the user stack are backed by frames
+pub const USER_STACK_SIZE: usize = 4096 * 64;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_HEAP_SIZE: usize = 0x20_0000;
 pub const MEMORY_END: usize = 0x80800000;
 pub const PAGE_SIZE: usize = 0x1000;
+// the harts with larger ids are left alone, entry.asm has a boot stack for each
+pub const MAX_HARTS: usize = 4;
+// the user stacks and trap contexts of this many threads are kept from mmap
+pub const MAX_THREADS: usize = 256;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
+// TrapContext of the main thread, the one of thread tid is tid pages below
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
 /// Return (bottom, top) of a kernel stack in kernel space.
@@ -16,4 +22,4 @@ pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
 }
 
 // #[cfg(feature = "board_qemu")]
-pub use crate::board::{CLOCK_FREQ, MMIO};
+pub use crate::board::{CLOCK_FREQ, MMIO, RTC_BASE};
@@ -1,8 +1,12 @@
 #![allow(dead_code)]
 
 use crate::sbi::console_putchar;
+use crate::sync::SpinLock;
 use core::fmt::{self, Write};
 
+// the lines printed by the harts do not interleave
+static STDOUT: SpinLock<Stdout> = SpinLock::new(Stdout);
+
 struct Stdout;
 
 impl Write for Stdout {
@@ -15,7 +19,7 @@ impl Write for Stdout {
 }
 
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
 }
 
 #[macro_export]
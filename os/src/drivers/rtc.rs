@@ -0,0 +1,15 @@
+use crate::config::RTC_BASE;
+
+// the registers of the goldfish rtc, reading the low half of the time latches
+// the high half
+const TIME_LOW: usize = 0x00;
+const TIME_HIGH: usize = 0x04;
+
+/// The nanoseconds since the unix epoch.
+pub fn rtc_time_ns() -> u64 {
+    unsafe {
+        let low = ((RTC_BASE + TIME_LOW) as *const u32).read_volatile() as u64;
+        let high = ((RTC_BASE + TIME_HIGH) as *const u32).read_volatile() as u64;
+        high << 32 | low
+    }
+}
@@ -1,12 +1,31 @@
     .section .text.entry
     .globl _start
 _start:
-    la sp, boot_stack_top
+    # a0: hart id, the kernel keeps it in tp
+    mv tp, a0
+    call set_boot_stack
     call rust_main
 
+    # the other harts are started here by the boot hart
+    .globl _start_secondary
+_start_secondary:
+    mv tp, a0
+    call set_boot_stack
+    call rust_main_secondary
+
+# sp = boot_stack + (hart id + 1) * boot stack size
+set_boot_stack:
+    addi t0, tp, 1
+    li t1, 4096 * 16
+    mul t0, t0, t1
+    la sp, boot_stack
+    add sp, sp, t0
+    ret
+
     .section .bss.stack
     .globl boot_stack
 boot_stack:
-    .space 4096 * 16
+    # MAX_HARTS boot stacks
+    .space 4096 * 16 * 4
     .globl boot_stack_top
-boot_stack_top:
\ No newline at end of file
+boot_stack_top:
@@ -1,14 +1,30 @@
-use super::{File, Stat, StatMode};
-use crate::drivers::BLOCK_DEVICE;
+use super::{File, Stat, StatMode, SEEK_CUR, SEEK_END, SEEK_SET};
+use crate::drivers::{rtc_time_ns, BLOCK_DEVICE};
+use crate::errno::SysError;
 use crate::fs::UserBuffer;
-use crate::sync::UPSafeCell;
+use crate::sync::SleepMutex;
+use crate::task::Credential;
+use crate::timer::get_time_us;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use easy_fs::{DiskInodeType, EasyFileSystem, Inode};
+use core::ops::Deref;
+use easy_fs::{
+    set_clock, DiskInodeType, EasyFileSystem, Inode, BLOCK_SZ, DIRENT_SZ, MAX_FILE_SIZE,
+    NAME_LENGTH_LIMIT,
+};
 use lazy_static::*;
 
+// the inodes are stamped with unix time as easy-fs-fuse does, counted from the
+// time of boot read once from the rtc so that it never goes backwards
+fn fs_clock() -> u64 {
+    *BOOT_TIME_NS + get_time_us() as u64 * 1000
+}
+
 lazy_static! {
+    static ref BOOT_TIME_NS: u64 = rtc_time_ns().saturating_sub(get_time_us() as u64 * 1000);
     pub static ref ROOT_INODE: Arc<Inode> = {
+        set_clock(fs_clock);
         let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
         Arc::new(EasyFileSystem::root_inode(&efs))
     };
@@ -21,31 +37,74 @@ bitflags! {
         const RDWR = 1 << 1;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        // every write goes to the end of the file
+        const APPEND = 1 << 11;
+        // a read or write returns -EAGAIN instead of sleeping
+        const NONBLOCK = 1 << 12;
+        // fail unless the path is a directory
+        const DIRECTORY = 1 << 16;
+        // fail if the last component is a symbolic link
+        const NOFOLLOW = 1 << 17;
+        // the descriptor is closed by exec
+        const CLOEXEC = 1 << 19;
     }
 }
 
 impl OpenFlags {
     /// Do not check validity for simplicity
-    /// Return (readable, writable)
+    /// Return (readable, writable) by the access mode, the other flags aside
     pub fn read_write(&self) -> (bool, bool) {
-        if self.is_empty() {
-            (true, false)
-        } else if self.contains(Self::WRONLY) {
+        if self.contains(Self::WRONLY) {
             (false, true)
-        } else {
+        } else if self.contains(Self::RDWR) {
             (true, true)
+        } else {
+            (true, false)
         }
     }
 }
 pub struct OSInode {
     readable: bool,
     writable: bool,
-    inner: UPSafeCell<OSInodeInner>,
+    append: bool,
+    // held across the disk I/O
+    inner: SleepMutex<OSInodeInner>,
 }
 
 struct OSInodeInner {
     offset: usize,
-    inode: Arc<Inode>,
+    inode: HeldInode,
+}
+
+/// An inode which is held while it is open or a working directory, so that
+/// it outlives its last name and is freed with the last of these instead.
+pub struct HeldInode(Arc<Inode>);
+
+impl HeldInode {
+    pub fn new(inode: Arc<Inode>) -> Self {
+        inode.hold();
+        Self(inode)
+    }
+}
+
+impl Clone for HeldInode {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+
+impl Deref for HeldInode {
+    type Target = Arc<Inode>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for HeldInode {
+    fn drop(&mut self) {
+        self.0.release();
+    }
 }
 
 impl OSInode {
@@ -53,12 +112,16 @@ impl OSInode {
         Self {
             readable,
             writable,
-            inner: unsafe { UPSafeCell::new(OSInodeInner { offset: 0, inode }) },
+            append: false,
+            inner: SleepMutex::new(OSInodeInner {
+                offset: 0,
+                inode: HeldInode::new(inode),
+            }),
         }
     }
 
     pub fn read_all(&self) -> Vec<u8> {
-        let mut inner = self.inner.exclusive_access();
+        let mut inner = self.inner.lock();
         let mut buffer = [0u8; 512];
         let mut v: Vec<u8> = Vec::new();
         loop {
@@ -71,6 +134,48 @@ impl OSInode {
         }
         v
     }
+
+    pub fn is_dir(&self) -> bool {
+        self.inner.lock().inode.is_dir()
+    }
+}
+
+// d_type of linux_dirent64
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+// d_ino, d_off, d_reclen and d_type before the name
+const DIRENT64_HEADER: usize = 19;
+
+// read into buf from offset, return the size read
+fn read_inode(inode: &Inode, mut offset: usize, mut buf: UserBuffer) -> usize {
+    let mut total_read_size = 0usize;
+    for slice in buf.buffers.iter_mut() {
+        let read_size = inode.read_at(offset, *slice);
+        if read_size == 0 {
+            break;
+        }
+        offset += read_size;
+        total_read_size += read_size;
+    }
+    total_read_size
+}
+
+// write buf at offset, return the size written, which stops at the largest
+// size of a file
+fn write_inode(inode: &Inode, mut offset: usize, buf: UserBuffer) -> usize {
+    let mut total_write_size = 0usize;
+    for slice in buf.buffers.iter() {
+        let len = slice.len().min(MAX_FILE_SIZE.saturating_sub(offset));
+        if len == 0 {
+            break;
+        }
+        let write_size = inode.write_at(offset, &slice[..len]);
+        assert_eq!(write_size, len);
+        offset += write_size;
+        total_write_size += write_size;
+    }
+    total_write_size
 }
 
 impl File for OSInode {
@@ -82,45 +187,118 @@ impl File for OSInode {
         self.writable
     }
 
-    fn read(&self, mut buf: UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
-        let mut total_read_size = 0usize;
-        for slice in buf.buffers.iter_mut() {
-            let read_size = inner.inode.read_at(inner.offset, *slice);
-            if read_size == 0 {
-                break;
-            }
-            inner.offset += read_size;
-            total_read_size += read_size;
-        }
-        total_read_size
+    fn appending(&self) -> bool {
+        self.append
+    }
+
+    fn read(&self, buf: UserBuffer) -> usize {
+        let mut inner = self.inner.lock();
+        let read_size = read_inode(&inner.inode, inner.offset, buf);
+        inner.offset += read_size;
+        read_size
     }
 
     fn write(&self, buf: UserBuffer) -> usize {
-        let mut inner = self.inner.exclusive_access();
-        let mut total_write_size = 0usize;
-        for slice in buf.buffers.iter() {
-            let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
-            total_write_size += write_size;
+        let mut inner = self.inner.lock();
+        if self.append {
+            inner.offset = inner.inode.size();
         }
-        total_write_size
+        let write_size = write_inode(&inner.inode, inner.offset, buf);
+        inner.offset += write_size;
+        write_size
     }
 
     fn fstat(&self) -> Stat {
-        let inner = self.inner.exclusive_access();
-        let mode = match inner.inode.mode() {
-            DiskInodeType::File => StatMode::FILE,
-            DiskInodeType::Directory => StatMode::DIR,
+        stat_of(&self.inner.lock().inode)
+    }
+
+    fn seek(&self, offset: isize, whence: usize) -> Result<usize, SysError> {
+        let mut inner = self.inner.lock();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => inner.offset,
+            SEEK_END => inner.inode.size(),
+            _ => return Err(SysError::EINVAL),
         };
-        Stat {
-            dev: 0,
-            ino: inner.inode.inode_id() as u64,
-            mode,
-            nlink: inner.inode.nlink() as u32,
-            pad: [0; 7],
+        let new_offset = match (base as isize).checked_add(offset) {
+            Some(new_offset) if new_offset >= 0 && new_offset as usize <= MAX_FILE_SIZE => {
+                new_offset as usize
+            }
+            _ => return Err(SysError::EINVAL),
+        };
+        inner.offset = new_offset;
+        Ok(inner.offset)
+    }
+
+    fn read_at(&self, offset: usize, buf: UserBuffer) -> Result<usize, SysError> {
+        let inner = self.inner.lock();
+        Ok(read_inode(&inner.inode, offset, buf))
+    }
+
+    fn write_at(&self, offset: usize, buf: UserBuffer) -> Result<usize, SysError> {
+        let inner = self.inner.lock();
+        if offset >= MAX_FILE_SIZE && buf.len() > 0 {
+            return Err(SysError::EFBIG);
+        }
+        Ok(write_inode(&inner.inode, offset, buf))
+    }
+
+    fn truncate(&self, len: usize) -> Result<(), SysError> {
+        let inner = self.inner.lock();
+        if inner.inode.is_dir() {
+            return Err(SysError::EISDIR);
+        }
+        if len > MAX_FILE_SIZE {
+            return Err(SysError::EFBIG);
         }
+        inner.inode.truncate(len as u32);
+        Ok(())
+    }
+
+    // the offset of a directory is the slot of its next entry times DIRENT_SZ
+    fn getdents(&self, buf: UserBuffer) -> Result<usize, SysError> {
+        let mut inner = self.inner.lock();
+        if !inner.inode.is_dir() {
+            return Err(SysError::ENOTDIR);
+        }
+        let dirents = inner.inode.dirents(inner.offset / DIRENT_SZ);
+        let len = buf.len();
+        let mut records: Vec<u8> = Vec::new();
+        for (slot, dirent, mode) in dirents.iter() {
+            let name = dirent.name().as_bytes();
+            // the name ends with a NUL, and the records are 8 bytes aligned
+            let reclen = (DIRENT64_HEADER + name.len() + 1 + 7) & !7;
+            if records.len() + reclen > len {
+                break;
+            }
+            let start = records.len();
+            let next = (slot + 1) * DIRENT_SZ;
+            records.extend_from_slice(&(dirent.inode_number() as u64).to_ne_bytes());
+            records.extend_from_slice(&(next as i64).to_ne_bytes());
+            records.extend_from_slice(&(reclen as u16).to_ne_bytes());
+            records.push(match mode {
+                DiskInodeType::File => DT_REG,
+                DiskInodeType::Directory => DT_DIR,
+                DiskInodeType::Symlink => DT_LNK,
+            });
+            records.extend_from_slice(name);
+            records.resize(start + reclen, 0);
+            inner.offset = next;
+        }
+        if records.is_empty() && !dirents.is_empty() {
+            // not even one entry fits
+            return Err(SysError::EINVAL);
+        }
+        for (dst, src) in buf.into_iter().zip(records.iter()) {
+            unsafe {
+                *dst = *src;
+            }
+        }
+        Ok(records.len())
+    }
+
+    fn inode(&self) -> Option<Arc<Inode>> {
+        Some(Arc::clone(&self.inner.lock().inode))
     }
 }
 
@@ -132,36 +310,464 @@ pub fn list_apps() {
     debug!("**************/");
 }
 
-// open file from root directory with name and flags
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
-    let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            // clear size
-            inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
+// split path into its parent directory and the last component
+fn split_path(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(idx) => (&path[..idx + 1], &path[idx + 1..]),
+        None => ("", path),
+    }
+}
+
+pub fn stat_of(inode: &Inode) -> Stat {
+    let file_type = match inode.mode() {
+        DiskInodeType::File => StatMode::FILE,
+        DiskInodeType::Directory => StatMode::DIR,
+        DiskInodeType::Symlink => StatMode::LNK,
+    };
+    let (atime, mtime, ctime) = inode.times();
+    Stat {
+        ino: inode.inode_id() as u64,
+        mode: file_type | StatMode::from_bits_truncate(inode.perm() as u32),
+        nlink: inode.nlink() as u32,
+        uid: inode.uid(),
+        gid: inode.gid(),
+        size: inode.size() as u64,
+        blksize: BLOCK_SZ as u32,
+        blocks: inode.blocks() as u64,
+        atime_sec: atime.sec as u64,
+        atime_nsec: atime.nsec as u64,
+        mtime_sec: mtime.sec as u64,
+        mtime_nsec: mtime.nsec as u64,
+        ctime_sec: ctime.sec as u64,
+        ctime_nsec: ctime.nsec as u64,
+        ..Stat::new()
+    }
+}
+
+// the access asked of an inode, as the rwx bits of one class
+pub const MAY_READ: u16 = 4;
+pub const MAY_WRITE: u16 = 2;
+pub const MAY_EXEC: u16 = 1;
+
+/// Whether cred may access inode as mask asks, by the bits of the owner if it
+/// is the owner, else of the group if it is in the group, else of the others.
+/// Root may do anything but execute a file with no x bit at all.
+pub fn permitted(inode: &Inode, cred: &Credential, mask: u16) -> bool {
+    let perm = inode.perm();
+    if cred.is_privileged() {
+        return mask & MAY_EXEC == 0 || inode.is_dir() || perm & 0o111 != 0;
+    }
+    let bits = if cred.euid == inode.uid() {
+        perm >> 6
+    } else if cred.egid == inode.gid() {
+        perm >> 3
+    } else {
+        perm
+    };
+    bits & mask == mask
+}
+
+// entries are added to or removed from dir
+fn may_modify(dir: &Inode, cred: &Credential) -> Result<(), SysError> {
+    if permitted(dir, cred, MAY_WRITE | MAY_EXEC) {
+        Ok(())
+    } else {
+        Err(SysError::EACCES)
+    }
+}
+
+// a new inode belongs to its creator, with the permission bits it asks for
+fn set_owner(inode: &Inode, mode: u32, cred: &Credential) {
+    inode.chown(cred.euid, cred.egid);
+    inode.chmod(mode as u16 & 0o7777);
+}
+
+// the symbolic links followed in one lookup at most
+const MAX_SYMLINKS: usize = 40;
+
+// walk path from dir for cred, counting the symbolic links followed in depth
+fn resolve_from(
+    dir: &Arc<Inode>,
+    path: &str,
+    follow: bool,
+    cred: &Credential,
+    depth: &mut usize,
+) -> Result<Arc<Inode>, SysError> {
+    let mut inode = if path.starts_with('/') {
+        ROOT_INODE.clone()
+    } else {
+        dir.clone()
+    };
+    // a trailing `/` asks for the directory a link points to
+    let follow = follow || path.ends_with('/');
+    let mut names = path.split('/').filter(|name| !name.is_empty()).peekable();
+    while let Some(name) = names.next() {
+        if !inode.is_dir() {
+            return Err(SysError::ENOTDIR);
+        }
+        // a directory is searched through its x bit
+        if !permitted(&inode, cred, MAY_EXEC) {
+            return Err(SysError::EACCES);
+        }
+        let next = inode.find(name).ok_or(SysError::ENOENT)?;
+        if next.is_symlink() && (follow || names.peek().is_some()) {
+            *depth += 1;
+            if *depth > MAX_SYMLINKS {
+                return Err(SysError::ELOOP);
+            }
+            // a relative target starts from the directory holding the link
+            let target = next.readlink().ok_or(SysError::EIO)?;
+            inode = resolve_from(&inode, &target, true, cred, depth)?;
         } else {
-            // create file
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
+            inode = next;
         }
+    }
+    Ok(inode)
+}
+
+/// Resolve path relative to the directory dir, absolute paths start from the
+/// root. The symbolic links on the way are followed, and the last component
+/// too if follow is set. Every directory passed through must be searchable
+/// by cred.
+pub fn resolve(
+    dir: &Arc<Inode>,
+    path: &str,
+    follow: bool,
+    cred: &Credential,
+) -> Result<Arc<Inode>, SysError> {
+    resolve_from(dir, path, follow, cred, &mut 0)
+}
+
+// find the directory which should hold the last component of path
+fn lookup_parent<'a>(
+    dir: &Arc<Inode>,
+    path: &'a str,
+    cred: &Credential,
+) -> Result<(Arc<Inode>, &'a str), SysError> {
+    let (parent_path, name) = split_path(path);
+    if name.is_empty() {
+        return Err(SysError::EINVAL);
+    }
+    if name.len() > NAME_LENGTH_LIMIT {
+        return Err(SysError::ENAMETOOLONG);
+    }
+    let parent = if path.starts_with('/') && parent_path.is_empty() {
+        ROOT_INODE.clone()
     } else {
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
+        resolve(dir, parent_path, true, cred)?
+    };
+    if !parent.is_dir() {
+        return Err(SysError::ENOTDIR);
+    }
+    Ok((parent, name))
+}
+
+/// Absolute path of the directory dir, found by walking up through `..`.
+pub fn path_of(dir: &Arc<Inode>) -> Option<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut inode = dir.clone();
+    while inode.inode_id() != ROOT_INODE.inode_id() {
+        let parent = inode.find("..")?;
+        names.push(parent.name_of(inode.inode_id())?);
+        inode = parent;
+    }
+    if names.is_empty() {
+        return Some(String::from("/"));
+    }
+    Some(
+        names
+            .iter()
+            .rev()
+            .fold(String::new(), |path, name| path + "/" + name),
+    )
+}
+
+// open file at path relative to dir with flags for cred, a file created gets
+// the permission bits of mode
+pub fn open_file(
+    dir: &Arc<Inode>,
+    path: &str,
+    flags: OpenFlags,
+    mode: u32,
+    cred: &Credential,
+) -> Result<Arc<OSInode>, SysError> {
+    let (readable, writable) = flags.read_write();
+    let follow = !flags.contains(OpenFlags::NOFOLLOW);
+    let inode = match resolve(dir, path, follow, cred) {
+        Ok(inode) => {
+            if inode.is_symlink() {
+                return Err(SysError::ELOOP);
+            }
+            if inode.is_dir() && writable {
+                return Err(SysError::EISDIR);
+            }
+            if !inode.is_dir() && flags.contains(OpenFlags::DIRECTORY) {
+                return Err(SysError::ENOTDIR);
+            }
+            // clear size, unless it is opened to append
+            let truncate = (flags.contains(OpenFlags::TRUNC)
+                || flags.contains(OpenFlags::CREATE) && !flags.contains(OpenFlags::APPEND))
+                && !inode.is_dir();
+            let mut mask = 0;
+            if readable {
+                mask |= MAY_READ;
+            }
+            // clearing the data is a write, whatever the access mode
+            if writable || truncate {
+                mask |= MAY_WRITE;
+            }
+            if !permitted(&inode, cred, mask) {
+                return Err(SysError::EACCES);
+            }
+            if truncate {
                 inode.clear();
             }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+            inode
+        }
+        Err(SysError::ENOENT)
+            if flags.contains(OpenFlags::CREATE) && !flags.contains(OpenFlags::DIRECTORY) =>
+        {
+            // create file
+            let (parent, name) = lookup_parent(dir, path, cred)?;
+            match parent.find(name) {
+                // a link to nowhere, create what it points to
+                Some(link) if link.is_symlink() => {
+                    let target = link.readlink().ok_or(SysError::EIO)?;
+                    return open_file(&parent, &target, flags, mode, cred);
+                }
+                Some(_) => return Err(SysError::ENOENT),
+                None => {
+                    may_modify(&parent, cred)?;
+                    let inode = parent.create(name).ok_or(SysError::EEXIST)?;
+                    set_owner(&inode, mode, cred);
+                    inode
+                }
+            }
+        }
+        Err(err) => return Err(err),
+    };
+    let mut file = OSInode::new(readable, writable, inode);
+    file.append = flags.contains(OpenFlags::APPEND);
+    Ok(Arc::new(file))
+}
+
+// mkdirat, create a directory with `.` and `..`
+pub fn mkdir(dir: &Arc<Inode>, path: &str, mode: u32, cred: &Credential) -> isize {
+    let (parent, name) = match lookup_parent(dir, path, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    if let Err(err) = may_modify(&parent, cred) {
+        return err.into();
+    }
+    match parent.mkdir(name) {
+        Some(inode) => {
+            set_owner(&inode, mode, cred);
+            0
+        }
+        None => SysError::EEXIST.into(),
+    }
+}
+
+// unlinkat with AT_REMOVEDIR, the directory must be empty
+pub fn rmdir(dir: &Arc<Inode>, path: &str, cred: &Credential) -> isize {
+    let (parent, name) = match lookup_parent(dir, path, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    if let Err(err) = may_modify(&parent, cred) {
+        return err.into();
+    }
+    if name == "." || name == ".." {
+        return SysError::EINVAL.into();
+    }
+    match parent.find(name) {
+        None => return SysError::ENOENT.into(),
+        Some(inode) if !inode.is_dir() => return SysError::ENOTDIR.into(),
+        Some(inode) if inode.ls().len() > 2 => return SysError::ENOTEMPTY.into(),
+        _ => {}
+    }
+    parent.rmdir(name)
+}
+
+// linkat, wrap the Inode interface and report why it fails
+pub fn linkat(dir: &Arc<Inode>, oldpath: &str, newpath: &str, cred: &Credential) -> isize {
+    // a symbolic link is linked itself, not what it points to
+    let old_inode = match resolve(dir, oldpath, false, cred) {
+        Err(err) => return err.into(),
+        Ok(inode) if inode.is_dir() => return SysError::EPERM.into(),
+        Ok(inode) => inode,
+    };
+    let (parent, name) = match lookup_parent(dir, newpath, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    if parent.find(name).is_some() {
+        return SysError::EEXIST.into();
+    }
+    if let Err(err) = may_modify(&parent, cred) {
+        return err.into();
+    }
+    parent.link(name, &old_inode)
+}
+
+// fail if the entry to replace exists
+const RENAME_NOREPLACE: u32 = 1;
+// swap the two entries, which both exist
+const RENAME_EXCHANGE: u32 = 2;
+
+// whether dir is ancestor or somewhere below it
+fn is_under(dir: &Arc<Inode>, ancestor: &Arc<Inode>) -> bool {
+    let mut inode = dir.clone();
+    loop {
+        if inode.inode_id() == ancestor.inode_id() {
+            return true;
+        }
+        if inode.inode_id() == ROOT_INODE.inode_id() {
+            return false;
+        }
+        inode = match inode.find("..") {
+            Some(parent) => parent,
+            None => return false,
+        };
+    }
+}
+
+// renameat2, wrap the Inode interface and report why it fails
+pub fn renameat(
+    old_dir: &Arc<Inode>,
+    oldpath: &str,
+    new_dir: &Arc<Inode>,
+    newpath: &str,
+    flags: u32,
+    cred: &Credential,
+) -> isize {
+    if flags & !(RENAME_NOREPLACE | RENAME_EXCHANGE) != 0
+        || flags == RENAME_NOREPLACE | RENAME_EXCHANGE
+    {
+        return SysError::EINVAL.into();
+    }
+    let (old_parent, old_name) = match lookup_parent(old_dir, oldpath, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    let (new_parent, new_name) = match lookup_parent(new_dir, newpath, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    if [old_name, new_name]
+        .iter()
+        .any(|&name| name == "." || name == "..")
+    {
+        return SysError::EBUSY.into();
+    }
+    if let Err(err) = may_modify(&old_parent, cred).and(may_modify(&new_parent, cred)) {
+        return err.into();
+    }
+    let old_inode = match old_parent.find(old_name) {
+        Some(inode) => inode,
+        None => return SysError::ENOENT.into(),
+    };
+    let new_inode = new_parent.find(new_name);
+    let exchange = flags & RENAME_EXCHANGE != 0;
+    match &new_inode {
+        None if exchange => return SysError::ENOENT.into(),
+        Some(_) if flags & RENAME_NOREPLACE != 0 => return SysError::EEXIST.into(),
+        // the same file, nothing to do
+        Some(inode) if inode.inode_id() == old_inode.inode_id() => return 0,
+        Some(inode) if !exchange => {
+            if old_inode.is_dir() && !inode.is_dir() {
+                return SysError::ENOTDIR.into();
+            }
+            if !old_inode.is_dir() && inode.is_dir() {
+                return SysError::EISDIR.into();
+            }
+            if inode.is_dir() && inode.ls().len() > 2 {
+                return SysError::ENOTEMPTY.into();
+            }
+        }
+        _ => {}
+    }
+    // a directory can not be moved below itself
+    if old_inode.is_dir() && is_under(&new_parent, &old_inode) {
+        return SysError::EINVAL.into();
     }
+    if let Some(inode) = &new_inode {
+        if exchange && inode.is_dir() && is_under(&old_parent, inode) {
+            return SysError::EINVAL.into();
+        }
+    }
+    old_parent.rename(old_name, &new_parent, new_name, exchange)
 }
 
-// linkat, just wrap the Inode interface
-pub fn linkat(oldpath: &str, newpath: &str, flags: u32) -> isize {
-    ROOT_INODE.linkat(oldpath, newpath, flags)
+// symlinkat, the target is kept as it is and need not exist
+pub fn symlinkat(target: &str, dir: &Arc<Inode>, linkpath: &str, cred: &Credential) -> isize {
+    if target.is_empty() {
+        return SysError::ENOENT.into();
+    }
+    let (parent, name) = match lookup_parent(dir, linkpath, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    if let Err(err) = may_modify(&parent, cred) {
+        return err.into();
+    }
+    match parent.symlink(name, target) {
+        Some(inode) => {
+            // the bits of a link are not used, it is only owned
+            inode.chown(cred.euid, cred.egid);
+            0
+        }
+        None => SysError::EEXIST.into(),
+    }
 }
 
-// unlinkat, just wrap the Inode interface
-pub fn unlinkat(path: &str, flags: u32) -> isize {
-    ROOT_INODE.unlinkat(path, flags)
+// readlinkat, the target of the symbolic link at path
+pub fn readlinkat(dir: &Arc<Inode>, path: &str, cred: &Credential) -> Result<String, SysError> {
+    resolve(dir, path, false, cred)?
+        .readlink()
+        .ok_or(SysError::EINVAL)
+}
+
+// unlinkat, wrap the Inode interface and report why it fails
+pub fn unlinkat(dir: &Arc<Inode>, path: &str, cred: &Credential) -> isize {
+    let (parent, name) = match lookup_parent(dir, path, cred) {
+        Ok(pair) => pair,
+        Err(err) => return err.into(),
+    };
+    if let Err(err) = may_modify(&parent, cred) {
+        return err.into();
+    }
+    match parent.find(name) {
+        None => return SysError::ENOENT.into(),
+        Some(inode) if inode.is_dir() => return SysError::EISDIR.into(),
+        _ => {}
+    }
+    parent.unlinkat(name, 0)
+}
+
+// fchmodat, only the owner or root may change the permission bits
+pub fn chmod(inode: &Inode, mode: u32, cred: &Credential) -> isize {
+    if !cred.is_privileged() && cred.euid != inode.uid() {
+        return SysError::EPERM.into();
+    }
+    inode.chmod(mode as u16 & 0o7777);
+    0
+}
+
+// fchownat, None keeps the id as it is. Root may give the inode to anyone,
+// the owner may only change its group to its own.
+pub fn chown(inode: &Inode, uid: Option<u32>, gid: Option<u32>, cred: &Credential) -> isize {
+    let uid = uid.unwrap_or_else(|| inode.uid());
+    let gid = gid.unwrap_or_else(|| inode.gid());
+    if !cred.is_privileged()
+        && (cred.euid != inode.uid()
+            || uid != inode.uid()
+            || gid != inode.gid() && gid != cred.egid)
+    {
+        return SysError::EPERM.into();
+    }
+    inode.chown(uid, gid);
+    0
 }
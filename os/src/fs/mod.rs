@@ -1,4 +1,7 @@
+use crate::errno::SysError;
 use crate::mm::UserBuffer;
+use alloc::sync::Arc;
+use easy_fs::Inode;
 
 mod inode;
 mod pipe;
@@ -11,12 +14,34 @@ pub struct Stat {
     pub dev: u64,
     /// inode number
     pub ino: u64,
-    /// file type and mode
+    /// file type and permission bits
     pub mode: StatMode,
     /// number of hard links
     pub nlink: u32,
-    /// unused pad
-    pad: [u64; 7],
+    /// user ID of owner
+    pub uid: u32,
+    /// group ID of owner
+    pub gid: u32,
+    /// device ID, for special files
+    pub rdev: u64,
+    pad1: u64,
+    /// total size in bytes
+    pub size: u64,
+    /// block size for I/O
+    pub blksize: u32,
+    pad2: u32,
+    /// number of 512B blocks allocated
+    pub blocks: u64,
+    /// time of last access
+    pub atime_sec: u64,
+    pub atime_nsec: u64,
+    /// time of last modification
+    pub mtime_sec: u64,
+    pub mtime_nsec: u64,
+    /// time of last status change
+    pub ctime_sec: u64,
+    pub ctime_nsec: u64,
+    unused: [u32; 2],
 }
 
 impl Stat {
@@ -26,7 +51,21 @@ impl Stat {
             ino: 0,
             mode: StatMode::NULL,
             nlink: 0,
-            pad: [0; 7],
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+            pad1: 0,
+            size: 0,
+            blksize: 0,
+            pad2: 0,
+            blocks: 0,
+            atime_sec: 0,
+            atime_nsec: 0,
+            mtime_sec: 0,
+            mtime_nsec: 0,
+            ctime_sec: 0,
+            ctime_nsec: 0,
+            unused: [0; 2],
         }
     }
 }
@@ -40,10 +79,16 @@ impl Default for Stat {
 bitflags! {
     pub struct StatMode: u32 {
         const NULL  = 0;
+        /// bit mask for the file type
+        const IFMT  = 0o170000;
         /// directory
         const DIR   = 0o040000;
         /// ordinary regular file
         const FILE  = 0o100000;
+        /// symbolic link
+        const LNK   = 0o120000;
+        /// bit mask for the permission bits, with setuid, setgid and sticky
+        const PERM  = 0o007777;
     }
 }
 
@@ -53,8 +98,80 @@ pub trait File: Send + Sync {
     fn read(&self, buf: UserBuffer) -> usize;
     fn write(&self, buf: UserBuffer) -> usize;
     fn fstat(&self) -> Stat;
+    /// Whether O_NONBLOCK is set, the files which never sleep ignore it.
+    fn nonblocking(&self) -> bool {
+        false
+    }
+    fn set_nonblocking(&self, _nonblocking: bool) {}
+    /// Whether every write goes to the end, as opened with O_APPEND.
+    fn appending(&self) -> bool {
+        false
+    }
+    /// Whether a read would return at once, with data or at the end of file.
+    fn read_ready(&self) -> bool {
+        true
+    }
+    /// Whether a write would return at once.
+    fn write_ready(&self) -> bool {
+        true
+    }
+    /// Move the offset to offset from the start, the current offset or the
+    /// end as whence is SEEK_SET, SEEK_CUR or SEEK_END, return the new offset.
+    fn seek(&self, _offset: isize, _whence: usize) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+    /// Read from offset without moving the offset of the file.
+    fn read_at(&self, _offset: usize, _buf: UserBuffer) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+    /// Write at offset without moving the offset of the file.
+    fn write_at(&self, _offset: usize, _buf: UserBuffer) -> Result<usize, SysError> {
+        Err(SysError::ESPIPE)
+    }
+    /// Cut the file to len bytes, or extend it with zeros.
+    fn truncate(&self, _len: usize) -> Result<(), SysError> {
+        Err(SysError::EINVAL)
+    }
+    /// Fill buf with linux_dirent64 records of the entries from the offset
+    /// on and move the offset past them, return the size filled.
+    fn getdents(&self, _buf: UserBuffer) -> Result<usize, SysError> {
+        Err(SysError::ENOTDIR)
+    }
+    /// The easy-fs inode behind the file, if there is one.
+    fn inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
+    /// Whether the file is the console, which ioctl works on.
+    fn is_tty(&self) -> bool {
+        false
+    }
+}
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+/// An entry of the fd table, the flags are of this descriptor only and
+/// not shared with its duplicates.
+#[derive(Clone)]
+pub struct FileDescriptor {
+    pub file: Arc<dyn File + Send + Sync>,
+    // closed by exec
+    pub cloexec: bool,
+}
+
+impl FileDescriptor {
+    pub fn new(file: Arc<dyn File + Send + Sync>, cloexec: bool) -> Self {
+        Self { file, cloexec }
+    }
 }
 
-pub use inode::{linkat, list_apps, open_file, unlinkat, OSInode, OpenFlags};
+pub use inode::{
+    chmod, chown, linkat, list_apps, mkdir, open_file, path_of, permitted, readlinkat, renameat,
+    resolve, rmdir, stat_of, symlinkat, unlinkat, HeldInode, OSInode, OpenFlags, MAY_EXEC,
+    MAY_WRITE, ROOT_INODE,
+};
 pub use pipe::{make_pipe, Pipe};
-pub use stdio::{Stdin, Stdout};
+pub use stdio::{
+    poll_stdin, tty_foreground, tty_session, tty_set_foreground, tty_set_session, Stdin, Stdout,
+};
@@ -1,8 +1,9 @@
 use super::{File, Stat};
 use crate::mm::UserBuffer;
-use crate::sync::UPSafeCell;
-use crate::task::suspend_current_and_run_next;
+use crate::sync::SpinLock;
+use crate::task::WaitQueue;
 use alloc::sync::{Arc, Weak};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 const RING_BUFFER_SIZE: usize = 32;
 
@@ -20,6 +21,9 @@ pub struct PipeRingBuffer {
     tail: usize,
     status: RingBufferStatus,
     write_end: Option<Weak<Pipe>>,
+    // threads waiting for data and for free space
+    read_waiters: Arc<WaitQueue>,
+    write_waiters: Arc<WaitQueue>,
 }
 
 impl PipeRingBuffer {
@@ -30,6 +34,8 @@ impl PipeRingBuffer {
             tail: 0,
             status: RingBufferStatus::EMPTY,
             write_end: None,
+            read_waiters: Arc::new(WaitQueue::new()),
+            write_waiters: Arc::new(WaitQueue::new()),
         }
     }
 
@@ -85,33 +91,46 @@ impl PipeRingBuffer {
 pub struct Pipe {
     readable: bool,
     writable: bool,
-    buffer: Arc<UPSafeCell<PipeRingBuffer>>,
+    // O_NONBLOCK of this end
+    nonblocking: AtomicBool,
+    buffer: Arc<SpinLock<PipeRingBuffer>>,
 }
 
 impl Pipe {
-    pub fn read_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn read_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: true,
             writable: false,
+            nonblocking: AtomicBool::new(false),
             buffer,
         }
     }
 
-    pub fn write_end_with_buffer(buffer: Arc<UPSafeCell<PipeRingBuffer>>) -> Self {
+    pub fn write_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
         Self {
             readable: false,
             writable: true,
+            nonblocking: AtomicBool::new(false),
             buffer,
         }
     }
 }
 
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // the last write end is gone, the waiting readers will see the end of file
+        if self.writable {
+            self.buffer.lock().read_waiters.wake_all();
+        }
+    }
+}
+
 // create a pipe and return it's read end and write end
 pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
-    let pipe_buffer = unsafe { Arc::new(UPSafeCell::new(PipeRingBuffer::new())) };
+    let pipe_buffer = Arc::new(SpinLock::new(PipeRingBuffer::new()));
     let read_end = Arc::new(Pipe::read_end_with_buffer(pipe_buffer.clone()));
     let write_end = Arc::new(Pipe::write_end_with_buffer(pipe_buffer.clone()));
-    pipe_buffer.exclusive_access().set_write_end(&write_end);
+    pipe_buffer.lock().set_write_end(&write_end);
     (read_end, write_end)
 }
 
@@ -127,16 +146,22 @@ impl File for Pipe {
         let mut buf_iter = buf.into_iter();
         let mut read_size = 0usize;
         loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
+            let mut ring_buffer = self.buffer.lock();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
-                if ring_buffer.all_write_ends_closed() {
+                // a nonblocking reader takes what there is
+                if ring_buffer.all_write_ends_closed() || self.nonblocking() {
                     return read_size;
                 }
-                drop(ring_buffer);
-                suspend_current_and_run_next();
+                let read_waiters = Arc::clone(&ring_buffer.read_waiters);
+                read_waiters.wait_with(|| {
+                    drop(ring_buffer);
+                    true
+                });
                 continue;
             }
+            // the writers run after us, there will be free space by then
+            ring_buffer.write_waiters.wake_all();
             // read at most loop_read bytes
             for _ in 0..loop_read {
                 if let Some(byte_ref) = buf_iter.next() {
@@ -155,13 +180,21 @@ impl File for Pipe {
         let mut buf_iter = buf.into_iter();
         let mut write_size = 0usize;
         loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
+            let mut ring_buffer = self.buffer.lock();
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
-                drop(ring_buffer);
-                suspend_current_and_run_next();
+                if self.nonblocking() {
+                    return write_size;
+                }
+                let write_waiters = Arc::clone(&ring_buffer.write_waiters);
+                write_waiters.wait_with(|| {
+                    drop(ring_buffer);
+                    true
+                });
                 continue;
             }
+            // the readers run after us, there will be data by then
+            ring_buffer.read_waiters.wake_all();
             // write at most loop_write bytes
             for _ in 0..loop_write {
                 if let Some(byte_ref) = buf_iter.next() {
@@ -176,4 +209,17 @@ impl File for Pipe {
     fn fstat(&self) -> Stat {
         Stat::new()
     }
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+    fn read_ready(&self) -> bool {
+        let ring_buffer = self.buffer.lock();
+        ring_buffer.available_read() > 0 || ring_buffer.all_write_ends_closed()
+    }
+    fn write_ready(&self) -> bool {
+        self.buffer.lock().available_write() > 0
+    }
 }
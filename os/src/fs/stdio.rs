@@ -1,12 +1,130 @@
 use super::{File, Stat};
 use crate::mm::UserBuffer;
 use crate::sbi::console_getchar;
-use crate::task::{current_add_signal, suspend_current_and_run_next, SignalFlags};
+use crate::sync::SpinNoIrq;
+use crate::task::{
+    current_add_signal, current_process, processes_in_group, send_signal, SignalFlags, WaitQueue,
+};
+use alloc::collections::VecDeque;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::*;
 
-pub struct Stdin;
+pub struct Stdin {
+    // O_NONBLOCK of this open console
+    nonblocking: AtomicBool,
+}
 
 pub struct Stdout;
 
+// the console as the controlling terminal of a session
+struct Tty {
+    // the session which has taken the console with TIOCSCTTY
+    sid: Option<usize>,
+    // the group Ctrl-C and Ctrl-Z are sent to, the only one which may read
+    fg_pgid: Option<usize>,
+}
+
+const CTRL_C: u8 = 3;
+const CTRL_Z: u8 = 26;
+
+lazy_static! {
+    // characters fetched from the console which no reader has taken yet
+    static ref STDIN_BUFFER: SpinNoIrq<VecDeque<u8>> =
+        SpinNoIrq::new(VecDeque::new());
+    static ref STDIN_WAITERS: WaitQueue = WaitQueue::new();
+    static ref TTY: SpinNoIrq<Tty> = SpinNoIrq::new(Tty {
+        sid: None,
+        fg_pgid: None,
+    });
+}
+
+/// The session the console is the controlling terminal of.
+pub fn tty_session() -> Option<usize> {
+    TTY.lock().sid
+}
+
+/// Make the console the controlling terminal of the session sid.
+pub fn tty_set_session(sid: usize) {
+    let mut tty = TTY.lock();
+    tty.sid = Some(sid);
+    tty.fg_pgid = Some(sid);
+}
+
+/// The foreground process group of the console.
+pub fn tty_foreground() -> Option<usize> {
+    TTY.lock().fg_pgid
+}
+
+/// Move the group pgid to the foreground, the readers left in the
+/// background go back to sleep.
+pub fn tty_set_foreground(pgid: usize) {
+    TTY.lock().fg_pgid = Some(pgid);
+    STDIN_WAITERS.wake_all();
+}
+
+// The console raises no interrupt, so it is polled on every timer tick and
+// whenever the cpu is idle, but only while somebody is waiting for input or
+// a foreground group may have to be signaled.
+pub fn poll_stdin() {
+    if STDIN_WAITERS.is_empty() && tty_foreground().is_none() {
+        return;
+    }
+    fetch_console();
+}
+
+// Move a character from the console to the buffer and wake the readers up.
+// With a foreground group Ctrl-C and Ctrl-Z are signals to it instead.
+fn fetch_console() {
+    let fg_pgid = tty_foreground();
+    let c = console_getchar();
+    if let Some(pgid) = fg_pgid {
+        let signal = match c as u8 {
+            CTRL_C => Some(SignalFlags::SIGINT),
+            CTRL_Z => Some(SignalFlags::SIGTSTP),
+            _ => None,
+        };
+        if let Some(signal) = signal {
+            for process in processes_in_group(pgid).iter() {
+                send_signal(process, signal);
+            }
+            // the readers of the group return to handle the signal
+            STDIN_WAITERS.wake_all();
+            return;
+        }
+    }
+    let mut buffer = STDIN_BUFFER.lock();
+    if c != 0 {
+        buffer.push_back(c as u8);
+    }
+    if !buffer.is_empty() {
+        STDIN_WAITERS.wake_all();
+    }
+}
+
+// Whether the current process may read from the console, a background
+// process of the session which owns it may not.
+fn in_foreground() -> bool {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let tty = TTY.lock();
+    tty.sid != Some(inner.sid) || tty.fg_pgid == Some(inner.pgid)
+}
+
+// Whether the current process has a signal to handle before reading on.
+fn signal_pending() -> bool {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    !inner.signals.difference(inner.signal_mask).is_empty()
+}
+
+impl Stdin {
+    pub fn new() -> Self {
+        Self {
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+}
+
 impl File for Stdin {
     fn readable(&self) -> bool {
         true
@@ -18,21 +136,41 @@ impl File for Stdin {
 
     fn read(&self, mut user_buf: UserBuffer) -> usize {
         assert_eq!(user_buf.len(), 1);
-        // busy loop, give up the CPU if there is no input
-        let mut c: usize;
-        loop {
-            c = console_getchar();
+        // sleep until poll_stdin gets some input for the foreground group
+        let ch = loop {
+            if signal_pending() {
+                return 0;
+            }
+            let mut buffer = STDIN_BUFFER.lock();
+            if !in_foreground() {
+                STDIN_WAITERS.wait_with(|| {
+                    drop(buffer);
+                    !in_foreground()
+                });
+                continue;
+            }
+            let c = match buffer.pop_front() {
+                Some(c) => c as usize,
+                None => console_getchar(),
+            };
             if c == 0 {
-                suspend_current_and_run_next();
+                if self.nonblocking() {
+                    return 0;
+                }
+                // poll_stdin fills the buffer and wakes us up under its lock
+                STDIN_WAITERS.wait_with(|| {
+                    drop(buffer);
+                    true
+                });
                 continue;
-            } else if c == 3 {
+            }
+            drop(buffer);
+            // without a controlling terminal Ctrl-C only interrupts the reader
+            if c as u8 == CTRL_C && tty_foreground().is_none() {
                 current_add_signal(SignalFlags::SIGINT);
-                break;
-            } else {
-                break;
             }
-        }
-        let ch = c as u8;
+            break c as u8;
+        };
         unsafe {
             user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
         }
@@ -47,6 +185,23 @@ impl File for Stdin {
     fn fstat(&self) -> Stat {
         Stat::new()
     }
+
+    fn is_tty(&self) -> bool {
+        true
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    fn read_ready(&self) -> bool {
+        fetch_console();
+        in_foreground() && !STDIN_BUFFER.lock().is_empty()
+    }
 }
 
 impl File for Stdout {
@@ -73,4 +228,8 @@ impl File for Stdout {
     fn fstat(&self) -> Stat {
         Stat::new()
     }
+
+    fn is_tty(&self) -> bool {
+        true
+    }
 }
@@ -17,10 +17,12 @@ mod board;
 mod console;
 mod config;
 mod drivers;
+mod errno;
 mod fs;
 mod lang_items;
 mod mm;
 mod sbi;
+mod smp;
 mod sync;
 mod syscall;
 mod task;
@@ -46,9 +48,24 @@ pub fn rust_main() -> ! {
     trap::init();
     info!("all traps enabled...");
     trap::enable_timer_interrupt();
+    trap::enable_software_interrupt();
     timer::set_next_trigger();
     info!("timer trigger enabled...");
     kprintln!("Welcome to rCore OS!");
+    smp::start_other_harts();
     task::run_tasks();
     panic!("Unreachable in rust_main!");
 }
+
+// the other harts, started once the boot hart has set everything up
+#[no_mangle]
+pub fn rust_main_secondary() -> ! {
+    mm::init_secondary();
+    trap::init();
+    trap::enable_timer_interrupt();
+    trap::enable_software_interrupt();
+    timer::set_next_trigger();
+    info!("hart {} started", smp::hart_id());
+    task::run_tasks();
+    panic!("Unreachable in rust_main_secondary!");
+}
@@ -1,4 +1,4 @@
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -14,7 +14,7 @@ use super::{
     frame_allocator::FrameTracker,
     page_table::{PTEFlags, PageTable, PageTableEntry},
 };
-use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
+use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE};
 
 extern "C" {
     fn stext();
@@ -33,6 +33,8 @@ extern "C" {
 pub enum MapType {
     Identical,
     Framed,
+    // framed, but every frame is allocated on its first access
+    Lazy,
 }
 
 bitflags! {
@@ -47,7 +49,8 @@ bitflags! {
 // a continued segment of virtual address
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    // frames may be shared with other MemorySets after fork (copy on write)
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     map_type: MapType,
     map_perm: MapPermission,
 }
@@ -83,13 +86,13 @@ impl MapArea {
     ) -> Result<(), &'static str> {
         let ppn: PhysPageNum;
         match self.map_type {
-            MapType::Framed => {
+            MapType::Framed | MapType::Lazy => {
                 let frame = frame_alloc();
                 // handle
                 match frame {
                     Some(frame) => {
                         ppn = frame.ppn;
-                        self.data_frames.insert(vpn, frame);
+                        self.data_frames.insert(vpn, Arc::new(frame));
                     }
                     None => {
                         return Err("No enough physical space");
@@ -107,13 +110,25 @@ impl MapArea {
 
     #[allow(unused)]
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        if self.map_type == MapType::Framed {
-            self.data_frames.remove(&vpn);
+        match self.map_type {
+            MapType::Framed => {
+                self.data_frames.remove(&vpn);
+            }
+            MapType::Lazy => {
+                // never touched, so never mapped
+                if self.data_frames.remove(&vpn).is_none() {
+                    return;
+                }
+            }
+            MapType::Identical => {}
         }
         page_table.unmap(vpn);
     }
 
     pub fn map(&mut self, page_table: &mut PageTable) -> Result<(), &'static str> {
+        if self.map_type == MapType::Lazy {
+            return Ok(());
+        }
         for vpn in self.vpn_range {
             match self.map_one(page_table, vpn) {
                 Ok(_) => {
@@ -134,6 +149,22 @@ impl MapArea {
         }
     }
 
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+
+    // Split the area at vpn, self keeps [start, vpn) and the rest is returned.
+    fn split_off(&mut self, vpn: VirtPageNum) -> Self {
+        let end = self.vpn_range.get_end();
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), vpn);
+        Self {
+            vpn_range: VPNRange::new(vpn, end),
+            data_frames: self.data_frames.split_off(&vpn),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+        }
+    }
+
     pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) {
         assert_eq!(self.map_type, MapType::Framed);
         let mut start: usize = 0;
@@ -171,24 +202,114 @@ impl MemorySet {
         }
     }
 
-    pub fn from_existed_userspace(user_space: &Self) -> Self {
+    // User pages are shared read-only between parent and child and only copied
+    // on the first write, see `handle_cow`. Kernel-only areas such as the
+    // TrapContext are still copied eagerly.
+    pub fn from_existed_userspace(user_space: &mut Self) -> Self {
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
-        // copy data from existed user space
         for area in user_space.areas.iter() {
-            memory_set.push(MapArea::from_another(&area), None).unwrap(); // TODO assume that memory sapce is enough
-            for vpn in area.vpn_range {
-                let src_ppn = user_space.translate(vpn).unwrap().ppn();
-                let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
-                dst_ppn
-                    .get_bytes_array()
-                    .copy_from_slice(src_ppn.get_bytes_array());
+            if !area.map_perm.contains(MapPermission::U) {
+                memory_set.push(MapArea::from_another(&area), None).unwrap(); // TODO assume that memory sapce is enough
+                for vpn in area.vpn_range {
+                    let src_ppn = user_space.translate(vpn).unwrap().ppn();
+                    let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
+                    dst_ppn
+                        .get_bytes_array()
+                        .copy_from_slice(src_ppn.get_bytes_array());
+                }
+                continue;
             }
+            let mut new_area = MapArea::from_another(&area);
+            let pte_flags = PTEFlags::from_bits(area.map_perm.bits).unwrap() - PTEFlags::W;
+            for (vpn, frame) in area.data_frames.iter() {
+                // both sides lose write permission until the frame is copied
+                memory_set.page_table.map(*vpn, frame.ppn, pte_flags);
+                user_space.page_table.remap(*vpn, frame.ppn, pte_flags);
+                new_area.data_frames.insert(*vpn, frame.clone());
+            }
+            memory_set.areas.push(new_area);
         }
         memory_set
     }
 
+    /// Handle a page fault on `vpn` caused by an access that needs `perm`.
+    /// Return false if the access is invalid and should raise SIGSEGV.
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, perm: MapPermission) -> bool {
+        if let Some(_) = self.page_table.translate(vpn) {
+            // only a store to a copy on write page can fault on a mapped page
+            return perm == MapPermission::W && self.handle_cow(vpn);
+        }
+        match self
+            .areas
+            .iter_mut()
+            .find(|area| area.map_type == MapType::Lazy && area.contains(vpn))
+        {
+            Some(area) if area.map_perm.contains(perm) => {
+                area.map_one(&mut self.page_table, vpn).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Resolve a write fault on a copy-on-write page.
+    /// Return false if `vpn` is not a COW page, i.e. the fault is a real one.
+    pub fn handle_cow(&mut self, vpn: VirtPageNum) -> bool {
+        let pte = match self.page_table.translate(vpn) {
+            Some(pte) => pte,
+            None => return false,
+        };
+        if pte.writable() {
+            return false;
+        }
+        let area = match self.areas.iter_mut().find(|area| {
+            area.map_perm.contains(MapPermission::W) && area.data_frames.contains_key(&vpn)
+        }) {
+            Some(area) => area,
+            None => return false,
+        };
+        let pte_flags = PTEFlags::from_bits(area.map_perm.bits).unwrap();
+        let frame = area.data_frames.get(&vpn).unwrap();
+        if Arc::strong_count(frame) == 1 {
+            // the other sharers are gone, just take the frame back
+            self.page_table.remap(vpn, frame.ppn, pte_flags);
+            return true;
+        }
+        let new_frame = match frame_alloc() {
+            Some(new_frame) => new_frame,
+            None => return false,
+        };
+        new_frame
+            .ppn
+            .get_bytes_array()
+            .copy_from_slice(frame.ppn.get_bytes_array());
+        self.page_table.remap(vpn, new_frame.ppn, pte_flags);
+        area.data_frames.insert(vpn, Arc::new(new_frame));
+        true
+    }
+
+    /// Fault in the lazy pages of [start_va, end_va) before the kernel reads them
+    /// through their physical addresses.
+    pub fn prepare_user_read(&mut self, start_va: VirtAddr, end_va: VirtAddr) {
+        for vpn in VPNRange::new(start_va.floor(), end_va.ceil()) {
+            self.handle_page_fault(vpn, MapPermission::R);
+        }
+    }
+
+    /// Like `prepare_user_read`, and also break copy on write so that the kernel
+    /// does not write to a shared frame.
+    pub fn prepare_user_write(&mut self, start_va: VirtAddr, end_va: VirtAddr) {
+        for vpn in VPNRange::new(start_va.floor(), end_va.ceil()) {
+            self.handle_page_fault(vpn, MapPermission::W);
+        }
+    }
+
+    // whether vpn belongs to some area, mapped or not yet
+    pub fn contains_vpn(&self, vpn: VirtPageNum) -> bool {
+        self.areas.iter().any(|area| area.contains(vpn))
+    }
+
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
@@ -232,38 +353,64 @@ impl MemorySet {
         }
     }
 
-    // TODO improve bruteforce munmap
-    pub fn remove_mapped_frames(&mut self, start_va: VirtAddr, end_va: VirtAddr) -> isize {
+    // Unmap [start_va, end_va), areas partly inside the range are split.
+    /// Return the frames which were mapped, None if part of the range is not
+    /// mapped. They must be kept until no TLB of any hart refers to them.
+    pub fn remove_mapped_frames(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+    ) -> Option<Vec<Arc<FrameTracker>>> {
+        let (start_vpn, end_vpn) = (start_va.floor(), end_va.ceil());
         // make sure the vpn is belong to current MemorySet
-        for vpn in VPNRange::new(start_va.floor(), end_va.ceil()) {
-            if let None = self
-                .areas
-                .iter()
-                .position(|area| area.data_frames.contains_key(&vpn))
-            {
-                return -1;
+        for vpn in VPNRange::new(start_vpn, end_vpn) {
+            if !self.contains_vpn(vpn) {
+                return None;
             }
         }
-        // drop the MapAreas in a bruteforce way
-        for vpn in VPNRange::new(start_va.floor(), end_va.ceil()) {
-            let index = self
-                .areas
-                .iter()
-                .position(|area| area.data_frames.contains_key(&vpn))
-                .unwrap();
-            self.areas[index].unmap_one(&mut self.page_table, vpn);
-            self.areas[index].data_frames.remove(&vpn);
-            if self.areas[index].data_frames.is_empty() {
-                self.areas.remove(index);
+        let mut frames = Vec::new();
+        let mut idx = 0;
+        while idx < self.areas.len() {
+            let area_start = self.areas[idx].vpn_range.get_start();
+            let area_end = self.areas[idx].vpn_range.get_end();
+            if area_end <= start_vpn || area_start >= end_vpn {
+                idx += 1;
+                continue;
+            }
+            let mut area = self.areas.remove(idx);
+            if area_start < start_vpn {
+                let rest = area.split_off(start_vpn);
+                self.areas.insert(idx, area);
+                idx += 1;
+                area = rest;
+            }
+            if end_vpn < area_end {
+                let rest = area.split_off(end_vpn);
+                self.areas.insert(idx, rest);
             }
+            frames.extend(area.data_frames.values().cloned());
+            area.unmap(&mut self.page_table);
         }
-        0
+        Some(frames)
     }
 
     pub fn recycle_data_pages(&mut self) {
         self.areas.clear();
     }
 
+    /// Assume that no conflicts. Frames are allocated on page faults.
+    pub fn insert_lazy_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        permission: MapPermission,
+    ) -> Result<(), &'static str> {
+        self.push(
+            MapArea::new(start_va, end_va, MapType::Lazy, permission),
+            None,
+        )
+    }
+
     /// Assume that no conflicts.
     pub fn insert_framed_area(
         &mut self,
@@ -382,8 +529,8 @@ impl MemorySet {
         }
         memory_set
     }
-    // Include sections in elf, set trampoline and TrapContext and user stack,
-    // also returns user_sp and entry point.
+    // Include sections in elf and set trampoline, also returns the base of the
+    // user stacks and entry point. User stacks and TrapContexts are mapped per thread.
     pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
         let mut memory_set = Self::new_bare();
         // map trampoline
@@ -424,55 +571,29 @@ impl MemorySet {
                     .unwrap();
             }
         }
-        // map user stack with U flags
+        // user stacks of the threads are placed above the elf
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
-        // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        memory_set
-            .push(
-                MapArea::new(
-                    user_stack_bottom.into(),
-                    user_stack_top.into(),
-                    MapType::Framed,
-                    MapPermission::R | MapPermission::W | MapPermission::U,
-                ),
-                None,
-            )
-            .unwrap();
-        // map TrapContext
-        memory_set
-            .push(
-                MapArea::new(
-                    TRAP_CONTEXT.into(),
-                    TRAMPOLINE.into(),
-                    MapType::Framed,
-                    MapPermission::R | MapPermission::W,
-                ),
-                None,
-            )
-            .unwrap();
+        let user_stack_base: usize = max_end_va.into();
         (
             memory_set,
-            user_stack_top,
+            user_stack_base,
             elf.header.pt2.entry_point() as usize,
         )
     }
 }
 
 lazy_static! {
-    pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> =
-        Arc::new(unsafe { UPSafeCell::new(MemorySet::new_kernel()) });
+    pub static ref KERNEL_SPACE: Arc<SpinLock<MemorySet>> =
+        Arc::new(SpinLock::new(MemorySet::new_kernel()));
 }
 
 pub fn kernel_token() -> usize {
-    KERNEL_SPACE.exclusive_access().token()
+    KERNEL_SPACE.lock().token()
 }
 
 #[allow(unused)]
 pub fn remap_test() {
-    let mut kernel_space = KERNEL_SPACE.exclusive_access();
+    let mut kernel_space = KERNEL_SPACE.lock();
     let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
     let mid_rodata: VirtAddr = ((srodata as usize + erodata as usize) / 2).into();
     let mid_data: VirtAddr = ((sdata as usize + edata as usize) / 2).into();
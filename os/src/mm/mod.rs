@@ -10,12 +10,18 @@ pub use frame_allocator::{frame_alloc, frame_dealloc, FrameTracker};
 pub use memory_set::remap_test;
 pub use memory_set::{kernel_token, MapArea, MapPermission, MapType, MemorySet, KERNEL_SPACE};
 pub use page_table::{
-    translated_byte_buffers, translated_ref, translated_refmut, translated_str, PageTableEntry,
+    translated_byte_buffers, translated_ref, translated_refmut, translated_str, user_buffer_mapped,
+    user_buffer_writable, PageTableEntry,
 };
 pub use page_table::{PTEFlags, PageTable, UserBuffer, UserBufferIterator};
 
 pub fn init() {
     heap_allocator::init_heap();
     frame_allocator::init_frame_allocator();
-    KERNEL_SPACE.exclusive_access().activate();
+    KERNEL_SPACE.lock().activate();
+}
+
+// the heap and the frames have been set up by the boot hart
+pub fn init_secondary() {
+    KERNEL_SPACE.lock().activate();
 }
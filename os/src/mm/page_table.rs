@@ -1,11 +1,12 @@
 use super::address::StepByOne;
-use super::address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
+use super::address::{PhysAddr, PhysPageNum, VPNRange, VirtAddr, VirtPageNum};
 use super::frame_allocator::{frame_alloc, FrameTracker};
 
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::*;
+use core::mem::size_of;
 
 bitflags! {
     pub struct PTEFlags: u8 {
@@ -139,6 +140,13 @@ impl PageTable {
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
     }
 
+    // change the mapping of an already mapped vpn
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        let pte = self.find_pte(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before remapping", vpn);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
+
     pub fn unmap(&mut self, vpn: VirtPageNum) {
         let pte = self.find_pte(vpn).unwrap();
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
@@ -168,15 +176,50 @@ pub fn translated_byte_buffers(token: usize, ptr: *const u8, len: usize) -> Vec<
     v
 }
 
-pub fn translated_str(token: usize, ptr: *const u8) -> String {
+// check that every page in [ptr, ptr + len) is mapped with flags for the user
+fn user_buffer_has(token: usize, ptr: *const u8, len: usize, flags: PTEFlags) -> bool {
+    let page_table = PageTable::from_token(token);
+    let start_va = VirtAddr::from(ptr as usize);
+    let end_va = VirtAddr::from(ptr as usize + len);
+    VPNRange::new(start_va.floor(), end_va.ceil())
+        .into_iter()
+        .all(|vpn| match page_table.translate(vpn) {
+            Some(pte) => pte.flags().contains(PTEFlags::U | flags),
+            None => false,
+        })
+}
+
+// check that every page in [ptr, ptr + len) is mapped and accessible in user mode
+pub fn user_buffer_mapped(token: usize, ptr: *const u8, len: usize) -> bool {
+    user_buffer_has(token, ptr, len, PTEFlags::empty())
+}
+
+// like user_buffer_mapped, and writable by the user too, so the kernel does
+// not write where the user may not, e.g. its text, once copy on write and
+// lazy mapping have been through prepare_user_write
+pub fn user_buffer_writable(token: usize, ptr: *const u8, len: usize) -> bool {
+    user_buffer_has(token, ptr, len, PTEFlags::W)
+}
+
+// the physical address of the user address va, None unless its page is
+// mapped for the user with flags
+fn translate_user(page_table: &PageTable, va: usize, flags: PTEFlags) -> Option<PhysAddr> {
+    let va = VirtAddr::from(va);
+    let pte = page_table.translate(va.floor())?;
+    if !pte.flags().contains(PTEFlags::U | flags) {
+        return None;
+    }
+    page_table.translate_va(va)
+}
+
+/// The string at the user address ptr up to its `\0`, None if some byte of
+/// it is not mapped for the user.
+pub fn translated_str(token: usize, ptr: *const u8) -> Option<String> {
     let page_table = PageTable::from_token(token);
     let mut string = String::new();
     let mut va = ptr as usize;
     loop {
-        let ch: u8 = *(page_table
-            .translate_va(VirtAddr::from(va))
-            .unwrap()
-            .get_mut());
+        let ch: u8 = *translate_user(&page_table, va, PTEFlags::empty())?.get_mut();
         if ch == 0 {
             break;
         } else {
@@ -184,24 +227,29 @@ pub fn translated_str(token: usize, ptr: *const u8) -> String {
             va += 1;
         }
     }
-    string
+    Some(string)
 }
 
-pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
-    let page_table = PageTable::from_token(token);
-    page_table
-        .translate_va(VirtAddr::from(ptr as usize))
-        .unwrap()
-        .get_ref()
+// the user address ptr of a T as a physical address, None if it is not mapped
+// for the user with flags or crosses a page boundary
+fn translated_user<T>(token: usize, ptr: *const T, flags: PTEFlags) -> Option<PhysAddr> {
+    let start = VirtAddr::from(ptr as usize);
+    let last = VirtAddr::from((ptr as usize).checked_add(size_of::<T>().max(1) - 1)?);
+    if start.floor() != last.floor() {
+        return None;
+    }
+    translate_user(&PageTable::from_token(token), ptr as usize, flags)
 }
 
-pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
-    let page_table = PageTable::from_token(token);
-    let va = ptr as usize;
-    page_table
-        .translate_va(VirtAddr::from(va))
-        .unwrap()
-        .get_mut()
+/// The T at the user address ptr, None if it is not mapped for the user.
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> Option<&'static T> {
+    translated_user(token, ptr, PTEFlags::empty()).map(|pa| pa.get_ref())
+}
+
+/// Like `translated_ref`, for the kernel to write the T, which must be writable
+/// by the user too.
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> Option<&'static mut T> {
+    translated_user(token, ptr, PTEFlags::W).map(|pa| pa.get_mut())
 }
 
 pub struct UserBuffer {
@@ -0,0 +1,116 @@
+//! Bringing up the other harts and the interrupts the harts send to each other.
+
+use core::arch::asm;
+use core::hint::spin_loop;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+use crate::config::MAX_HARTS;
+use crate::sbi;
+
+// the reasons of an interprocessor interrupt, a bit each
+pub const IPI_RESCHEDULE: usize = 1 << 0;
+pub const IPI_TLB_FLUSH: usize = 1 << 1;
+
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+// the reasons posted to each hart and not handled yet
+static IPI_PENDING: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+// the address space a hart runs in the user mode, 0 while it is in the kernel
+static USER_TOKEN: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+// bumped each time a hart traps into the kernel
+static TRAP_EPOCH: [AtomicUsize; MAX_HARTS] = [ZERO; MAX_HARTS];
+
+/// The id of the hart running this code, the kernel keeps it in tp.
+pub fn hart_id() -> usize {
+    let id;
+    unsafe {
+        asm!("mv {}, tp", out(reg) id);
+    }
+    id
+}
+
+/// Start the harts other than the boot hart at _start_secondary.
+pub fn start_other_harts() {
+    extern "C" {
+        fn _start_secondary();
+    }
+    let boot_hart = hart_id();
+    for hart in (0..MAX_HARTS).filter(|&hart| hart != boot_hart) {
+        // QEMU may have fewer harts than MAX_HARTS
+        if sbi::hart_start(hart, _start_secondary as usize, 0) {
+            info!("hart {} starting...", hart);
+        }
+    }
+}
+
+/// Interrupt the hart for the reasons in kind.
+pub fn send_ipi(hart: usize, kind: usize) {
+    IPI_PENDING[hart].fetch_or(kind, Ordering::SeqCst);
+    sbi::send_ipi(1 << hart);
+}
+
+/// Interrupt all the other harts for the reasons in kind.
+pub fn send_ipi_others(kind: usize) {
+    let me = hart_id();
+    for hart in (0..MAX_HARTS).filter(|&hart| hart != me) {
+        send_ipi(hart, kind);
+    }
+}
+
+/// Acknowledge the software interrupt of this hart and return the reasons
+/// posted to it.
+pub fn take_ipi() -> usize {
+    unsafe {
+        // clear sip.SSIP
+        asm!("csrci sip, 2");
+    }
+    let kind = IPI_PENDING[hart_id()].swap(0, Ordering::SeqCst);
+    if kind & IPI_TLB_FLUSH != 0 {
+        unsafe {
+            asm!("sfence.vma");
+        }
+    }
+    kind
+}
+
+/// Called first on a trap from the user mode, before any lock is taken.
+pub fn enter_kernel() {
+    let hart = hart_id();
+    USER_TOKEN[hart].store(0, Ordering::SeqCst);
+    TRAP_EPOCH[hart].fetch_add(1, Ordering::SeqCst);
+}
+
+/// Called last before returning to the address space token in the user mode,
+/// the TLB is flushed after this.
+pub fn leave_kernel(token: usize) {
+    USER_TOKEN[hart_id()].store(token, Ordering::SeqCst);
+}
+
+/// The harts running in the user mode of the address space token.
+pub fn harts_in(token: usize) -> impl Iterator<Item = usize> {
+    (0..MAX_HARTS).filter(move |&hart| USER_TOKEN[hart].load(Ordering::SeqCst) == token)
+}
+
+/// Make sure no other hart uses the stale translations of the address space
+/// token, after some of its mappings are removed. A hart in the user mode of
+/// the address space is interrupted and waited for until it traps, the others
+/// flush their TLBs before they get back to it. Must be called with no locks
+/// held, the other harts may need them before they trap.
+pub fn tlb_shootdown(token: usize) {
+    // the page table writes are visible to the other harts before their
+    // tokens are read
+    fence(Ordering::SeqCst);
+    unsafe {
+        asm!("sfence.vma");
+    }
+    let me = hart_id();
+    for hart in harts_in(token).filter(|&hart| hart != me) {
+        let epoch = TRAP_EPOCH[hart].load(Ordering::SeqCst);
+        send_ipi(hart, IPI_TLB_FLUSH);
+        while USER_TOKEN[hart].load(Ordering::SeqCst) == token
+            && TRAP_EPOCH[hart].load(Ordering::SeqCst) == epoch
+        {
+            spin_loop();
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use super::Mutex;
+use crate::task::WaitQueue;
+use alloc::sync::Arc;
+
+pub struct Condvar {
+    pub wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self {
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    // wake up one waiter, the signal is lost if there is none
+    pub fn signal(&self) {
+        self.wait_queue.wake_one();
+    }
+
+    // Release the mutex while sleeping and take it again after being woken up.
+    // Return false without waiting if the mutex is not locked.
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) -> bool {
+        // a signal right after the unlock finds us in the queue already
+        let mut locked = false;
+        self.wait_queue.wait_with(|| {
+            locked = mutex.unlock();
+            locked
+        });
+        if !locked {
+            return false;
+        }
+        mutex.lock();
+        true
+    }
+}
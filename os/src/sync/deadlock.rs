@@ -0,0 +1,135 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+
+// a kernel object a thread can hold and wait for
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Resource {
+    Mutex(usize),
+    Semaphore(usize),
+}
+
+/// Book-keeping of the locks of a process for the banker's safety check.
+///
+/// The available vector and the allocation and need matrices are kept up to date
+/// all the time, the check itself only runs when the detection is enabled.
+/// A semaphore released by a thread which never acquired it (signalling) takes
+/// the unit from one of the threads which did, so that a consumer is not taken
+/// to hold what a producer has given back. The units still held by a thread
+/// when it exits are never given back, they are dropped from the books.
+pub struct DeadlockDetector {
+    pub enabled: bool,
+    available: BTreeMap<Resource, usize>,
+    // (tid, resource) -> number of units
+    allocation: BTreeMap<(usize, Resource), usize>,
+    need: BTreeMap<(usize, Resource), usize>,
+}
+
+impl DeadlockDetector {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            available: BTreeMap::new(),
+            allocation: BTreeMap::new(),
+            need: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_resource(&mut self, res: Resource, count: usize) {
+        self.available.insert(res, count);
+    }
+
+    /// Record that tid is about to wait for one unit of res.
+    /// Return false and forget the request if granting it may lead to a deadlock.
+    pub fn request(&mut self, tid: usize, res: Resource) -> bool {
+        *self.need.entry((tid, res)).or_insert(0) += 1;
+        if self.enabled && !self.is_safe() {
+            self.cancel(tid, res);
+            return false;
+        }
+        true
+    }
+
+    fn cancel(&mut self, tid: usize, res: Resource) {
+        if let Some(need) = self.need.get_mut(&(tid, res)) {
+            *need -= 1;
+            if *need == 0 {
+                self.need.remove(&(tid, res));
+            }
+        }
+    }
+
+    /// Record that the requested unit of res has been given to tid.
+    pub fn acquire(&mut self, tid: usize, res: Resource) {
+        self.cancel(tid, res);
+        if let Some(available) = self.available.get_mut(&res) {
+            *available = available.saturating_sub(1);
+        }
+        *self.allocation.entry((tid, res)).or_insert(0) += 1;
+    }
+
+    /// Record that tid gives one unit of res back. If it holds none, the unit
+    /// is taken back from some thread which does.
+    pub fn release(&mut self, tid: usize, res: Resource) {
+        let holder = if self.allocation.contains_key(&(tid, res)) {
+            Some(tid)
+        } else {
+            self.allocation
+                .keys()
+                .find(|(_, r)| *r == res)
+                .map(|(holder, _)| *holder)
+        };
+        if let Some(holder) = holder {
+            let allocation = self.allocation.get_mut(&(holder, res)).unwrap();
+            *allocation -= 1;
+            if *allocation == 0 {
+                self.allocation.remove(&(holder, res));
+            }
+        }
+        *self.available.entry(res).or_insert(0) += 1;
+    }
+
+    /// Forget an exited thread, a recycled tid starts with a clean slate. What
+    /// it holds is not made available, nobody is going to release it.
+    pub fn remove_thread(&mut self, tid: usize) {
+        self.need.retain(|(t, _), _| *t != tid);
+        self.allocation.retain(|(t, _), _| *t != tid);
+    }
+
+    // Whether all threads can finish in some order with the available resources.
+    fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let mut unfinished: BTreeSet<usize> = self
+            .allocation
+            .keys()
+            .chain(self.need.keys())
+            .map(|(tid, _)| *tid)
+            .collect();
+        loop {
+            let finishable = unfinished.iter().copied().find(|tid| {
+                self.need
+                    .range((*tid, Resource::Mutex(0))..)
+                    .take_while(|((t, _), _)| t == tid)
+                    .all(|((_, res), need)| work.get(res).copied().unwrap_or(0) >= *need)
+            });
+            match finishable {
+                Some(tid) => {
+                    // the thread finishes and gives back everything it holds
+                    for ((_, res), allocation) in self
+                        .allocation
+                        .range((tid, Resource::Mutex(0))..)
+                        .take_while(|((t, _), _)| *t == tid)
+                    {
+                        *work.entry(*res).or_insert(0) += allocation;
+                    }
+                    unfinished.remove(&tid);
+                }
+                None => return unfinished.is_empty(),
+            }
+        }
+    }
+}
+
+impl Default for DeadlockDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
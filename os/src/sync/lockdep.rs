@@ -0,0 +1,203 @@
+//! Lock order checking, built with debug assertions only.
+//!
+//! The locks are grouped into classes by the type of the data they guard.
+//! Each time a hart takes a lock while holding others, the order between
+//! their classes is recorded. A lock taken against an order recorded
+//! earlier, directly or through other classes, is reported once as a
+//! possible deadlock. Taking a lock the hart already holds panics instead
+//! of spinning forever. Locks of the same class taken inside each other,
+//! e.g. the parent and the child process, are not ordered. The locks still
+//! held by a thread switching out are parked, it may release them on
+//! another hart.
+
+#[cfg(debug_assertions)]
+mod imp {
+    use alloc::collections::{BTreeMap, BTreeSet};
+    use alloc::vec::Vec;
+    use core::cell::UnsafeCell;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::config::MAX_HARTS;
+    use crate::smp::hart_id;
+
+    const MAX_HELD: usize = 32;
+
+    // the spin locks held by a hart, as (address, class)
+    struct Held {
+        locks: [(usize, &'static str); MAX_HELD],
+        len: usize,
+    }
+
+    struct PerHart(UnsafeCell<Held>);
+
+    // each hart only touches its own
+    unsafe impl Sync for PerHart {}
+
+    const EMPTY: PerHart = PerHart(UnsafeCell::new(Held {
+        locks: [(0, ""); MAX_HELD],
+        len: 0,
+    }));
+
+    static HELD: [PerHart; MAX_HARTS] = [EMPTY; MAX_HARTS];
+
+    // the graph of the orders seen so far, it can not be guarded by a
+    // checked lock itself
+    struct Graph {
+        after: BTreeMap<&'static str, BTreeSet<&'static str>>,
+        reported: BTreeSet<(&'static str, &'static str)>,
+        // the addresses of the locks held by threads which have switched out
+        parked: Vec<usize>,
+    }
+
+    // guarded by GRAPH_LOCKED
+    struct GraphCell(UnsafeCell<Option<Graph>>);
+
+    unsafe impl Sync for GraphCell {}
+
+    static GRAPH_LOCKED: AtomicBool = AtomicBool::new(false);
+    static GRAPH: GraphCell = GraphCell(UnsafeCell::new(None));
+
+    fn with_graph<R>(f: impl FnOnce(&mut Graph) -> R) -> R {
+        while GRAPH_LOCKED
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let graph = unsafe {
+            (*GRAPH.0.get()).get_or_insert_with(|| Graph {
+                after: BTreeMap::new(),
+                reported: BTreeSet::new(),
+                parked: Vec::new(),
+            })
+        };
+        let result = f(graph);
+        GRAPH_LOCKED.store(false, Ordering::Release);
+        result
+    }
+
+    fn held() -> &'static mut Held {
+        unsafe { &mut *HELD[hart_id()].0.get() }
+    }
+
+    impl Graph {
+        // Whether class to comes after class from, through any path.
+        fn reaches(&self, from: &'static str, to: &'static str) -> bool {
+            let mut seen = BTreeSet::new();
+            let mut stack = Vec::from([from]);
+            while let Some(class) = stack.pop() {
+                if class == to {
+                    return true;
+                }
+                if seen.insert(class) {
+                    if let Some(after) = self.after.get(class) {
+                        stack.extend(after.iter().copied());
+                    }
+                }
+            }
+            false
+        }
+    }
+
+    pub fn acquire(addr: usize, class: &'static str) {
+        let held = held();
+        let locks = &held.locks[..held.len];
+        if let Some(&(_, class)) = locks.iter().find(|&&(a, _)| a == addr) {
+            panic!("lock of {} taken again on hart {}", class, hart_id());
+        }
+        let mut inversions = Vec::new();
+        with_graph(|graph| {
+            for &(_, before) in locks.iter().filter(|&&(_, c)| c != class) {
+                if graph.after.get(before).map_or(false, |s| s.contains(class)) {
+                    continue;
+                }
+                if graph.reaches(class, before) && graph.reported.insert((before, class)) {
+                    inversions.push(before);
+                }
+                graph.after.entry(before).or_default().insert(class);
+            }
+        });
+        // printing takes a lock, nothing is recorded as held yet
+        for before in inversions {
+            error!(
+                "[kernel] lock order inversion on hart {}: {} taken while holding {}, \
+                 the other order has been seen",
+                hart_id(),
+                class,
+                before
+            );
+        }
+        let held = self::held();
+        assert!(held.len < MAX_HELD, "too many locks held");
+        held.locks[held.len] = (addr, class);
+        held.len += 1;
+    }
+
+    pub fn release(addr: usize) {
+        let held = held();
+        // the guards may be dropped in any order
+        if let Some(i) = held.locks[..held.len].iter().rposition(|&(a, _)| a == addr) {
+            held.locks.copy_within(i + 1..held.len, i);
+            held.len -= 1;
+            return;
+        }
+        // taken before the thread switched out, possibly on another hart
+        with_graph(|graph| {
+            if let Some(i) = graph.parked.iter().rposition(|&a| a == addr) {
+                graph.parked.swap_remove(i);
+            }
+        });
+    }
+
+    pub fn park_held_locks() {
+        let held = held();
+        if held.len == 0 {
+            return;
+        }
+        // the hart runs another thread next, which holds none of them
+        with_graph(|graph| {
+            graph
+                .parked
+                .extend(held.locks[..held.len].iter().map(|&(a, _)| a))
+        });
+        held.len = 0;
+    }
+
+    pub fn might_sleep(what: &'static str) {
+        let held = held();
+        if held.len > 0 {
+            error!(
+                "[kernel] {} may sleep on hart {} while holding the lock of {}",
+                what,
+                hart_id(),
+                held.locks[held.len - 1].1
+            );
+        }
+    }
+}
+
+#[cfg(not(debug_assertions))]
+mod imp {
+    #[inline(always)]
+    pub fn acquire(_addr: usize, _class: &'static str) {}
+
+    #[inline(always)]
+    pub fn release(_addr: usize) {}
+
+    #[inline(always)]
+    pub fn might_sleep(_what: &'static str) {}
+
+    #[inline(always)]
+    pub fn park_held_locks() {}
+}
+
+/// Record that the lock at addr, guarding data of the class, is about to be
+/// taken by this hart.
+pub use imp::acquire;
+/// Report if this hart holds a spin lock, before something that may sleep.
+pub use imp::might_sleep;
+/// Hand the locks this hart holds over to the thread switching out, before
+/// the hart switches to another thread.
+pub use imp::park_held_locks;
+/// Record that the lock at addr has been released.
+pub use imp::release;
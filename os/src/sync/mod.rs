@@ -1,3 +1,19 @@
+mod condvar;
+mod deadlock;
+mod lockdep;
+mod mutex;
+mod rwlock;
+mod semaphore;
+mod sleep_mutex;
+mod spin;
 mod up;
 
+pub use condvar::Condvar;
+pub use deadlock::{DeadlockDetector, Resource};
+pub use lockdep::park_held_locks;
+pub use mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use rwlock::RwLock;
+pub use semaphore::Semaphore;
+pub use sleep_mutex::SleepMutex;
+pub use spin::{SpinLock, SpinLockGuard, SpinNoIrq};
 pub use up::UPSafeCell;
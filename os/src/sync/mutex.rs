@@ -0,0 +1,88 @@
+use super::SpinLock;
+use crate::task::{suspend_current_and_run_next, WaitQueue};
+
+pub trait Mutex: Sync + Send {
+    fn lock(&self);
+    /// Return false if the mutex is not locked.
+    fn unlock(&self) -> bool;
+}
+
+// a mutex that yields the cpu until it gets the lock
+pub struct MutexSpin {
+    locked: SpinLock<bool>,
+}
+
+impl MutexSpin {
+    pub fn new() -> Self {
+        Self {
+            locked: SpinLock::new(false),
+        }
+    }
+}
+
+impl Mutex for MutexSpin {
+    fn lock(&self) {
+        loop {
+            let mut locked = self.locked.lock();
+            if *locked {
+                drop(locked);
+                suspend_current_and_run_next();
+                continue;
+            } else {
+                *locked = true;
+                return;
+            }
+        }
+    }
+
+    fn unlock(&self) -> bool {
+        let mut locked = self.locked.lock();
+        if !*locked {
+            return false;
+        }
+        *locked = false;
+        true
+    }
+}
+
+// a mutex that puts the waiters to sleep, the lock is handed over to the
+// first waiter on unlock
+pub struct MutexBlocking {
+    locked: SpinLock<bool>,
+    wait_queue: WaitQueue,
+}
+
+impl MutexBlocking {
+    pub fn new() -> Self {
+        Self {
+            locked: SpinLock::new(false),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    fn lock(&self) {
+        let mut locked = self.locked.lock();
+        if *locked {
+            // the mutex is still locked when we are woken up, it is ours now
+            self.wait_queue.wait_with(|| {
+                drop(locked);
+                true
+            });
+        } else {
+            *locked = true;
+        }
+    }
+
+    fn unlock(&self) -> bool {
+        let mut locked = self.locked.lock();
+        if !*locked {
+            return false;
+        }
+        if !self.wait_queue.wake_one() {
+            *locked = false;
+        }
+        true
+    }
+}
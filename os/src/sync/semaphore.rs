@@ -0,0 +1,36 @@
+use super::SpinLock;
+use crate::task::WaitQueue;
+
+// a counting semaphore, a negative count is the number of waiters
+pub struct Semaphore {
+    pub count: SpinLock<isize>,
+    pub wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    pub fn new(res_count: usize) -> Self {
+        Self {
+            count: SpinLock::new(res_count as isize),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    pub fn up(&self) {
+        let mut count = self.count.lock();
+        *count += 1;
+        if *count <= 0 {
+            self.wait_queue.wake_one();
+        }
+    }
+
+    pub fn down(&self) {
+        let mut count = self.count.lock();
+        *count -= 1;
+        if *count < 0 {
+            self.wait_queue.wait_with(|| {
+                drop(count);
+                true
+            });
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+use super::{lockdep, SpinLock};
+use crate::task::WaitQueue;
+
+/// A lock for the kernel data which may be held for long, e.g. across disk
+/// I/O, the threads waiting for it sleep instead of spinning. It may only be
+/// taken by a thread, never by the idle loop or with a spin lock held.
+pub struct SleepMutex<T> {
+    locked: SpinLock<bool>,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SleepMutex<T> {}
+unsafe impl<T: Send> Send for SleepMutex<T> {}
+
+impl<T> SleepMutex<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            locked: SpinLock::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Sleep until the lock is ours, it is released when the guard is dropped.
+    pub fn lock(&self) -> SleepMutexGuard<'_, T> {
+        lockdep::might_sleep("SleepMutex::lock");
+        loop {
+            let mut locked = self.locked.lock();
+            if !*locked {
+                *locked = true;
+                return SleepMutexGuard { mutex: self };
+            }
+            // another thread may take it before us once woken up
+            self.waiters.wait_with(|| {
+                drop(locked);
+                true
+            });
+        }
+    }
+}
+
+pub struct SleepMutexGuard<'a, T> {
+    mutex: &'a SleepMutex<T>,
+}
+
+impl<T> Deref for SleepMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for SleepMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for SleepMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut locked = self.mutex.locked.lock();
+        *locked = false;
+        self.mutex.waiters.wake_one();
+    }
+}
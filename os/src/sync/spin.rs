@@ -0,0 +1,141 @@
+use core::any::type_name;
+use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use riscv::register::sstatus;
+
+use super::lockdep;
+
+/// A lock for the data shared by the harts, waiting for it spins.
+///
+/// The kernel runs with interrupts disabled, so a hart never takes a lock
+/// it is already holding from an interrupt handler, see `SpinNoIrq` for the
+/// data an interrupt handler may touch otherwise. Taking it twice on the
+/// same hart deadlocks, it panics with debug assertions.
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+unsafe impl<T: Send> Send for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Spin until the lock is ours, it is released when the guard is dropped.
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        lockdep::acquire(self as *const _ as usize, type_name::<T>());
+        self.raw_lock();
+        SpinLockGuard { lock: self }
+    }
+
+    fn raw_lock(&self) {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // wait without writing the cache line
+            while self.locked.load(Ordering::Relaxed) {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    fn raw_unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+        lockdep::release(self as *const _ as usize);
+    }
+}
+
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.raw_unlock();
+    }
+}
+
+/// A `SpinLock` which also disables the interrupts of the hart while it is
+/// held, for the data an interrupt handler may touch if the kernel ever runs
+/// with interrupts enabled. They are enabled again when the guard is dropped
+/// if they were before.
+pub struct SpinNoIrq<T> {
+    lock: SpinLock<T>,
+}
+
+impl<T> SpinNoIrq<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            lock: SpinLock::new(value),
+        }
+    }
+
+    pub fn lock(&self) -> SpinNoIrqGuard<'_, T> {
+        let irq_enabled = sstatus::read().sie();
+        unsafe {
+            sstatus::clear_sie();
+        }
+        SpinNoIrqGuard {
+            guard: ManuallyDrop::new(self.lock.lock()),
+            irq_enabled,
+        }
+    }
+}
+
+pub struct SpinNoIrqGuard<'a, T> {
+    guard: ManuallyDrop<SpinLockGuard<'a, T>>,
+    irq_enabled: bool,
+}
+
+impl<T> Deref for SpinNoIrqGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for SpinNoIrqGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for SpinNoIrqGuard<'_, T> {
+    fn drop(&mut self) {
+        // release the lock before an interrupt handler may want it
+        unsafe {
+            ManuallyDrop::drop(&mut self.guard);
+        }
+        if self.irq_enabled {
+            unsafe {
+                sstatus::set_sie();
+            }
+        }
+    }
+}
@@ -3,7 +3,8 @@ use core::cell::{RefCell, RefMut};
 /// Wrap a static data structure inside it so that we are
 /// able to access it without any `unsafe`.
 ///
-/// We should only use it in uniprocessor.
+/// We should only use it for the data of a single hart, see `SpinLock`
+/// for the data shared by the harts.
 ///
 /// In order to get mutable reference of inner data, call
 /// `exclusive_access`.
@@ -15,8 +16,8 @@ pub struct UPSafeCell<T> {
 unsafe impl<T> Sync for UPSafeCell<T> {}
 
 impl<T> UPSafeCell<T> {
-    /// User is responsible to guarantee that inner struct is only used in
-    /// uniprocessor.
+    /// User is responsible to guarantee that inner struct is only used by
+    /// one hart.
     pub unsafe fn new(value: T) -> Self {
         Self {
             inner: RefCell::new(value),
@@ -1,79 +1,324 @@
 use alloc::sync::Arc;
 use core::mem::size_of;
+use easy_fs::Inode;
 
-use crate::fs::{linkat, make_pipe, open_file, unlinkat, OpenFlags, Stat};
-use crate::mm::{translated_byte_buffers, translated_refmut, translated_str, UserBuffer};
-use crate::task::{current_task, current_user_token};
+use super::process::TimeSpec;
+use super::{copy_from_user, copy_to_user};
+use crate::errno::SysError;
+use crate::fs::{
+    chmod, chown, linkat, make_pipe, mkdir, open_file, path_of, permitted, readlinkat, renameat,
+    resolve, rmdir, stat_of, symlinkat, tty_foreground, tty_session, tty_set_foreground,
+    tty_set_session, unlinkat, File, FileDescriptor, HeldInode, OpenFlags, Stat, MAY_EXEC,
+    MAY_WRITE,
+};
+use crate::mm::{
+    translated_byte_buffers, translated_str, user_buffer_mapped, user_buffer_writable, UserBuffer,
+};
+use crate::task::{
+    current_prepare_user_read, current_prepare_user_write, current_process, current_user_token,
+    processes_in_group, Credential,
+};
+use easy_fs::DiskTime;
 
 const AT_FDCWD: i32 = -100;
+const AT_REMOVEDIR: u32 = 0x200;
+const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
 
 const FD_STDIN: usize = 0;
 const FD_STDOUT: usize = 1;
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return SysError::EBADF.into();
     }
-    if let Some(file) = &inner.fd_table[fd] {
-        if !file.writable() {
-            return -1;
+    if let Some(fd) = &inner.fd_table[fd] {
+        if !fd.file.writable() {
+            return SysError::EBADF.into();
         }
-        let file = file.clone();
+        let file = fd.file.clone();
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
+        // the file may sleep, which must not keep the process alive
+        drop(process);
+        if file.nonblocking() && !file.write_ready() {
+            return SysError::EAGAIN.into();
+        }
+        current_prepare_user_read(buf, len);
+        if !user_buffer_mapped(token, buf, len) {
+            return SysError::EFAULT.into();
+        }
         file.write(UserBuffer::new(translated_byte_buffers(token, buf, len))) as isize
     } else {
-        -1
+        SysError::EBADF.into()
     }
 }
 
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return SysError::EBADF.into();
     }
-    if let Some(file) = &inner.fd_table[fd] {
-        let file = file.clone();
+    if let Some(fd) = &inner.fd_table[fd] {
+        let file = fd.file.clone();
         if !file.readable() {
-            return -1;
+            return SysError::EBADF.into();
         }
         // release current task TCB manually to avoid multi-borrow
         drop(inner);
+        // the file may sleep, which must not keep the process alive
+        drop(process);
+        if file.nonblocking() && !file.read_ready() {
+            return SysError::EAGAIN.into();
+        }
+        current_prepare_user_write(buf, len);
+        if !user_buffer_writable(token, buf, len) {
+            return SysError::EFAULT.into();
+        }
         file.read(UserBuffer::new(translated_byte_buffers(token, buf, len))) as isize
     } else {
-        -1
+        SysError::EBADF.into()
+    }
+}
+
+// the file fd refers to in the current process
+fn file_of(fd: usize) -> Result<Arc<dyn File + Send + Sync>, SysError> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    match inner.fd_table.get(fd) {
+        Some(Some(fd)) => Ok(fd.file.clone()),
+        _ => Err(SysError::EBADF),
+    }
+}
+
+// Move the offset of fd as whence says, return the new offset.
+// Return -ESPIPE if fd is not a regular file or a directory, or -EINVAL if
+// whence is unknown or the offset would be negative.
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    let file = match file_of(fd) {
+        Ok(file) => file,
+        Err(err) => return err.into(),
+    };
+    match file.seek(offset, whence) {
+        Ok(offset) => offset as isize,
+        Err(err) => err.into(),
+    }
+}
+
+// Read from offset of fd, the offset of the file is left as it is.
+pub fn sys_pread64(fd: usize, buf: *const u8, len: usize, offset: isize) -> isize {
+    let token = current_user_token();
+    let file = match file_of(fd) {
+        Ok(file) => file,
+        Err(err) => return err.into(),
+    };
+    if !file.readable() {
+        return SysError::EBADF.into();
+    }
+    if offset < 0 {
+        return SysError::EINVAL.into();
+    }
+    current_prepare_user_write(buf, len);
+    if !user_buffer_writable(token, buf, len) {
+        return SysError::EFAULT.into();
+    }
+    let buf = UserBuffer::new(translated_byte_buffers(token, buf, len));
+    match file.read_at(offset as usize, buf) {
+        Ok(size) => size as isize,
+        Err(err) => err.into(),
+    }
+}
+
+// Write at offset of fd, the offset of the file is left as it is.
+pub fn sys_pwrite64(fd: usize, buf: *const u8, len: usize, offset: isize) -> isize {
+    let token = current_user_token();
+    let file = match file_of(fd) {
+        Ok(file) => file,
+        Err(err) => return err.into(),
+    };
+    if !file.writable() {
+        return SysError::EBADF.into();
+    }
+    if offset < 0 {
+        return SysError::EINVAL.into();
+    }
+    current_prepare_user_read(buf, len);
+    if !user_buffer_mapped(token, buf, len) {
+        return SysError::EFAULT.into();
+    }
+    let buf = UserBuffer::new(translated_byte_buffers(token, buf, len));
+    match file.write_at(offset as usize, buf) {
+        Ok(size) => size as isize,
+        Err(err) => err.into(),
+    }
+}
+
+// Fill buf with linux_dirent64 records of the directory fd, continuing from
+// the last call. Return 0 at the end of the directory, -ENOTDIR if fd is not
+// a directory, or -EINVAL if buf can not hold the next record.
+pub fn sys_getdents64(fd: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let file = match file_of(fd) {
+        Ok(file) => file,
+        Err(err) => return err.into(),
+    };
+    current_prepare_user_write(buf, len);
+    if !user_buffer_writable(token, buf, len) {
+        return SysError::EFAULT.into();
+    }
+    let buf = UserBuffer::new(translated_byte_buffers(token, buf, len));
+    match file.getdents(buf) {
+        Ok(size) => size as isize,
+        Err(err) => err.into(),
     }
 }
 
+// Cut or extend the regular file fd to len bytes, freeing the blocks past the
+// new end. Return -EINVAL if fd is not open for writing or len is negative.
+pub fn sys_ftruncate(fd: usize, len: isize) -> isize {
+    let file = match file_of(fd) {
+        Ok(file) => file,
+        Err(err) => return err.into(),
+    };
+    if !file.writable() || len < 0 {
+        return SysError::EINVAL.into();
+    }
+    match file.truncate(len as usize) {
+        Ok(()) => 0,
+        Err(err) => err.into(),
+    }
+}
+
+// the directory that a relative path given to the *at syscalls starts from
+fn dir_of(dirfd: i32) -> Result<Arc<Inode>, SysError> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    if dirfd == AT_FDCWD {
+        return Ok(Arc::clone(&inner.cwd));
+    }
+    let file = match inner.fd_table.get(dirfd as usize) {
+        Some(Some(fd)) => fd.file.clone(),
+        _ => return Err(SysError::EBADF),
+    };
+    match file.inode() {
+        Some(inode) if inode.is_dir() => Ok(inode),
+        _ => Err(SysError::ENOTDIR),
+    }
+}
+
+// the identity of the current process
+fn current_cred() -> Credential {
+    current_process().inner_exclusive_access().cred
+}
+
+// open takes no mode, the files it creates are rw-r--r--
+const DEFAULT_MODE: u32 = 0o644;
+
 pub fn sys_open(path: *const u8, flags: u32) -> isize {
-    let task = current_task().unwrap();
-    let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = task.inner_exclusive_access();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
+    sys_openat(AT_FDCWD, path, flags, DEFAULT_MODE)
+}
+
+pub fn sys_openat(dirfd: i32, path: *const u8, flags: u32, mode: u32) -> isize {
+    let process = current_process();
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    let flags = match OpenFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => return SysError::EINVAL.into(),
+    };
+    let dir = match dir_of(dirfd) {
+        Ok(dir) => dir,
+        Err(err) => return err.into(),
+    };
+    match open_file(&dir, path.as_str(), flags, mode, &current_cred()) {
+        Ok(inode) => {
+            let mut inner = process.inner_exclusive_access();
+            let fd = inner.alloc_fd();
+            inner.fd_table[fd] = Some(FileDescriptor::new(
+                inode,
+                flags.contains(OpenFlags::CLOEXEC),
+            ));
+            fd as isize
+        }
+        Err(err) => err.into(),
+    }
+}
+
+pub fn sys_mkdirat(dirfd: i32, path: *const u8, mode: u32) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    match dir_of(dirfd) {
+        Ok(dir) => mkdir(&dir, &path, mode, &current_cred()),
+        Err(err) => err.into(),
+    }
+}
+
+pub fn sys_chdir(path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    let cred = current_cred();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    match resolve(&inner.cwd, &path, true, &cred) {
+        Ok(dir) if !dir.is_dir() => SysError::ENOTDIR.into(),
+        // the new working directory must be searchable too
+        Ok(dir) if !permitted(&dir, &cred, MAY_EXEC) => SysError::EACCES.into(),
+        Ok(dir) => {
+            inner.cwd = HeldInode::new(dir);
+            0
+        }
+        Err(err) => err.into(),
+    }
+}
+
+// Return the length of the path including the trailing '\0'.
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let cwd = Arc::clone(&current_process().inner_exclusive_access().cwd);
+    let path = match path_of(&cwd) {
+        Some(path) => path,
+        // the directory has been removed
+        None => return SysError::ENOENT.into(),
+    };
+    if path.len() + 1 > len {
+        return SysError::ERANGE.into();
+    }
+    current_prepare_user_write(buf, path.len() + 1);
+    if !user_buffer_writable(token, buf, path.len() + 1) {
+        return SysError::EFAULT.into();
+    }
+    let user_buf = UserBuffer::new(translated_byte_buffers(token, buf, path.len() + 1));
+    for (byte_ref, byte) in user_buf.into_iter().zip(path.bytes().chain(Some(0))) {
+        unsafe {
+            *byte_ref = byte;
+        }
     }
+    (path.len() + 1) as isize
 }
 
 pub fn sys_close(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     // overflow
     if fd >= inner.fd_table.len() {
-        return -1;
+        return SysError::EBADF.into();
     }
     // is busy
     if inner.fd_table[fd].is_none() {
-        return -1;
+        return SysError::EBADF.into();
     }
     inner.fd_table[fd].take();
     0
@@ -86,74 +331,464 @@ pub fn sys_linkat(
     newpath: *const u8,
     flags: u32,
 ) -> isize {
-    assert_eq!(olddirfd, AT_FDCWD);
-    assert_eq!(newdirfd, AT_FDCWD);
-    assert_eq!(flags, 0);
+    // both paths must be relative to the same directory for now
+    if olddirfd != newdirfd || flags != 0 {
+        return SysError::EINVAL.into();
+    }
     let token = current_user_token();
-    let oldpath = translated_str(token, oldpath);
-    let newpath = translated_str(token, newpath);
-    linkat(&oldpath, &newpath, flags)
+    let oldpath = match translated_str(token, oldpath) {
+        Some(oldpath) => oldpath,
+        None => return SysError::EFAULT.into(),
+    };
+    let newpath = match translated_str(token, newpath) {
+        Some(newpath) => newpath,
+        None => return SysError::EFAULT.into(),
+    };
+    match dir_of(olddirfd) {
+        Ok(dir) => linkat(&dir, &oldpath, &newpath, &current_cred()),
+        Err(err) => err.into(),
+    }
 }
 
-pub fn sys_unlinkat(dirfd: i32, path: *const u8, flags: u32) -> isize {
-    assert_eq!(dirfd, AT_FDCWD);
-    assert_eq!(flags, 0);
+pub fn sys_symlinkat(target: *const u8, newdirfd: i32, linkpath: *const u8) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
-    unlinkat(&path, flags)
+    let target = match translated_str(token, target) {
+        Some(target) => target,
+        None => return SysError::EFAULT.into(),
+    };
+    let linkpath = match translated_str(token, linkpath) {
+        Some(linkpath) => linkpath,
+        None => return SysError::EFAULT.into(),
+    };
+    match dir_of(newdirfd) {
+        Ok(dir) => symlinkat(&target, &dir, &linkpath, &current_cred()),
+        Err(err) => err.into(),
+    }
 }
 
-pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
-    let task = current_task().unwrap();
-    let inner = task.inner_exclusive_access();
-    if fd >= inner.fd_table.len() {
-        return -1;
+// Copy the target of the symbolic link at path into buf without a trailing
+// '\0', cut to len bytes. Return the size copied, or -EINVAL if path is not a
+// symbolic link.
+pub fn sys_readlinkat(dirfd: i32, path: *const u8, buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    if len == 0 {
+        return SysError::EINVAL.into();
     }
-    // translate buffer in user mode
-    let len = size_of::<Stat>();
-    // release current task TCB manually to avoid multi-borrow
-    drop(inner);
-    let mut ts_buffers = translated_byte_buffers(current_user_token(), st.cast(), len);
-    // At least one buf
-    if ts_buffers.len() <= 0 {
-        return -1;
-    }
-    let st: *mut Stat = ts_buffers[0].as_mut_ptr().cast();
-    // re-access to current task TCB
-    let inner = task.inner_exclusive_access();
-    if let Some(file) = &inner.fd_table[fd] {
-        let file = file.clone();
-        let stat = file.fstat();
+    let target = match dir_of(dirfd).and_then(|dir| readlinkat(&dir, &path, &current_cred())) {
+        Ok(target) => target,
+        Err(err) => return err.into(),
+    };
+    let size = target.len().min(len);
+    current_prepare_user_write(buf, size);
+    if !user_buffer_writable(token, buf, size) {
+        return SysError::EFAULT.into();
+    }
+    let user_buf = UserBuffer::new(translated_byte_buffers(token, buf, size));
+    for (byte_ref, byte) in user_buf.into_iter().zip(target.bytes()) {
         unsafe {
-            *st = stat;
+            *byte_ref = byte;
+        }
+    }
+    size as isize
+}
+
+// fstat on path, which is not followed if it is a symbolic link and flags has
+// AT_SYMLINK_NOFOLLOW.
+pub fn sys_fstatat(dirfd: i32, path: *const u8, st: *mut Stat, flags: u32) -> isize {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return SysError::EINVAL.into();
+    }
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+    let inode = match dir_of(dirfd).and_then(|dir| resolve(&dir, &path, follow, &current_cred())) {
+        Ok(inode) => inode,
+        Err(err) => return err.into(),
+    };
+    if !copy_to_user(st, &stat_of(&inode)) {
+        return SysError::EFAULT.into();
+    }
+    0
+}
+
+pub fn sys_renameat2(
+    olddirfd: i32,
+    oldpath: *const u8,
+    newdirfd: i32,
+    newpath: *const u8,
+    flags: u32,
+) -> isize {
+    let token = current_user_token();
+    let oldpath = match translated_str(token, oldpath) {
+        Some(oldpath) => oldpath,
+        None => return SysError::EFAULT.into(),
+    };
+    let newpath = match translated_str(token, newpath) {
+        Some(newpath) => newpath,
+        None => return SysError::EFAULT.into(),
+    };
+    let old_dir = match dir_of(olddirfd) {
+        Ok(dir) => dir,
+        Err(err) => return err.into(),
+    };
+    match dir_of(newdirfd) {
+        Ok(new_dir) => renameat(
+            &old_dir,
+            &oldpath,
+            &new_dir,
+            &newpath,
+            flags,
+            &current_cred(),
+        ),
+        Err(err) => err.into(),
+    }
+}
+
+pub fn sys_unlinkat(dirfd: i32, path: *const u8, flags: u32) -> isize {
+    if flags & !AT_REMOVEDIR != 0 {
+        return SysError::EINVAL.into();
+    }
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    let dir = match dir_of(dirfd) {
+        Ok(dir) => dir,
+        Err(err) => return err.into(),
+    };
+    if flags & AT_REMOVEDIR != 0 {
+        rmdir(&dir, &path, &current_cred())
+    } else {
+        unlinkat(&dir, &path, &current_cred())
+    }
+}
+
+// Set the permission bits of path, following a symbolic link. Return -EPERM
+// if the caller is neither the owner nor root.
+pub fn sys_fchmodat(dirfd: i32, path: *const u8, mode: u32, flags: u32) -> isize {
+    if flags != 0 {
+        return SysError::EINVAL.into();
+    }
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    match dir_of(dirfd).and_then(|dir| resolve(&dir, &path, true, &current_cred())) {
+        Ok(inode) => chmod(&inode, mode, &current_cred()),
+        Err(err) => err.into(),
+    }
+}
+
+// Set the owner and the group of path, -1 leaves one as it is. The link
+// itself is changed with AT_SYMLINK_NOFOLLOW. Return -EPERM unless the caller
+// is root, or the owner moving it to its own group.
+pub fn sys_fchownat(dirfd: i32, path: *const u8, uid: u32, gid: u32, flags: u32) -> isize {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return SysError::EINVAL.into();
+    }
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+    let inode = match dir_of(dirfd).and_then(|dir| resolve(&dir, &path, follow, &current_cred())) {
+        Ok(inode) => inode,
+        Err(err) => return err.into(),
+    };
+    let uid = if uid == u32::MAX { None } else { Some(uid) };
+    let gid = if gid == u32::MAX { None } else { Some(gid) };
+    chown(&inode, uid, gid, &current_cred())
+}
+
+// the nsec of a time given to utimensat, for the current time or none
+const UTIME_NOW: usize = (1 << 30) - 1;
+const UTIME_OMIT: usize = (1 << 30) - 2;
+
+// Set the access and modification times of path, or of the file dirfd if path
+// is null. Null times sets both to now. Return -EPERM if times are given and
+// the caller is neither the owner nor root, or -EACCES if it may not write the
+// file either when setting them to now.
+pub fn sys_utimensat(
+    dirfd: i32,
+    path: *const u8,
+    times: *const [TimeSpec; 2],
+    flags: u32,
+) -> isize {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return SysError::EINVAL.into();
+    }
+    let inode = if path.is_null() {
+        match file_of(dirfd as usize).map(|file| file.inode()) {
+            Ok(Some(inode)) => inode,
+            Ok(None) => return SysError::EINVAL.into(),
+            Err(err) => return err.into(),
+        }
+    } else {
+        let path = match translated_str(current_user_token(), path) {
+            Some(path) => path,
+            None => return SysError::EFAULT.into(),
+        };
+        let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+        match dir_of(dirfd).and_then(|dir| resolve(&dir, &path, follow, &current_cred())) {
+            Ok(inode) => inode,
+            Err(err) => return err.into(),
         }
-        0
+    };
+    let times = if times.is_null() {
+        [TimeSpec {
+            sec: 0,
+            nsec: UTIME_NOW,
+        }; 2]
     } else {
-        -1
+        match copy_from_user(times) {
+            Some(times) => times,
+            None => return SysError::EFAULT.into(),
+        }
+    };
+    let now = DiskTime::now();
+    let mut stamps = [None; 2];
+    for (stamp, time) in stamps.iter_mut().zip(times.iter()) {
+        *stamp = match time.nsec {
+            UTIME_OMIT => None,
+            UTIME_NOW => Some(now),
+            nsec if nsec < 1_000_000_000 => Some(DiskTime {
+                sec: time.sec as u32,
+                nsec: nsec as u32,
+            }),
+            _ => return SysError::EINVAL.into(),
+        };
     }
+    if stamps.iter().all(|stamp| stamp.is_none()) {
+        return 0;
+    }
+    let cred = current_cred();
+    if !cred.is_privileged() && cred.euid != inode.uid() {
+        let explicit = times
+            .iter()
+            .any(|time| time.nsec != UTIME_NOW && time.nsec != UTIME_OMIT);
+        if explicit {
+            return SysError::EPERM.into();
+        }
+        if !permitted(&inode, &cred, MAY_WRITE) {
+            return SysError::EACCES.into();
+        }
+    }
+    inode.set_times(stamps[0], stamps[1]);
+    0
+}
+
+pub fn sys_fstat(fd: usize, st: *mut Stat) -> isize {
+    let file = match file_of(fd) {
+        Ok(file) => file,
+        Err(err) => return err.into(),
+    };
+    // the larger Stat may cross a page boundary
+    if !copy_to_user(st, &file.fstat()) {
+        return SysError::EFAULT.into();
+    }
+    0
 }
 
 pub fn sys_pipe(pipe: *mut usize) -> isize {
-    let task = current_task().unwrap();
+    let process = current_process();
     let token = current_user_token();
-    let mut inner = task.inner_exclusive_access();
+    current_prepare_user_write(pipe.cast(), 2 * size_of::<usize>());
+    if !user_buffer_writable(token, pipe.cast(), 2 * size_of::<usize>()) {
+        return SysError::EFAULT.into();
+    }
+    let mut inner = process.inner_exclusive_access();
     let (pipe_read, pipe_write) = make_pipe();
     let read_fd = inner.alloc_fd();
-    inner.fd_table[read_fd] = Some(pipe_read);
+    inner.fd_table[read_fd] = Some(FileDescriptor::new(pipe_read, false));
     let write_fd = inner.alloc_fd();
-    inner.fd_table[write_fd] = Some(pipe_write);
-    *translated_refmut(token, pipe) = read_fd;
-    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
+    inner.fd_table[write_fd] = Some(FileDescriptor::new(pipe_write, false));
+    drop(inner);
+    // the two may sit on different pages
+    if !copy_to_user(pipe.cast::<[usize; 2]>(), &[read_fd, write_fd]) {
+        return SysError::EFAULT.into();
+    }
     0
 }
 
 pub fn sys_dup(fd: usize) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     if fd >= inner.fd_table.len() || inner.fd_table[fd].is_none() {
-        return -1;
+        return SysError::EBADF.into();
     }
     let new_fd = inner.alloc_fd();
-    inner.fd_table[new_fd] = Some(Arc::clone(inner.fd_table[fd].as_ref().unwrap()));
+    // the new descriptor is not close-on-exec
+    let file = inner.fd_table[fd].as_ref().unwrap().file.clone();
+    inner.fd_table[new_fd] = Some(FileDescriptor::new(file, false));
     new_fd as isize
 }
+
+// the fd table grows up to this many descriptors
+const MAX_FD: usize = 1024;
+
+// Make newfd a copy of oldfd, closing the file newfd refers to if any.
+// O_CLOEXEC is the only flag. Return -EINVAL if the two are the same.
+pub fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> isize {
+    let cloexec = match OpenFlags::from_bits(flags) {
+        Some(flags) if (flags - OpenFlags::CLOEXEC).is_empty() => {
+            flags.contains(OpenFlags::CLOEXEC)
+        }
+        _ => return SysError::EINVAL.into(),
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(oldfd) {
+        Some(Some(fd)) => fd.file.clone(),
+        _ => return SysError::EBADF.into(),
+    };
+    if newfd >= MAX_FD {
+        return SysError::EBADF.into();
+    }
+    if oldfd == newfd {
+        return SysError::EINVAL.into();
+    }
+    if newfd >= inner.fd_table.len() {
+        inner.fd_table.resize(newfd + 1, None);
+    }
+    inner.fd_table[newfd] = Some(FileDescriptor::new(file, cloexec));
+    newfd as isize
+}
+
+// duplicate to the lowest free descriptor not below arg
+const F_DUPFD: usize = 0;
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+const F_GETFL: usize = 3;
+const F_SETFL: usize = 4;
+const F_DUPFD_CLOEXEC: usize = 1030;
+// the only descriptor flag
+const FD_CLOEXEC: usize = 1;
+
+// Get or set the flags of the descriptor fd or of its file, or duplicate it.
+// Only O_NONBLOCK of the file status flags can be set.
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let descriptor = match inner.fd_table.get(fd) {
+        Some(Some(descriptor)) => descriptor.clone(),
+        _ => return SysError::EBADF.into(),
+    };
+    match cmd {
+        F_DUPFD | F_DUPFD_CLOEXEC => {
+            if arg >= MAX_FD {
+                return SysError::EINVAL.into();
+            }
+            if arg > inner.fd_table.len() {
+                inner.fd_table.resize(arg, None);
+            }
+            let new_fd = match (arg..inner.fd_table.len()).find(|&i| inner.fd_table[i].is_none()) {
+                Some(new_fd) => new_fd,
+                None => {
+                    inner.fd_table.push(None);
+                    inner.fd_table.len() - 1
+                }
+            };
+            let cloexec = cmd == F_DUPFD_CLOEXEC;
+            inner.fd_table[new_fd] = Some(FileDescriptor::new(descriptor.file, cloexec));
+            new_fd as isize
+        }
+        F_GETFD => {
+            if descriptor.cloexec {
+                FD_CLOEXEC as isize
+            } else {
+                0
+            }
+        }
+        F_SETFD => {
+            inner.fd_table[fd].as_mut().unwrap().cloexec = arg & FD_CLOEXEC != 0;
+            0
+        }
+        F_GETFL => {
+            let file = descriptor.file;
+            let mut flags = match (file.readable(), file.writable()) {
+                (true, true) => OpenFlags::RDWR,
+                (false, true) => OpenFlags::WRONLY,
+                _ => OpenFlags::RDONLY,
+            };
+            if file.nonblocking() {
+                flags |= OpenFlags::NONBLOCK;
+            }
+            if file.appending() {
+                flags |= OpenFlags::APPEND;
+            }
+            flags.bits() as isize
+        }
+        F_SETFL => {
+            descriptor
+                .file
+                .set_nonblocking(arg as u32 & OpenFlags::NONBLOCK.bits() != 0);
+            0
+        }
+        _ => SysError::EINVAL.into(),
+    }
+}
+
+// make the console the controlling terminal of the session of the caller
+const TIOCSCTTY: usize = 0x540E;
+// get the foreground process group of the console
+const TIOCGPGRP: usize = 0x540F;
+// set the foreground process group of the console
+const TIOCSPGRP: usize = 0x5410;
+
+// Control the console behind fd, only the job control requests are supported.
+// Return -ENOTTY if fd is not the console, or -EPERM if the caller is not the
+// session leader for TIOCSCTTY, or not in the session of the console or asks for
+// a group outside it for TIOCSPGRP.
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    match inner.fd_table.get(fd) {
+        Some(Some(fd)) if fd.file.is_tty() => {}
+        Some(Some(_)) => return SysError::ENOTTY.into(),
+        _ => return SysError::EBADF.into(),
+    }
+    let (pid, sid) = (process.getpid(), inner.sid);
+    drop(inner);
+    let pgrp = arg as *mut i32;
+    match cmd {
+        TIOCSCTTY => {
+            if sid != pid {
+                return SysError::EPERM.into();
+            }
+            tty_set_session(sid);
+            0
+        }
+        TIOCGPGRP => {
+            if !copy_to_user(pgrp, &(tty_foreground().unwrap_or(0) as i32)) {
+                return SysError::EFAULT.into();
+            }
+            0
+        }
+        TIOCSPGRP => {
+            let pgid = match copy_from_user(pgrp as *const i32) {
+                Some(pgid) => pgid as usize,
+                None => return SysError::EFAULT.into(),
+            };
+            if tty_session() != Some(sid)
+                || !processes_in_group(pgid)
+                    .iter()
+                    .any(|process| process.inner_exclusive_access().sid == sid)
+            {
+                return SysError::EPERM.into();
+            }
+            tty_set_foreground(pgid);
+            0
+        }
+        _ => SysError::EINVAL.into(),
+    }
+}
@@ -1,25 +1,58 @@
 use crate::config::PAGE_SIZE;
-use crate::mm::{MapPermission, PageTable, VPNRange, VirtAddr};
+use crate::errno::SysError;
+use crate::mm::{MapPermission, VPNRange, VirtAddr};
 use crate::task::{
-    current_memory_set_mmap, current_memory_set_munmap, current_pid, current_user_token,
+    current_memory_set_mmap, current_memory_set_munmap, current_pid, current_process, current_task,
+    overlaps_thread_slots,
 };
 
+// whether [start, end) is kept for the user stacks and trap contexts of the
+// threads, which are mapped by the kernel
+fn reserved(start: usize, end: usize) -> bool {
+    let task = current_task().unwrap();
+    let ustack_base = task
+        .inner_exclusive_access()
+        .res
+        .as_ref()
+        .unwrap()
+        .ustack_base();
+    overlaps_thread_slots(ustack_base, start, end)
+}
+
+// the end of [start, start + len) rounded up to a page, None if the range
+// is empty or wraps around
+fn range_end(start: usize, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let len = len.checked_add(PAGE_SIZE - 1)? / PAGE_SIZE * PAGE_SIZE;
+    start.checked_add(len)
+}
+
 pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
     if (start & (PAGE_SIZE - 1)) != 0 || (prot & !0x7) != 0 || (prot & 0x7) == 0 {
-        return -1;
+        return SysError::EINVAL.into();
     }
 
-    let len = ((len + PAGE_SIZE - 1) / PAGE_SIZE) * PAGE_SIZE;
+    let end = match range_end(start, len) {
+        Some(end) => end,
+        None => return SysError::EINVAL.into(),
+    };
     let start_vpn = VirtAddr::from(start).floor();
-    let end_vpn = VirtAddr::from(start + len).ceil();
+    let end_vpn = VirtAddr::from(end).ceil();
+    if reserved(start, end) {
+        return SysError::EINVAL.into();
+    }
 
-    let page_table_user = PageTable::from_token(current_user_token());
-    // make sure there are no mapped pages in [start..start+len)
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    // make sure there are no mapped pages in [start..start+len), lazy ones included
     for vpn in VPNRange::new(start_vpn, end_vpn) {
-        if let Some(_) = page_table_user.translate(vpn) {
-            return -1;
+        if inner.memory_set.contains_vpn(vpn) {
+            return SysError::EEXIST.into();
         }
     }
+    drop(inner);
     let mut map_perm = MapPermission::U;
     if (prot & 0x1) != 0 {
         map_perm |= MapPermission::R;
@@ -31,31 +64,42 @@ pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
         map_perm |= MapPermission::X;
     }
 
-    match current_memory_set_mmap(VirtAddr::from(start), VirtAddr::from(start + len), map_perm) {
+    match current_memory_set_mmap(VirtAddr::from(start), VirtAddr::from(end), map_perm) {
         Ok(_) => 0,
         Err(e) => {
             error!("[Kernel]: mmap error {}, task id={}", e, current_pid());
-            -1
+            SysError::ENOMEM.into()
         }
     }
 }
 
 pub fn sys_munmap(start: usize, len: usize) -> isize {
     if (start & (PAGE_SIZE - 1)) != 0 {
-        return -1;
+        return SysError::EINVAL.into();
     }
 
-    let len = ((len + PAGE_SIZE - 1) / PAGE_SIZE) * PAGE_SIZE;
+    let end = match range_end(start, len) {
+        Some(end) => end,
+        None => return SysError::EINVAL.into(),
+    };
     let start_vpn = VirtAddr::from(start).floor();
-    let end_vpn = VirtAddr::from(start + len).ceil();
+    let end_vpn = VirtAddr::from(end).ceil();
+    if reserved(start, end) {
+        return SysError::EINVAL.into();
+    }
 
-    let page_table_user = PageTable::from_token(current_user_token());
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
     // make sure there are no unmapped pages in [start..start+len)
     for vpn in VPNRange::new(start_vpn, end_vpn) {
-        if let None = page_table_user.translate(vpn) {
-            return -1;
+        if !inner.memory_set.contains_vpn(vpn) {
+            return SysError::EINVAL.into();
         }
     }
+    drop(inner);
 
-    current_memory_set_munmap(VirtAddr::from(start), VirtAddr::from(start + len))
+    match current_memory_set_munmap(VirtAddr::from(start), VirtAddr::from(end)) {
+        0 => 0,
+        _ => SysError::EINVAL.into(),
+    }
 }
@@ -5,13 +5,21 @@ use core::mem::size_of;
 
 use crate::mm::translated_ref;
 
-use crate::fs::{open_file, OpenFlags};
-use crate::mm::{translated_byte_buffers, translated_refmut, translated_str};
+use super::{copy_from_user, copy_to_user};
+use crate::errno::SysError;
+use crate::fs::{open_file, permitted, File, OpenFlags, MAY_EXEC};
+use crate::mm::{
+    translated_byte_buffers, translated_refmut, translated_str, user_buffer_mapped,
+    user_buffer_writable, MemorySet, UserBuffer, VirtAddr,
+};
 use crate::task::{
-    add_task, current_task, current_user_token, exit_current_and_run_next, pid2task,
-    set_current_prio, suspend_current_and_run_next, SignalAction, SignalFlags, MAX_SIG,
+    add_task, block_current_and_run_next, current_prepare_user_read, current_prepare_user_write,
+    current_process, current_task, current_user_token, exit_current_and_run_next, pid2process,
+    processes_in_group, remove_task, send_signal, suspend_current_and_run_next,
+    ProcessControlBlock, RUsage, RtParams, RtPolicy, SignalAction, SignalFlags, TaskStatus,
+    MAX_MAIL_LEN, MAX_PRIORITY, MAX_RT_PRIORITY, MAX_SIG, MIN_PRIORITY, SIG_IGN,
 };
-use crate::timer::get_time_us;
+use crate::timer::{add_timer, get_time_us};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -20,6 +28,13 @@ pub struct TimeVal {
     pub usec: usize,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
@@ -30,21 +45,207 @@ pub fn sys_yield() -> isize {
     0
 }
 
-pub fn sys_set_priority(prio: usize) -> isize {
-    if (prio as isize) < 2 {
-        -1
+// which of getpriority and setpriority, only a single process is supported
+const PRIO_PROCESS: usize = 0;
+
+// The process with pid, 0 for the caller.
+fn process_of(pid: usize) -> Result<Arc<ProcessControlBlock>, SysError> {
+    if pid == 0 {
+        Ok(current_process())
     } else {
-        set_current_prio(prio);
-        prio as isize
+        pid2process(pid).ok_or(SysError::ESRCH)
+    }
+}
+
+// The process getpriority and setpriority work on, who is its pid or 0 for the caller.
+fn prio_target(which: usize, who: usize) -> Result<Arc<ProcessControlBlock>, SysError> {
+    if which != PRIO_PROCESS {
+        return Err(SysError::EINVAL);
+    }
+    process_of(who)
+}
+
+// Return the priority of the process, its threads share it.
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    let process = match prio_target(which, who) {
+        Ok(process) => process,
+        Err(err) => return err.into(),
+    };
+    let process_inner = process.inner_exclusive_access();
+    match process_inner.tasks.iter().flatten().next() {
+        Some(task) => task.inner_exclusive_access().priority as isize,
+        // a zombie
+        None => SysError::ESRCH.into(),
+    }
+}
+
+// Set the priority of all the threads of the process, the threads it creates later
+// inherit it. Return -EINVAL if prio is out of MIN_PRIORITY..=MAX_PRIORITY, or
+// -EPERM if the caller is not root and the process belongs to another user or
+// prio is higher than its priority.
+pub fn sys_setpriority(which: usize, who: usize, prio: usize) -> isize {
+    if !(MIN_PRIORITY..=MAX_PRIORITY).contains(&prio) {
+        return SysError::EINVAL.into();
+    }
+    let cred = current_process().inner_exclusive_access().cred;
+    let process = match prio_target(which, who) {
+        Ok(process) => process,
+        Err(err) => return err.into(),
+    };
+    let process_inner = process.inner_exclusive_access();
+    if process_inner.thread_count() == 0 {
+        return SysError::ESRCH.into();
+    }
+    if !cred.may_control(&process_inner.cred) {
+        return SysError::EPERM.into();
+    }
+    // only root may give a process more of the cpu than it has
+    let raised = process_inner
+        .tasks
+        .iter()
+        .flatten()
+        .any(|task| prio > task.inner_exclusive_access().priority);
+    if raised && !cred.is_privileged() {
+        return SysError::EPERM.into();
     }
+    for task in process_inner.tasks.iter().flatten() {
+        task.inner_exclusive_access().set_prio(prio);
+    }
+    0
+}
+
+const SCHED_NORMAL: usize = 0;
+const SCHED_FIFO: usize = 1;
+const SCHED_DEADLINE: usize = 6;
+
+// the times are in microseconds
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SchedAttr {
+    pub policy: usize,
+    // fifo only, 1..=MAX_RT_PRIORITY
+    pub priority: usize,
+    pub runtime_us: usize,
+    // deadline only, 0 for the end of the period
+    pub deadline_us: usize,
+    pub period_us: usize,
+}
+
+// Check the attributes and turn them into real-time parameters, None for SCHED_NORMAL.
+fn rt_params(attr: &SchedAttr) -> Result<Option<RtParams>, SysError> {
+    let deadline_us = match attr.deadline_us {
+        0 => attr.period_us,
+        deadline_us => deadline_us,
+    };
+    let policy = match attr.policy {
+        SCHED_NORMAL => return Ok(None),
+        SCHED_FIFO if (1..=MAX_RT_PRIORITY).contains(&attr.priority) => RtPolicy::Fifo,
+        SCHED_DEADLINE if attr.priority == 0 && deadline_us <= attr.period_us => RtPolicy::Deadline,
+        _ => return Err(SysError::EINVAL),
+    };
+    if attr.runtime_us == 0 || attr.runtime_us > deadline_us {
+        return Err(SysError::EINVAL);
+    }
+    Ok(Some(RtParams::new(
+        policy,
+        attr.priority,
+        attr.runtime_us,
+        deadline_us,
+        attr.period_us,
+    )))
+}
+
+// Set the scheduling policy of all the threads of the process pid, 0 for the caller,
+// the threads it creates later inherit it. A fifo or deadline thread runs before the
+// others, and for at most runtime_us in every period_us.
+// Return -EINVAL if the attributes are invalid, or -EPERM if the caller is not root
+// and the process belongs to another user or the policy is fifo or deadline.
+pub fn sys_sched_setattr(pid: usize, attr: *const SchedAttr, flags: usize) -> isize {
+    if flags != 0 {
+        return SysError::EINVAL.into();
+    }
+    let attr = match copy_from_user(attr) {
+        Some(attr) => attr,
+        None => return SysError::EFAULT.into(),
+    };
+    let rt = match rt_params(&attr) {
+        Ok(rt) => rt,
+        Err(err) => return err.into(),
+    };
+    let cred = current_process().inner_exclusive_access().cred;
+    let process = match process_of(pid) {
+        Ok(process) => process,
+        Err(err) => return err.into(),
+    };
+    let process_inner = process.inner_exclusive_access();
+    // a real-time class runs before everyone else, which is for root to grant
+    if !cred.may_control(&process_inner.cred) || rt.is_some() && !cred.is_privileged() {
+        return SysError::EPERM.into();
+    }
+    for task in process_inner.tasks.iter().flatten() {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.rt = rt;
+        // a running thread is charged by its new class from now on
+        task_inner.charged_at_us = get_time_us();
+        let ready = task_inner.task_status == TaskStatus::Ready;
+        drop(task_inner);
+        // move a ready thread to the ready queue of its new class
+        if ready {
+            remove_task(task);
+            add_task(Arc::clone(task));
+        }
+    }
+    0
+}
+
+// Write the scheduling policy of the process pid, 0 for the caller, to attr.
+pub fn sys_sched_getattr(pid: usize, attr: *mut SchedAttr) -> isize {
+    let process = match process_of(pid) {
+        Ok(process) => process,
+        Err(err) => return err.into(),
+    };
+    let process_inner = process.inner_exclusive_access();
+    let rt = match process_inner.tasks.iter().flatten().next() {
+        Some(task) => task.inner_exclusive_access().rt,
+        None => return SysError::ESRCH.into(),
+    };
+    drop(process_inner);
+    let value = match rt {
+        Some(rt) => SchedAttr {
+            policy: match rt.policy {
+                RtPolicy::Fifo => SCHED_FIFO,
+                RtPolicy::Deadline => SCHED_DEADLINE,
+            },
+            priority: rt.priority,
+            runtime_us: rt.runtime_us,
+            deadline_us: rt.deadline_us,
+            period_us: rt.period_us,
+        },
+        None => SchedAttr {
+            policy: SCHED_NORMAL,
+            priority: 0,
+            runtime_us: 0,
+            deadline_us: 0,
+            period_us: 0,
+        },
+    };
+    if !copy_to_user(attr, &value) {
+        return SysError::EFAULT.into();
+    }
+    0
 }
 
 pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
     let len = size_of::<TimeVal>();
-    let mut ts_buffers = translated_byte_buffers(current_user_token(), ts.cast(), len);
+    let token = current_user_token();
+    current_prepare_user_write(ts.cast(), len);
+    if !user_buffer_writable(token, ts.cast(), len) {
+        return SysError::EFAULT.into();
+    }
+    let mut ts_buffers = translated_byte_buffers(token, ts.cast(), len);
     // At least one buf
     if ts_buffers.len() <= 0 {
-        return -1;
+        return SysError::EFAULT.into();
     }
     let us = get_time_us();
     let ts: *mut TimeVal = ts_buffers[0].as_mut_ptr().cast();
@@ -57,129 +258,483 @@ pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
     0
 }
 
+// Block the current thread until the time in req has passed.
+// The sleep is never interrupted, so rem is not written.
+pub fn sys_nanosleep(req: *const TimeSpec, _rem: *mut TimeSpec) -> isize {
+    let req = match copy_from_user(req) {
+        Some(req) => req,
+        None => return SysError::EFAULT.into(),
+    };
+    if req.nsec >= 1_000_000_000 {
+        return SysError::EINVAL.into();
+    }
+    // round up, never wake up too early, and a sleep too long to count ends
+    // at the end of time
+    let expire_us = req
+        .sec
+        .checked_mul(1_000_000)
+        .map_or(usize::MAX, |us| us.saturating_add((req.nsec + 999) / 1000))
+        .saturating_add(get_time_us());
+    // the timer stays until it expires, a wakeup before that only rechecks
+    add_timer(expire_us, current_task().unwrap());
+    while get_time_us() < expire_us {
+        block_current_and_run_next();
+    }
+    0
+}
+
+// the times are in microseconds instead of clock ticks
+#[repr(C)]
+#[derive(Debug)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+// Write the times of the current process and its reaped children to tms,
+// return the microseconds since boot.
+pub fn sys_times(tms: *mut Tms) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let times = Tms {
+        utime: inner.rusage.utime_us,
+        stime: inner.rusage.stime_us,
+        cutime: inner.children_rusage.utime_us,
+        cstime: inner.children_rusage.stime_us,
+    };
+    drop(inner);
+    if !copy_to_user(tms, &times) {
+        return SysError::EFAULT.into();
+    }
+    get_time_us() as isize
+}
+
+const RUSAGE_SELF: isize = 0;
+const RUSAGE_CHILDREN: isize = -1;
+
+pub fn sys_getrusage(who: isize, usage: *mut RUsage) -> isize {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let rusage = match who {
+        RUSAGE_SELF => inner.rusage,
+        RUSAGE_CHILDREN => inner.children_rusage,
+        _ => return SysError::EINVAL.into(),
+    };
+    drop(inner);
+    if !copy_to_user(usage, &rusage) {
+        return SysError::EFAULT.into();
+    }
+    0
+}
+
 pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+    current_process().getpid() as isize
+}
+
+pub fn sys_getuid() -> isize {
+    current_process().inner_exclusive_access().cred.uid as isize
+}
+
+pub fn sys_geteuid() -> isize {
+    current_process().inner_exclusive_access().cred.euid as isize
+}
+
+pub fn sys_getgid() -> isize {
+    current_process().inner_exclusive_access().cred.gid as isize
+}
+
+pub fn sys_getegid() -> isize {
+    current_process().inner_exclusive_access().cred.egid as isize
 }
 
+// Root sets the real and the effective user id, any other process may only
+// set the effective one to its real one, otherwise return -EPERM.
+pub fn sys_setuid(uid: u32) -> isize {
+    if current_process().inner_exclusive_access().cred.set_uid(uid) {
+        0
+    } else {
+        SysError::EPERM.into()
+    }
+}
+
+// The same as setuid for the group ids.
+pub fn sys_setgid(gid: u32) -> isize {
+    if current_process().inner_exclusive_access().cred.set_gid(gid) {
+        0
+    } else {
+        SysError::EPERM.into()
+    }
+}
+
+// Only a single-threaded process can fork, otherwise return -EBUSY.
 pub fn sys_fork() -> isize {
-    let current = current_task().unwrap();
-    let child_task = current.fork();
-    let child_pid = child_task.pid.0;
+    let current = current_process();
+    if current.inner_exclusive_access().thread_count() > 1 {
+        return SysError::EBUSY.into();
+    }
+    let child = current.fork();
+    let child_pid = child.getpid();
+    let task = child.inner_exclusive_access().get_task(0);
     // modify return address in trap context
-    let trap_cx = child_task.inner_exclusive_access().get_trap_cx();
+    let trap_cx = task.inner_exclusive_access().get_trap_cx();
     // return value of child is 0
     trap_cx.x[10] = 0; //x[10] is a0 reg
                        // add task to scheduler queue
-    add_task(child_task);
+    add_task(task);
     child_pid as isize
 }
 
 pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
     let mut args_vec: Vec<String> = Vec::new();
     loop {
-        let arg_str_ptr = *translated_ref(token, args);
+        let arg_str_ptr = match copy_from_user(args) {
+            Some(arg_str_ptr) => arg_str_ptr,
+            None => return SysError::EFAULT.into(),
+        };
         if arg_str_ptr == 0 {
             break;
         }
-        args_vec.push(translated_str(token, arg_str_ptr as *const u8));
+        match translated_str(token, arg_str_ptr as *const u8) {
+            Some(arg) => args_vec.push(arg),
+            None => return SysError::EFAULT.into(),
+        }
         unsafe {
             args = args.add(1);
         }
     }
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
-        let all_data = app_inode.read_all();
-        let task = current_task().unwrap();
-        let argc = args_vec.len();
-        task.exec(all_data.as_slice(), args_vec);
-        // return argc because cx.x[10] will be covered with it later
-        argc as isize
-    } else {
-        -1
+    let process = current_process();
+    if process.inner_exclusive_access().thread_count() > 1 {
+        return SysError::EBUSY.into();
+    }
+    let inner = process.inner_exclusive_access();
+    let (cwd, cred) = (Arc::clone(&inner.cwd), inner.cred);
+    drop(inner);
+    match open_file(&cwd, path.as_str(), OpenFlags::RDONLY, 0, &cred) {
+        Ok(app_inode) if app_inode.is_dir() => SysError::EACCES.into(),
+        Ok(app_inode) if !permitted(&app_inode.inode().unwrap(), &cred, MAY_EXEC) => {
+            SysError::EACCES.into()
+        }
+        Ok(app_inode) => {
+            let inode = app_inode.inode().unwrap();
+            process.inner_exclusive_access().cred =
+                cred.exec_as(inode.perm(), inode.uid(), inode.gid());
+            let all_data = app_inode.read_all();
+            let argc = args_vec.len();
+            process.exec(all_data.as_slice(), args_vec);
+            // return argc because cx.x[10] will be covered with it later
+            argc as isize
+        }
+        Err(err) => err.into(),
+    }
+}
+
+// create a child process from the elf file at path, without fork+exec
+pub fn sys_spawn(path: *const u8) -> isize {
+    let token = current_user_token();
+    let path = match translated_str(token, path) {
+        Some(path) => path,
+        None => return SysError::EFAULT.into(),
+    };
+    let current = current_process();
+    let inner = current.inner_exclusive_access();
+    let (cwd, cred) = (Arc::clone(&inner.cwd), inner.cred);
+    drop(inner);
+    match open_file(&cwd, path.as_str(), OpenFlags::RDONLY, 0, &cred) {
+        Ok(app_inode) if app_inode.is_dir() => SysError::EACCES.into(),
+        Ok(app_inode) if !permitted(&app_inode.inode().unwrap(), &cred, MAY_EXEC) => {
+            SysError::EACCES.into()
+        }
+        Ok(app_inode) => {
+            let inode = app_inode.inode().unwrap();
+            let all_data = app_inode.read_all();
+            let child = current.spawn(all_data.as_slice());
+            child.inner_exclusive_access().cred =
+                cred.exec_as(inode.perm(), inode.uid(), inode.gid());
+            let child_pid = child.getpid();
+            add_task(child.inner_exclusive_access().get_task(0));
+            child_pid as isize
+        }
+        Err(err) => err.into(),
+    }
+}
+
+// return at once instead of waiting for a running child
+const WNOHANG: usize = 1;
+// also report a child which has been stopped by a signal
+const WUNTRACED: usize = 2;
+
+// Write the status of a waited child to status_ptr, a null pointer asks for
+// no status.
+fn put_status(
+    memory_set: &mut MemorySet,
+    status_ptr: *mut i32,
+    status: i32,
+) -> Result<(), SysError> {
+    if status_ptr.is_null() {
+        return Ok(());
+    }
+    let start_va = VirtAddr::from(status_ptr as usize);
+    let end_va = VirtAddr::from(status_ptr as usize + size_of::<i32>());
+    memory_set.prepare_user_write(start_va, end_va);
+    match translated_refmut(memory_set.token(), status_ptr) {
+        Some(code) => {
+            *code = status;
+            Ok(())
+        }
+        None => Err(SysError::EFAULT),
     }
 }
 
 // If pid == -1, try to recycle every child
-// If there is not a child process whose pid is same as given, return -1.
-// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
-    // no such child
-    if inner
-        .children
-        .iter()
-        .find(|p| pid == -1 || pid as usize == p.getpid())
-        .is_none()
-    {
-        return -1;
-    }
-    // get child
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily access child PCB exclusively
-        p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-        // ++++ stop exclusively accessing child PCB
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after removing from children list
-        // so that after dropped, the kernel stack and pagetable and pid_handle will be recycled
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // write exit_code to the user space
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
+// If there is not a child process whose pid is same as given, return -ECHILD.
+// Else if the child is still running, sleep until a child exits, or return -EAGAIN
+// at once with WNOHANG in options. Return -EINTR if a signal arrives while sleeping.
+// With WUNTRACED in options, a child stopped since last reported is returned as well,
+// with the status (signal << 8) | 0x7f. The status is not written if exit_code_ptr
+// is null, and a child is not reaped or reported if it can not be written.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> isize {
+    loop {
+        let process = current_process();
+        let mut inner = process.inner_exclusive_access();
+        // no such child
+        if inner
+            .children
+            .iter()
+            .find(|p| pid == -1 || pid as usize == p.getpid())
+            .is_none()
+        {
+            return SysError::ECHILD.into();
+        }
+        // get child
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily access child PCB exclusively
+            p.inner_exclusive_access().is_zombie && (pid == -1 || pid as usize == p.getpid())
+            // ++++ stop exclusively accessing child PCB
+        });
+        if let Some((idx, _)) = pair {
+            let child = inner.children.remove(idx);
+            // its main thread may still be on its way out on another hart, which
+            // may need the lock of initproc
+            drop(inner);
+            let main_task = child.inner_exclusive_access().get_task(0);
+            main_task.wait_off_cpu();
+            drop(main_task);
+            // another hart may still hold the child from pid2process for a
+            // moment, its pagetable and pid_handle go with the last reference
+            let found_pid = child.getpid();
+            let mut inner = process.inner_exclusive_access();
+            let child_inner = child.inner_exclusive_access();
+            let exit_code = child_inner.exit_code;
+            // a bad pointer leaves the child to be waited for again
+            if put_status(&mut inner.memory_set, exit_code_ptr, exit_code).is_err() {
+                drop(child_inner);
+                inner.children.push(child);
+                return SysError::EFAULT.into();
+            }
+            // the resources used by the child and its reaped descendants are ours now
+            inner.children_rusage.add(&child_inner.rusage);
+            inner.children_rusage.add(&child_inner.children_rusage);
+            return found_pid as isize;
+        }
+        if options & WUNTRACED != 0 {
+            let stopped = inner.children.iter().find_map(|p| {
+                if pid != -1 && pid as usize != p.getpid() {
+                    return None;
+                }
+                let child_inner = p.inner_exclusive_access();
+                match child_inner.stop_signal {
+                    Some(signal) if !child_inner.stop_reported => Some((Arc::clone(p), signal)),
+                    _ => None,
+                }
+            });
+            if let Some((child, signal)) = stopped {
+                let status = ((signal << 8) | 0x7f) as i32;
+                if put_status(&mut inner.memory_set, exit_code_ptr, status).is_err() {
+                    return SysError::EFAULT.into();
+                }
+                // reported only once it has been written
+                child.inner_exclusive_access().stop_reported = true;
+                return child.getpid() as isize;
+            }
+        }
+        if options & WNOHANG != 0 {
+            return SysError::EAGAIN.into();
+        }
+        // let the pending signals be handled before going to sleep
+        if !inner.signals.difference(inner.signal_mask).is_empty() {
+            return SysError::EINTR.into();
+        }
+        let child_waiters = Arc::clone(&process.child_waiters);
+        // the process must not be kept alive by a sleeping thread
+        drop(inner);
+        drop(process);
+        // a child exiting or a signal arriving from now on wakes us up, look
+        // again for those which came in before
+        child_waiters.wait_with(|| !child_exited_or_signaled(pid, options));
     }
 }
 
-// set task.signals[signum] = 1 from another process
-pub fn sys_kill(pid: usize, signum: i32) -> isize {
-    if let Some(task) = pid2task(pid) {
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            // insert the signal if legal
-            let mut task_ref = task.inner_exclusive_access();
-            if task_ref.signals.contains(flag) {
-                return -1;
+fn child_exited_or_signaled(pid: isize, options: usize) -> bool {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    !inner.signals.difference(inner.signal_mask).is_empty()
+        || inner.children.iter().any(|p| {
+            let child_inner = p.inner_exclusive_access();
+            (pid == -1 || pid as usize == p.getpid())
+                && (child_inner.is_zombie
+                    || options & WUNTRACED != 0
+                        && child_inner.stop_signal.is_some()
+                        && !child_inner.stop_reported)
+        })
+}
+
+// Send a signal to the process pid, or to every process in the group -pid
+// if pid is negative, only root may signal the processes of other users.
+// Return -EAGAIN if the signal is already pending for a single process,
+// -ESRCH if there is no such process or group, or -EPERM if none of them
+// may be signaled.
+pub fn sys_kill(pid: isize, signum: i32) -> isize {
+    if !(0..=MAX_SIG as i32).contains(&signum) {
+        return SysError::EINVAL.into();
+    }
+    let flag = match SignalFlags::from_bits(1 << signum) {
+        Some(flag) => flag,
+        None => return SysError::EINVAL.into(),
+    };
+    let cred = current_process().inner_exclusive_access().cred;
+    let may_signal = |process: &Arc<ProcessControlBlock>| {
+        cred.may_signal(&process.inner_exclusive_access().cred)
+    };
+    if pid < 0 {
+        let group = processes_in_group(-pid as usize);
+        if group.is_empty() {
+            return SysError::ESRCH.into();
+        }
+        // the processes of the other users are skipped
+        let allowed: Vec<_> = group.iter().filter(|process| may_signal(process)).collect();
+        if allowed.is_empty() {
+            return SysError::EPERM.into();
+        }
+        for process in allowed {
+            send_signal(process, flag);
+        }
+        return 0;
+    }
+    match pid2process(pid as usize) {
+        Some(process) => {
+            if !may_signal(&process) {
+                return SysError::EPERM.into();
+            }
+            if process.inner_exclusive_access().signals.contains(flag) {
+                return SysError::EAGAIN.into();
             }
-            task_ref.signals.insert(flag);
+            send_signal(&process, flag);
             0
-        } else {
-            -1
         }
+        None => SysError::ESRCH.into(),
+    }
+}
+
+// Move the process pid, the caller or one of its children, into the group
+// pgid in the same session, 0 for either means the process itself. A new
+// group is created if pgid is pid.
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    let current = current_process();
+    let target = if pid == 0 || pid == current.getpid() {
+        Arc::clone(&current)
     } else {
-        -1
+        let inner = current.inner_exclusive_access();
+        match inner.children.iter().find(|child| child.getpid() == pid) {
+            Some(child) => Arc::clone(child),
+            None => return SysError::ESRCH.into(),
+        }
+    };
+    let pgid = if pgid == 0 { target.getpid() } else { pgid };
+    let sid = current.inner_exclusive_access().sid;
+    let target_inner = target.inner_exclusive_access();
+    // a session leader stays in its group, and the groups do not span sessions
+    if target_inner.sid != sid || target_inner.sid == target.getpid() {
+        return SysError::EPERM.into();
+    }
+    drop(target_inner);
+    if pgid != target.getpid()
+        && !processes_in_group(pgid)
+            .iter()
+            .any(|process| process.inner_exclusive_access().sid == sid)
+    {
+        return SysError::EPERM.into();
     }
+    target.inner_exclusive_access().pgid = pgid;
+    0
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    match process_of(pid) {
+        Ok(process) => process.inner_exclusive_access().pgid as isize,
+        Err(err) => err.into(),
+    }
+}
+
+pub fn sys_getsid(pid: usize) -> isize {
+    match process_of(pid) {
+        Ok(process) => process.inner_exclusive_access().sid as isize,
+        Err(err) => err.into(),
+    }
+}
+
+// Make the caller the leader of a new session and group, return the session id.
+// A group leader can not, otherwise its group would span two sessions.
+pub fn sys_setsid() -> isize {
+    let process = current_process();
+    let pid = process.getpid();
+    let mut inner = process.inner_exclusive_access();
+    if inner.pgid == pid {
+        return SysError::EPERM.into();
+    }
+    inner.pgid = pid;
+    inner.sid = pid;
+    pid as isize
 }
 
 pub fn sys_sigprocmask(mask: u32) -> isize {
     if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
+        let process = task.process.upgrade().unwrap();
+        let mut inner = process.inner_exclusive_access();
         let old_mask = inner.signal_mask;
         if let Some(flag) = SignalFlags::from_bits(mask) {
             inner.signal_mask = flag;
             old_mask.bits() as isize
         } else {
-            -1
+            SysError::EINVAL.into()
         }
     } else {
-        -1
+        SysError::ESRCH.into()
     }
 }
 
 pub fn sys_sigretrun() -> isize {
     if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
+        let process = task.process.upgrade().unwrap();
+        let mut inner = process.inner_exclusive_access();
+        // not returning from a signal handler
+        let trap_ctx_backup = match inner.trap_ctx_backup {
+            Some(trap_ctx_backup) => trap_ctx_backup,
+            None => return SysError::EINVAL.into(),
+        };
         inner.handling_sig = -1;
         // restore the trap context
-        let trap_ctx = inner.get_trap_cx();
-        *trap_ctx = inner.trap_ctx_backup.unwrap();
+        let trap_ctx = task.inner_exclusive_access().get_trap_cx();
+        *trap_ctx = trap_ctx_backup;
         0
     } else {
-        -1
+        SysError::ESRCH.into()
     }
 }
 
@@ -202,25 +757,102 @@ pub fn sys_sigaction(
 ) -> isize {
     let token = current_user_token();
     if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
+        let process = task.process.upgrade().unwrap();
+        let mut inner = process.inner_exclusive_access();
         if signum as usize > MAX_SIG {
-            return -1;
+            return SysError::EINVAL.into();
         }
         if let Some(flag) = SignalFlags::from_bits(1 << signum) {
             if check_sigaction_error(flag, action as usize, old_action as usize) {
-                return -1;
+                return SysError::EINVAL.into();
             }
             let old_kernel_action = inner.signal_actions.table[signum as usize];
+            let start_va = VirtAddr::from(old_action as usize);
+            let end_va = VirtAddr::from(old_action as usize + size_of::<SignalAction>());
+            inner.memory_set.prepare_user_write(start_va, end_va);
+            let ref_old_action = match translated_refmut(token, old_action) {
+                Some(ref_old_action) => ref_old_action,
+                None => return SysError::EFAULT.into(),
+            };
             if old_kernel_action.mask != SignalFlags::from_bits(40).unwrap() {
-                *translated_refmut(token, old_action) = old_kernel_action;
+                *ref_old_action = old_kernel_action;
             } else {
-                let mut ref_old_action = *translated_refmut(token, old_action);
                 ref_old_action.handler = old_kernel_action.handler;
             }
-            let ref_action = translated_ref(token, action);
+            let start_va = VirtAddr::from(action as usize);
+            let end_va = VirtAddr::from(action as usize + size_of::<SignalAction>());
+            inner.memory_set.prepare_user_read(start_va, end_va);
+            let ref_action = match translated_ref(token, action) {
+                Some(ref_action) => ref_action,
+                None => return SysError::EFAULT.into(),
+            };
             inner.signal_actions.table[signum as usize] = *ref_action;
+            // a pending signal is discarded once it is ignored
+            if ref_action.handler == SIG_IGN {
+                inner.signals.remove(flag);
+            }
             return 0;
         }
     }
-    -1
+    SysError::EINVAL.into()
+}
+
+// Read the oldest mail into buf, the rest of a longer mail is discarded.
+// Return -EAGAIN if the mailbox is empty, or -EFAULT if buf is invalid.
+// An empty buf only checks whether there is a mail to read.
+pub fn sys_mail_read(buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if inner.mailbox.is_empty() {
+        return SysError::EAGAIN.into();
+    }
+    if len == 0 {
+        return 0;
+    }
+    let start_va = VirtAddr::from(buf as usize);
+    let end_va = VirtAddr::from(buf as usize + len);
+    inner.memory_set.prepare_user_write(start_va, end_va);
+    if !user_buffer_writable(token, buf, len) {
+        return SysError::EFAULT.into();
+    }
+    let mail = inner.mailbox.pop().unwrap();
+    // release current task TCB manually to avoid multi-borrow
+    drop(inner);
+    let len = len.min(mail.len());
+    let user_buf = UserBuffer::new(translated_byte_buffers(token, buf, len));
+    for (byte_ref, byte) in user_buf.into_iter().zip(mail.iter()) {
+        unsafe {
+            *byte_ref = *byte;
+        }
+    }
+    len as isize
+}
+
+// Send buf as a mail to process pid, at most MAX_MAIL_LEN bytes are sent.
+// Return -EAGAIN if the target mailbox is full, or -EFAULT if buf is invalid.
+// An empty buf only checks whether the target mailbox can accept a mail.
+pub fn sys_mail_write(pid: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    let target = match pid2process(pid) {
+        Some(process) => process,
+        None => return SysError::ESRCH.into(),
+    };
+    if target.inner_exclusive_access().mailbox.is_full() {
+        return SysError::EAGAIN.into();
+    }
+    if len == 0 {
+        return 0;
+    }
+    let len = len.min(MAX_MAIL_LEN);
+    current_prepare_user_read(buf, len);
+    if !user_buffer_mapped(token, buf, len) {
+        return SysError::EFAULT.into();
+    }
+    let mail: Vec<u8> = UserBuffer::new(translated_byte_buffers(token, buf, len))
+        .into_iter()
+        .map(|byte_ref| unsafe { *byte_ref })
+        .collect();
+    target.inner_exclusive_access().mailbox.push(mail);
+    len as isize
 }
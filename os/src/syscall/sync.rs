@@ -0,0 +1,191 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::errno::SysError;
+use crate::sync::{Condvar, Mutex, MutexBlocking, MutexSpin, Resource, Semaphore};
+use crate::task::{current_process, current_task};
+
+// Put obj into the first free slot of list and return its id.
+fn insert_into<T: ?Sized>(list: &mut Vec<Option<Arc<T>>>, obj: Arc<T>) -> usize {
+    if let Some(id) = list.iter().position(|item| item.is_none()) {
+        list[id] = Some(obj);
+        id
+    } else {
+        list.push(Some(obj));
+        list.len() - 1
+    }
+}
+
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    let process = current_process();
+    let mutex: Arc<dyn Mutex> = if blocking {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    let mut process_inner = process.inner_exclusive_access();
+    let mutex_id = insert_into(&mut process_inner.mutex_list, mutex);
+    process_inner
+        .deadlock_detector
+        .add_resource(Resource::Mutex(mutex_id), 1);
+    mutex_id as isize
+}
+
+// Return -EDEADLK if the deadlock detection is on and waiting for the mutex may deadlock.
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    let tid = current_task().unwrap().gettid();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let mutex = match process_inner.mutex_list.get(mutex_id) {
+        Some(Some(mutex)) => Arc::clone(mutex),
+        _ => return SysError::EINVAL.into(),
+    };
+    if !process_inner
+        .deadlock_detector
+        .request(tid, Resource::Mutex(mutex_id))
+    {
+        return SysError::EDEADLK.into();
+    }
+    // the lock may block, release the process first
+    drop(process_inner);
+    drop(process);
+    mutex.lock();
+    current_process()
+        .inner_exclusive_access()
+        .deadlock_detector
+        .acquire(tid, Resource::Mutex(mutex_id));
+    0
+}
+
+// Return -EPERM if the mutex is not locked.
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    let tid = current_task().unwrap().gettid();
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let mutex = match process_inner.mutex_list.get(mutex_id) {
+        Some(Some(mutex)) => Arc::clone(mutex),
+        _ => return SysError::EINVAL.into(),
+    };
+    drop(process_inner);
+    if mutex.unlock() {
+        process
+            .inner_exclusive_access()
+            .deadlock_detector
+            .release(tid, Resource::Mutex(mutex_id));
+        0
+    } else {
+        SysError::EPERM.into()
+    }
+}
+
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let sem_id = insert_into(
+        &mut process_inner.semaphore_list,
+        Arc::new(Semaphore::new(res_count)),
+    );
+    process_inner
+        .deadlock_detector
+        .add_resource(Resource::Semaphore(sem_id), res_count);
+    sem_id as isize
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    let tid = current_task().unwrap().gettid();
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let sem = match process_inner.semaphore_list.get(sem_id) {
+        Some(Some(sem)) => Arc::clone(sem),
+        _ => return SysError::EINVAL.into(),
+    };
+    drop(process_inner);
+    sem.up();
+    process
+        .inner_exclusive_access()
+        .deadlock_detector
+        .release(tid, Resource::Semaphore(sem_id));
+    0
+}
+
+// Return -EDEADLK if the deadlock detection is on and waiting for the semaphore may deadlock.
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    let tid = current_task().unwrap().gettid();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let sem = match process_inner.semaphore_list.get(sem_id) {
+        Some(Some(sem)) => Arc::clone(sem),
+        _ => return SysError::EINVAL.into(),
+    };
+    if !process_inner
+        .deadlock_detector
+        .request(tid, Resource::Semaphore(sem_id))
+    {
+        return SysError::EDEADLK.into();
+    }
+    // the down may block, release the process first
+    drop(process_inner);
+    drop(process);
+    sem.down();
+    current_process()
+        .inner_exclusive_access()
+        .deadlock_detector
+        .acquire(tid, Resource::Semaphore(sem_id));
+    0
+}
+
+pub fn sys_condvar_create() -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    insert_into(&mut process_inner.condvar_list, Arc::new(Condvar::new())) as isize
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let condvar = match process_inner.condvar_list.get(condvar_id) {
+        Some(Some(condvar)) => Arc::clone(condvar),
+        _ => return SysError::EINVAL.into(),
+    };
+    drop(process_inner);
+    condvar.signal();
+    0
+}
+
+// The mutex is given back and taken again by the same thread,
+// so the deadlock detector does not need to know about the wait.
+// The caller must hold the mutex, it is held again when the wait returns.
+// Return -EPERM if the mutex is not locked.
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let condvar = match process_inner.condvar_list.get(condvar_id) {
+        Some(Some(condvar)) => Arc::clone(condvar),
+        _ => return SysError::EINVAL.into(),
+    };
+    let mutex = match process_inner.mutex_list.get(mutex_id) {
+        Some(Some(mutex)) => Arc::clone(mutex),
+        _ => return SysError::EINVAL.into(),
+    };
+    drop(process_inner);
+    drop(process);
+    if condvar.wait(mutex) {
+        0
+    } else {
+        SysError::EPERM.into()
+    }
+}
+
+// Turn the deadlock detection of the current process on (1) or off (0).
+// While it is on, acquiring a mutex or a semaphore fails instead of
+// entering an unsafe state.
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    let enabled = match enabled {
+        0 => false,
+        1 => true,
+        _ => return SysError::EINVAL.into(),
+    };
+    let process = current_process();
+    process.inner_exclusive_access().deadlock_detector.enabled = enabled;
+    0
+}
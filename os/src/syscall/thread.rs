@@ -0,0 +1,94 @@
+use alloc::sync::Arc;
+use core::mem::size_of;
+
+use crate::errno::SysError;
+use crate::mm::{translated_refmut, VirtAddr, KERNEL_SPACE};
+use crate::task::{add_task, current_task, TaskControlBlock};
+use crate::trap::{trap_handler, TrapContext};
+
+// Create a thread of the current process running entry(arg) on its own user stack,
+// return the tid of the new thread, -EAGAIN if the process has too many
+// threads and -ENOMEM if there is no memory for the new one.
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let task_inner = task.inner_exclusive_access();
+    let ustack_base = task_inner.res.as_ref().unwrap().ustack_base();
+    // the threads of a process share its priority and real-time parameters
+    let priority = task_inner.priority;
+    let rt = task_inner.rt.map(|rt| rt.restarted());
+    drop(task_inner);
+    // create a new thread, its user stack and trap context are mapped here
+    let new_task = match TaskControlBlock::new(Arc::clone(&process), ustack_base, true) {
+        Ok(task) => Arc::new(task),
+        Err(err) => return err.into(),
+    };
+    let mut new_task_inner = new_task.inner_exclusive_access();
+    new_task_inner.set_prio(priority);
+    new_task_inner.rt = rt;
+    let new_task_res = new_task_inner.res.as_ref().unwrap();
+    let new_task_tid = new_task_res.tid;
+    let new_task_trap_cx = new_task_inner.get_trap_cx();
+    *new_task_trap_cx = TrapContext::app_init_context(
+        entry,
+        new_task_res.ustack_top(),
+        KERNEL_SPACE.lock().token(),
+        new_task.kstack.get_top(),
+        trap_handler as usize,
+    );
+    (*new_task_trap_cx).x[10] = arg;
+    drop(new_task_inner);
+    // add the new thread to its process, tids are reused so the slot may exist
+    let mut process_inner = process.inner_exclusive_access();
+    let tasks = &mut process_inner.tasks;
+    while tasks.len() < new_task_tid + 1 {
+        tasks.push(None);
+    }
+    tasks[new_task_tid] = Some(Arc::clone(&new_task));
+    drop(process_inner);
+    // add new task to scheduler
+    add_task(new_task);
+    new_task_tid as isize
+}
+
+pub fn sys_gettid() -> isize {
+    current_task().unwrap().gettid() as isize
+}
+
+// Reap the exited thread tid of the current process and write its exit code to exit_code_ptr.
+// Return -EDEADLK if a thread waits for itself, -ESRCH if there is no such thread,
+// and -EAGAIN if the thread is still running.
+pub fn sys_waittid(tid: usize, exit_code_ptr: *mut i32) -> isize {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    if task.gettid() == tid {
+        return SysError::EDEADLK.into();
+    }
+    let mut process_inner = process.inner_exclusive_access();
+    let exit_code = match process_inner.tasks.get(tid) {
+        Some(Some(waited_task)) => match waited_task.inner_exclusive_access().exit_code {
+            Some(exit_code) => exit_code,
+            None => return SysError::EAGAIN.into(),
+        },
+        _ => return SysError::ESRCH.into(),
+    };
+    // write exit_code to the user space
+    let start_va = VirtAddr::from(exit_code_ptr as usize);
+    let end_va = VirtAddr::from(exit_code_ptr as usize + size_of::<i32>());
+    process_inner
+        .memory_set
+        .prepare_user_write(start_va, end_va);
+    match translated_refmut(process_inner.memory_set.token(), exit_code_ptr) {
+        Some(code) => *code = exit_code,
+        None => return SysError::EFAULT.into(),
+    }
+    let waited_task = process_inner.tasks[tid].take();
+    // the user resources borrow the process when released
+    drop(process_inner);
+    let waited_task = waited_task.unwrap();
+    // it may still be switching out of its kernel stack on another hart
+    waited_task.wait_off_cpu();
+    // dealloc the tid, user stack, trap context and kernel stack of the thread
+    drop(waited_task);
+    tid as isize
+}
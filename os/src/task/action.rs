@@ -1,5 +1,9 @@
 use crate::task::{SignalFlags, MAX_SIG};
 
+// the handlers with special meanings
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
 /// Action for a signal
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,65 @@
+// the user and group a process acts for, the effective ones are checked
+// against the owner and the permission bits of the inodes it touches
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Credential {
+    pub uid: u32,
+    pub euid: u32,
+    pub gid: u32,
+    pub egid: u32,
+}
+
+impl Credential {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    // root passes the permission checks
+    pub fn is_privileged(&self) -> bool {
+        self.euid == 0
+    }
+
+    // the scheduling of a process is changed by its user, or by root
+    pub fn may_control(&self, target: &Credential) -> bool {
+        self.is_privileged() || self.euid == target.uid
+    }
+
+    // what a file with the permission bits perm owned by uid and gid is run
+    // with, a setuid or setgid file lends its owner or its group
+    pub fn exec_as(&self, perm: u16, uid: u32, gid: u32) -> Self {
+        let mut cred = *self;
+        if perm & 0o4000 != 0 {
+            cred.euid = uid;
+        }
+        if perm & 0o2000 != 0 {
+            cred.egid = gid;
+        }
+        cred
+    }
+
+    // a signal goes to the processes of the same user, or anywhere from root
+    pub fn may_signal(&self, target: &Credential) -> bool {
+        self.is_privileged() || self.uid == target.uid || self.euid == target.uid
+    }
+
+    // root sets both ids, the others may only switch the effective one back
+    // to the real one
+    pub fn set_uid(&mut self, uid: u32) -> bool {
+        if self.is_privileged() {
+            self.uid = uid;
+        } else if uid != self.uid {
+            return false;
+        }
+        self.euid = uid;
+        true
+    }
+
+    pub fn set_gid(&mut self, gid: u32) -> bool {
+        if self.is_privileged() {
+            self.gid = gid;
+        } else if gid != self.gid {
+            return false;
+        }
+        self.egid = gid;
+        true
+    }
+}
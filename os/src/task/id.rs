@@ -0,0 +1,241 @@
+use crate::config::{
+    KERNEL_STACK_SIZE, MAX_THREADS, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE,
+};
+use crate::errno::SysError;
+use crate::mm::{MapPermission, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::sync::SpinLock;
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use lazy_static::*;
+
+use super::ProcessControlBlock;
+
+// hand out small integers and reuse the released ones first
+pub struct RecycleAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl RecycleAllocator {
+    pub fn new() -> Self {
+        RecycleAllocator {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
+        assert!(
+            !self.recycled.iter().any(|i| *i == id),
+            "id {} has been deallocated!",
+            id
+        );
+        self.recycled.push(id);
+    }
+}
+
+lazy_static! {
+    static ref PID_ALLOCATOR: SpinLock<RecycleAllocator> = SpinLock::new(RecycleAllocator::new());
+    static ref KSTACK_ALLOCATOR: SpinLock<RecycleAllocator> =
+        SpinLock::new(RecycleAllocator::new());
+}
+
+pub struct PidHandle(pub usize);
+
+impl Drop for PidHandle {
+    fn drop(&mut self) {
+        PID_ALLOCATOR.lock().dealloc(self.0);
+    }
+}
+
+pub fn pid_alloc() -> PidHandle {
+    PidHandle(PID_ALLOCATOR.lock().alloc())
+}
+
+/// Return (bottom, top) of the kernel stack with the given id in kernel space.
+pub fn kernel_stack_position(kstack_id: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - kstack_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let bottom = top - KERNEL_STACK_SIZE;
+    (bottom, top)
+}
+
+// every thread owns a kernel stack, the id is unique among all threads
+pub struct KernelStack(pub usize);
+
+pub fn kstack_alloc() -> KernelStack {
+    let kstack_id = KSTACK_ALLOCATOR.lock().alloc();
+    let (bottom, top) = kernel_stack_position(kstack_id);
+    if let Err(e) = KERNEL_SPACE.lock().insert_framed_area(
+        bottom.into(),
+        top.into(),
+        MapPermission::R | MapPermission::W,
+    ) {
+        error!("Cannot allocate kernel stack {}, {}", kstack_id, e);
+    }
+    KernelStack(kstack_id)
+}
+
+impl KernelStack {
+    pub fn get_top(&self) -> usize {
+        let (_, top) = kernel_stack_position(self.0);
+        top
+    }
+
+    pub fn put_on_top<T>(&self, value: T) -> *mut T
+    where
+        T: Sized,
+    {
+        let top = self.get_top();
+        let ptr_mut = (top - core::mem::size_of::<T>()) as *mut T;
+        unsafe { *ptr_mut = value };
+        ptr_mut
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (kernel_stack_bottom, _) = kernel_stack_position(self.0);
+        let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
+        KERNEL_SPACE
+            .lock()
+            .remove_area_with_start_vpn(kernel_stack_bottom_va.into());
+        KSTACK_ALLOCATOR.lock().dealloc(self.0);
+    }
+}
+
+// trap context pages of the threads grow downwards from TRAP_CONTEXT
+fn trap_cx_bottom_from_tid(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+// user stacks grow upwards from ustack_base, each one above a guard page
+fn ustack_bottom_from_tid(ustack_base: usize, tid: usize) -> usize {
+    ustack_base + PAGE_SIZE + tid * (PAGE_SIZE + USER_STACK_SIZE)
+}
+
+/// Whether [start, end) overlaps the user stack or the trap context of any
+/// thread the process may have, or the trampoline, where mmap must not go.
+pub fn overlaps_thread_slots(ustack_base: usize, start: usize, end: usize) -> bool {
+    let ustack_end = ustack_bottom_from_tid(ustack_base, MAX_THREADS);
+    let trap_cx_start = trap_cx_bottom_from_tid(MAX_THREADS - 1);
+    (start < ustack_end && end > ustack_base) || end > trap_cx_start
+}
+
+// the user space resources of a thread, released with the thread
+pub struct TaskUserRes {
+    pub tid: usize,
+    pub ustack_base: usize,
+    pub process: Weak<ProcessControlBlock>,
+}
+
+impl TaskUserRes {
+    pub fn new(
+        process: Arc<ProcessControlBlock>,
+        ustack_base: usize,
+        alloc_user_res: bool,
+    ) -> Result<Self, SysError> {
+        let tid = process.inner_exclusive_access().alloc_tid();
+        // no room is kept for the stack and trap context of more threads
+        if tid >= MAX_THREADS {
+            process.inner_exclusive_access().dealloc_tid(tid);
+            return Err(SysError::EAGAIN);
+        }
+        let task_user_res = Self {
+            tid,
+            ustack_base,
+            process: Arc::downgrade(&process),
+        };
+        if alloc_user_res {
+            task_user_res.alloc_user_res()?;
+        }
+        Ok(task_user_res)
+    }
+
+    /// Map the user stack and the trap context of the thread, fail with
+    /// ENOMEM if there are no frames left for them.
+    pub fn alloc_user_res(&self) -> Result<(), SysError> {
+        let process = self.process.upgrade().unwrap();
+        let mut process_inner = process.inner_exclusive_access();
+        // the user stack grows on demand down to the guard page
+        let ustack_bottom = ustack_bottom_from_tid(self.ustack_base, self.tid);
+        let ustack_top = ustack_bottom + USER_STACK_SIZE;
+        process_inner
+            .memory_set
+            .insert_lazy_area(
+                ustack_bottom.into(),
+                ustack_top.into(),
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            )
+            .map_err(|_| SysError::ENOMEM)?;
+        // the trap context is accessed by the kernel directly, map it at once
+        let trap_cx_bottom = trap_cx_bottom_from_tid(self.tid);
+        let trap_cx_top = trap_cx_bottom + PAGE_SIZE;
+        process_inner
+            .memory_set
+            .insert_framed_area(
+                trap_cx_bottom.into(),
+                trap_cx_top.into(),
+                MapPermission::R | MapPermission::W,
+            )
+            .map_err(|_| SysError::ENOMEM)
+    }
+
+    fn dealloc_user_res(&self) {
+        let process = self.process.upgrade().unwrap();
+        let mut process_inner = process.inner_exclusive_access();
+        let ustack_bottom_va: VirtAddr = ustack_bottom_from_tid(self.ustack_base, self.tid).into();
+        process_inner
+            .memory_set
+            .remove_area_with_start_vpn(ustack_bottom_va.into());
+        let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(self.tid).into();
+        process_inner
+            .memory_set
+            .remove_area_with_start_vpn(trap_cx_bottom_va.into());
+    }
+
+    pub fn dealloc_tid(&self) {
+        let process = self.process.upgrade().unwrap();
+        let mut process_inner = process.inner_exclusive_access();
+        process_inner.dealloc_tid(self.tid);
+    }
+
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_cx_bottom_from_tid(self.tid)
+    }
+
+    pub fn trap_cx_ppn(&self) -> PhysPageNum {
+        let process = self.process.upgrade().unwrap();
+        let process_inner = process.inner_exclusive_access();
+        let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(self.tid).into();
+        process_inner
+            .memory_set
+            .translate(trap_cx_bottom_va.into())
+            .unwrap()
+            .ppn()
+    }
+
+    pub fn ustack_base(&self) -> usize {
+        self.ustack_base
+    }
+
+    pub fn ustack_top(&self) -> usize {
+        ustack_bottom_from_tid(self.ustack_base, self.tid) + USER_STACK_SIZE
+    }
+}
+
+impl Drop for TaskUserRes {
+    fn drop(&mut self) {
+        self.dealloc_tid();
+        self.dealloc_user_res();
+    }
+}
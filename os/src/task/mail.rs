@@ -0,0 +1,49 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// Max number of mails a mailbox can hold
+pub const MAX_MAIL_NUM: usize = 16;
+/// Max length of a single mail, longer mails are truncated
+pub const MAX_MAIL_LEN: usize = 256;
+
+// a bounded FIFO mailbox owned by every process
+pub struct MailBox {
+    mails: VecDeque<Vec<u8>>,
+}
+
+impl MailBox {
+    pub fn new() -> Self {
+        Self {
+            mails: VecDeque::with_capacity(MAX_MAIL_NUM),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mails.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.mails.len() == MAX_MAIL_NUM
+    }
+
+    /// Append a mail, return false if the mailbox is full.
+    pub fn push(&mut self, mut mail: Vec<u8>) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        mail.truncate(MAX_MAIL_LEN);
+        self.mails.push_back(mail);
+        true
+    }
+
+    /// Take the oldest mail.
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        self.mails.pop_front()
+    }
+}
+
+impl Default for MailBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
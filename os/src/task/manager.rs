@@ -1,74 +1,143 @@
-use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 
-use super::scheduler::BIG_STRIDE;
-use super::StrideScheduler;
-use super::TaskControlBlock;
-use super::{current_pid, current_task};
-use crate::sync::UPSafeCell;
-
-type Scheduler = StrideScheduler;
+use super::scheduler::{RtScheduler, Scheduler, SelectedScheduler};
+use super::{ProcessControlBlock, TaskControlBlock};
+use crate::smp::{send_ipi_others, IPI_RESCHEDULE};
+use crate::sync::{RwLock, SpinNoIrq};
 
+// the ready threads, the real-time ones are kept apart and run first,
+// the others are kept by the scheduler selected at build time
 pub struct TaskManager {
-    ready_queue: BTreeMap<usize, Arc<TaskControlBlock>>,
-    scheduler: Box<Scheduler>,
+    rt: RtScheduler,
+    scheduler: SelectedScheduler,
 }
 
 impl TaskManager {
     pub fn new() -> Self {
-        let stride_scheduler: Scheduler = Scheduler::new();
         Self {
-            ready_queue: BTreeMap::new(),
-            scheduler: Box::new(stride_scheduler),
+            rt: RtScheduler::new(),
+            scheduler: SelectedScheduler::new(),
         }
     }
 
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        // update pass for stride scheduler
-        let mut task_inner = task.inner_exclusive_access();
-        task_inner.pass += BIG_STRIDE / task_inner.priority;
-        drop(task_inner);
-        self.scheduler
-            .insert_task(task.getpid(), task.clone().inner_exclusive_access().pass);
-        self.ready_queue.insert(task.getpid(), task);
+        if is_rt(&task) {
+            self.rt.enqueue(task);
+        } else {
+            self.scheduler.enqueue(task);
+        }
     }
 
+    // blocked threads live in wait queues and are added again when woken up
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        let next_pid = loop {
-            if let Some(next_pid) = self.scheduler.find_next_task() {
-                // TODO how about wait state
-                if self.ready_queue.contains_key(&next_pid) {
-                    break next_pid;
-                }
-            } else {
-                return None;
-            }
-        };
-        let (_, task) = self.ready_queue.remove_entry(&next_pid).unwrap();
-        Some(task)
+        self.rt.pick_next().or_else(|| self.scheduler.pick_next())
+    }
+
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.rt.remove(task);
+        self.scheduler.remove(task);
     }
+
+    fn tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        if is_rt(task) {
+            self.rt.charge(task);
+            task.inner_exclusive_access().rt.unwrap().exhausted() || self.rt.preempts(task)
+        } else {
+            // the scheduler is told about the tick even if a real-time thread is waiting
+            self.scheduler.on_tick(task) || !self.rt.is_empty()
+        }
+    }
+
+    // Called when another hart has made a real-time thread ready.
+    fn resched(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        if is_rt(task) {
+            self.rt.charge(task);
+            self.rt.preempts(task)
+        } else if !self.rt.is_empty() {
+            self.scheduler.on_yield(task);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn yield_(&mut self, task: &Arc<TaskControlBlock>) {
+        if is_rt(task) {
+            self.rt.charge(task);
+        } else {
+            self.scheduler.on_yield(task);
+        }
+    }
+}
+
+fn is_rt(task: &TaskControlBlock) -> bool {
+    task.inner_exclusive_access().rt.is_some()
 }
 
 lazy_static! {
-    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
-        unsafe { UPSafeCell::new(TaskManager::new()) };
+    // also taken from the timer interrupt
+    pub static ref TASK_MANAGER: SpinNoIrq<TaskManager> = SpinNoIrq::new(TaskManager::new());
+    // looked up by pid much more often than changed
+    pub static ref PID2PCB: RwLock<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        RwLock::new(BTreeMap::new());
 }
 
 pub fn add_task(task: Arc<TaskControlBlock>) {
-    TASK_MANAGER.exclusive_access().add(task);
+    let rt = is_rt(&task);
+    TASK_MANAGER.lock().add(task);
+    // it may preempt a thread running on another hart
+    if rt {
+        send_ipi_others(IPI_RESCHEDULE);
+    }
 }
 
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
-    TASK_MANAGER.exclusive_access().fetch()
+    TASK_MANAGER.lock().fetch()
+}
+
+pub fn remove_task(task: &Arc<TaskControlBlock>) {
+    TASK_MANAGER.lock().remove(task);
+}
+
+// Return true if the running task should give up the cpu on this timer tick.
+pub fn tick_task(task: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGER.lock().tick(task)
+}
+
+// Return true if the running task should give up the cpu to a real-time thread.
+pub fn resched_task(task: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGER.lock().resched(task)
+}
+
+pub fn yield_task(task: &Arc<TaskControlBlock>) {
+    TASK_MANAGER.lock().yield_(task);
+}
+
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    let map = PID2PCB.read();
+    map.get(&pid).map(Arc::clone)
+}
+
+// The processes in the process group pgid.
+pub fn processes_in_group(pgid: usize) -> Vec<Arc<ProcessControlBlock>> {
+    PID2PCB
+        .read()
+        .values()
+        .filter(|process| process.inner_exclusive_access().pgid == pgid)
+        .cloned()
+        .collect()
+}
+
+pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
+    PID2PCB.write().insert(pid, process);
 }
 
-pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
-    if pid == current_pid() {
-        current_task()
-    } else {
-        let map = &TASK_MANAGER.exclusive_access().ready_queue;
-        map.get(&pid).map(Arc::clone)
+pub fn remove_from_pid2process(pid: usize) {
+    let mut map = PID2PCB.write();
+    if map.remove(&pid).is_none() {
+        panic!("cannot find pid {} in pid2process!", pid);
     }
 }
@@ -1,109 +1,327 @@
 mod action;
 mod context;
+mod cred;
+mod id;
+mod mail;
 mod manager;
-mod pid;
+mod process;
 mod processor;
+mod rusage;
 mod scheduler;
 mod signal;
 mod switch;
 mod task;
+mod wait_queue;
 
-use crate::fs::{open_file, OpenFlags};
+use crate::fs::{open_file, OpenFlags, ROOT_INODE};
 use crate::mm::{MapPermission, VirtAddr};
-use crate::sync::UPSafeCell;
+use crate::smp::{harts_in, send_ipi, tlb_shootdown, IPI_RESCHEDULE};
+use crate::timer::{add_timer, get_time_us};
 use crate::trap::TrapContext;
 
-pub use action::{SignalAction, SignalActions};
-use alloc::boxed::Box;
-use alloc::sync::Arc;
+pub use action::{SignalAction, SignalActions, SIG_DFL, SIG_IGN};
+use alloc::sync::{Arc, Weak};
 use alloc::vec::Vec;
+pub use cred::Credential;
+pub use id::{overlaps_thread_slots, TaskUserRes};
 use lazy_static::*;
-pub use manager::{add_task, fetch_task, pid2task};
+pub use mail::{MailBox, MAX_MAIL_LEN};
+pub use manager::{
+    add_task, fetch_task, pid2process, processes_in_group, remove_from_pid2process, remove_task,
+};
+use manager::{resched_task, tick_task, yield_task};
+pub use process::ProcessControlBlock;
 pub use processor::{
-    current_pid, current_task, current_trap_cx, current_user_token, run_tasks, schedule,
-    take_current_task,
+    current_pid, current_process, current_task, current_trap_cx, current_trap_cx_user_va,
+    current_user_token, run_tasks, schedule, take_current_task,
 };
-use scheduler::StrideScheduler;
+pub use rusage::RUsage;
+pub use scheduler::{RtParams, RtPolicy, MAX_RT_PRIORITY};
 pub use signal::{SignalFlags, MAX_SIG};
 use switch::__switch;
-use task::{TaskControlBlock, TaskStatus};
+pub use task::{TaskControlBlock, TaskStatus, DEFAULT_PRIORITY, MAX_PRIORITY, MIN_PRIORITY};
+pub use wait_queue::WaitQueue;
 
 pub use context::TaskContext;
 
 lazy_static! {
-    pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
-        let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
+    pub static ref INITPROC: Arc<ProcessControlBlock> = {
+        let inode = open_file(
+            &ROOT_INODE,
+            "initproc",
+            OpenFlags::RDONLY,
+            0,
+            &Credential::root(),
+        )
+        .unwrap();
         let v = inode.read_all();
-        TaskControlBlock::new(v.as_slice())
-    });
+        ProcessControlBlock::new(v.as_slice())
+    };
 }
 
-pub struct TaskManager {
-    num_app: usize,
-    inner: UPSafeCell<TaskManagerInner>,
+pub fn add_initproc() {
+    add_task(INITPROC.inner_exclusive_access().get_task(0));
 }
 
-struct TaskManagerInner {
-    tasks: Vec<TaskControlBlock>,
-    current_task: usize,
-    scheduler: Box<StrideScheduler>,
+// The current thread gives up the cpu by itself.
+pub fn suspend_current_and_run_next() {
+    yield_task(&current_task().unwrap());
+    current_process()
+        .inner_exclusive_access()
+        .rusage
+        .voluntary_switches += 1;
+    ready_current_and_run_next();
 }
 
-pub fn add_initproc() {
-    add_task(INITPROC.clone());
+// Called on every timer interrupt, the cpu is taken away from the
+// current thread if the scheduler says so. A real-time thread which has
+// used up its budget sleeps until its next period.
+pub fn preempt_current_and_run_next() {
+    let task = current_task().unwrap();
+    if !tick_task(&task) {
+        return;
+    }
+    current_process()
+        .inner_exclusive_access()
+        .rusage
+        .involuntary_switches += 1;
+    let throttled_until = task
+        .inner_exclusive_access()
+        .rt
+        .filter(|rt| rt.exhausted())
+        .map(|rt| rt.next_period_us());
+    match throttled_until {
+        Some(expire_us) => {
+            add_timer(expire_us, task);
+            park_current_and_run_next();
+        }
+        None => {
+            drop(task);
+            ready_current_and_run_next();
+        }
+    }
 }
 
-pub fn suspend_current_and_run_next() {
+// Called on a reschedule interrupt, another hart has made a real-time thread
+// ready which may take the cpu from the current thread.
+pub fn resched_current_and_run_next() {
+    let task = current_task().unwrap();
+    if !resched_task(&task) {
+        return;
+    }
+    drop(task);
+    current_process()
+        .inner_exclusive_access()
+        .rusage
+        .involuntary_switches += 1;
+    ready_current_and_run_next();
+}
+
+fn ready_current_and_run_next() {
     let task = take_current_task().unwrap();
 
     // ---- access current TCB exclusively
     let mut task_inner = task.inner_exclusive_access();
     let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
-    // Change status to Ready
-    task_inner.task_status = TaskStatus::Ready;
+    // a thread of a process exited on another hart is not run again
+    let exited = task_inner.task_status == TaskStatus::Exited;
+    if !exited {
+        task_inner.task_status = TaskStatus::Ready;
+    }
     drop(task_inner);
     // ---- stop exclusively accessing current PCB
 
     // push back to ready queue.
-    add_task(task);
+    if !exited {
+        add_task(task);
+    } else {
+        drop(task);
+    }
     // jump to scheduling cycle
     schedule(task_cx_ptr);
 }
 
+// Park the current thread, it must have been put into some wait queue
+// so that it can be woken up by `wakeup_task` later.
+pub fn block_current_and_run_next() {
+    yield_task(&current_task().unwrap());
+    current_process()
+        .inner_exclusive_access()
+        .rusage
+        .voluntary_switches += 1;
+    park_current_and_run_next();
+}
+
+// Give up the cpu until woken up, unless a wakeup has come in since the
+// thread was put into the wait queue, possibly from another hart.
+fn park_current_and_run_next() {
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.wakeup_pending {
+        task_inner.wakeup_pending = false;
+        return;
+    }
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    // a thread of a process exited on another hart stays exited
+    if task_inner.task_status == TaskStatus::Running {
+        task_inner.task_status = TaskStatus::Blocked;
+    }
+    drop(task_inner);
+    drop(task);
+    take_current_task();
+    schedule(task_cx_ptr);
+}
+
+// Make a blocked thread ready again, return false if it has exited. A thread
+// still on its way to sleep on another hart does not sleep then.
+pub fn wakeup_task(task: Arc<TaskControlBlock>) -> bool {
+    let mut task_inner = task.inner_exclusive_access();
+    match task_inner.task_status {
+        TaskStatus::Blocked => {
+            task_inner.task_status = TaskStatus::Ready;
+            drop(task_inner);
+            add_task(task);
+            true
+        }
+        TaskStatus::Running => {
+            task_inner.wakeup_pending = true;
+            true
+        }
+        TaskStatus::Ready => true,
+        TaskStatus::UnInit | TaskStatus::Exited => false,
+    }
+}
+
+// Leave the cpu for good if the process of the current thread has exited on
+// another hart, called before returning to the user space.
+pub fn leave_current_if_exited() {
+    let task = current_task().unwrap();
+    if task.inner_exclusive_access().task_status != TaskStatus::Exited {
+        return;
+    }
+    drop(task);
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    drop(task_inner);
+    drop(task);
+    schedule(task_cx_ptr);
+    unreachable!("an exited thread is never switched back to");
+}
+
+// Exit the current thread, the whole process exits if it is the main thread.
 pub fn exit_current_and_run_next(exit_code: i32) {
     // take from processor
     let task = take_current_task().unwrap();
-    // access current TCB exclusively
-    let mut inner = task.inner_exclusive_access();
-    // change status to Zombie
-    inner.task_status = TaskStatus::Zombie;
+    let mut task_inner = task.inner_exclusive_access();
+    let process = task.process.upgrade().unwrap();
+    let tid = task_inner.res.as_ref().unwrap().tid;
     // record exit code
-    inner.exit_code = exit_code;
-    // initproc collects children
-    {
-        let mut initproc_inner = INITPROC.inner_exclusive_access();
-        for child in inner.children.iter() {
+    task_inner.exit_code = Some(exit_code);
+    task_inner.task_status = TaskStatus::Exited;
+    // the tid, user stack, trap context and the kernel stack we are running on
+    // are kept until the thread is reaped by waittid
+    drop(task_inner);
+    drop(task);
+    // the process terminates with its main thread
+    if tid == 0 {
+        remove_from_pid2process(process.getpid());
+        let mut inner = process.inner_exclusive_access();
+        // mark this process as a zombie process
+        inner.is_zombie = true;
+        // record exit code of the process
+        inner.exit_code = exit_code;
+        // initproc collects children, a parent locks itself before its children
+        // in waitpid, so ours are not touched while we are locked
+        let children = core::mem::take(&mut inner.children);
+        let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+        drop(inner);
+        for child in children.iter() {
             child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
-            initproc_inner.children.push(child.clone());
         }
+        let has_children = !children.is_empty();
+        INITPROC.inner_exclusive_access().children.extend(children);
+        // wake up the parent waiting for us and initproc if it has got zombies
+        if let Some(parent) = parent {
+            parent.child_waiters.wake_all();
+        }
+        if has_children {
+            INITPROC.child_waiters.wake_all();
+        }
+        let inner = process.inner_exclusive_access();
+        // take the other threads out of the ready queue, the threads sleeping in
+        // some wait queue will never be woken up again, those running on other
+        // harts leave the cpu on their next way back to the user space
+        let token = inner.memory_set.token();
+        let others: Vec<_> = inner.tasks.iter().skip(1).flatten().cloned().collect();
+        for task in others.iter() {
+            remove_task(task);
+            task.inner_exclusive_access().task_status = TaskStatus::Exited;
+        }
+        drop(inner);
+        for hart in harts_in(token) {
+            send_ipi(hart, IPI_RESCHEDULE);
+        }
+        for task in others.iter() {
+            task.wait_off_cpu();
+        }
+        drop(others);
+        // collect the user resources of all threads, they need to borrow the
+        // process when dropped
+        let mut inner = process.inner_exclusive_access();
+        let recycle_res: Vec<TaskUserRes> = inner
+            .tasks
+            .iter()
+            .flatten()
+            .filter_map(|task| task.inner_exclusive_access().res.take())
+            .collect();
+        drop(inner);
+        drop(recycle_res);
+        let mut inner = process.inner_exclusive_access();
+        // dealloc memory in user space,
+        // but the page table in phys memory still here and will be recycled by parent with sys_waitpid
+        inner.memory_set.recycle_data_pages();
+        inner.fd_table.clear();
+        // keep the main thread only, its kernel stack is still in use
+        inner.tasks.truncate(1);
     }
-    inner.children.clear();
-    // dealloc memory in user space,
-    // but the page table in phys memory still here and will be recycled by parent with sys_waitpid
-    inner.memory_set.recycle_data_pages();
-    drop(inner);
-    // drop task, so there is only one ref to it in it's parent
-    drop(task);
+    // drop process, so there is only one ref to it in it's parent
+    drop(process);
     // No task context
     let mut _unused = TaskContext::zero_init();
     schedule(&mut _unused as *mut _);
 }
 
-pub fn set_current_prio(prio: usize) {
-    current_task()
-        .unwrap()
-        .inner_exclusive_access()
-        .set_prio(prio);
+// Count the time of task since its last mark into its process.
+fn account_time(task: &TaskControlBlock, user: bool) {
+    let now = get_time_us();
+    let mut task_inner = task.inner_exclusive_access();
+    let elapsed = now - task_inner.time_mark_us;
+    task_inner.time_mark_us = now;
+    drop(task_inner);
+    // the threads of an exited process may still be around
+    if let Some(process) = task.process.upgrade() {
+        let rusage = &mut process.inner_exclusive_access().rusage;
+        if user {
+            rusage.utime_us += elapsed;
+        } else {
+            rusage.stime_us += elapsed;
+        }
+    }
+}
+
+// Called on every trap from the user space, which has been running since the last mark.
+pub fn current_trap_enter(is_syscall: bool) {
+    let task = current_task().unwrap();
+    account_time(&task, true);
+    if is_syscall {
+        current_process().inner_exclusive_access().rusage.syscalls += 1;
+    }
+}
+
+// Called when returning to the user space, the kernel has been running since the last mark.
+pub fn current_trap_leave() {
+    account_time(&current_task().unwrap(), false);
 }
 
 pub fn current_memory_set_mmap(
@@ -111,67 +329,142 @@ pub fn current_memory_set_mmap(
     end_va: VirtAddr,
     permission: MapPermission,
 ) -> Result<(), &'static str> {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
     inner
         .memory_set
-        .insert_framed_area(start_va, end_va, permission)
+        .insert_lazy_area(start_va, end_va, permission)
 }
 
 pub fn current_memory_set_munmap(start_va: VirtAddr, end_va: VirtAddr) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
-    inner.memory_set.remove_mapped_frames(start_va, end_va)
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let token = inner.memory_set.token();
+    let frames = match inner.memory_set.remove_mapped_frames(start_va, end_va) {
+        Some(frames) => frames,
+        None => return -1,
+    };
+    drop(inner);
+    // the frames are freed only after no hart can reach them through its TLB
+    tlb_shootdown(token);
+    drop(frames);
+    0
+}
+
+// Return true if the page fault at `va` has been resolved, i.e. a lazy page
+// has been allocated or a copy on write page has been copied.
+pub fn current_handle_page_fault(va: VirtAddr, perm: MapPermission) -> bool {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.rusage.page_faults += 1;
+    inner.memory_set.handle_page_fault(va.floor(), perm)
+}
+
+// Must be called before the kernel reads from the user buffer [ptr, ptr + len).
+pub fn current_prepare_user_read(ptr: *const u8, len: usize) {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let start = ptr as usize;
+    inner
+        .memory_set
+        .prepare_user_read(VirtAddr::from(start), VirtAddr::from(start + len));
+}
+
+// Must be called before the kernel writes to the user buffer [ptr, ptr + len).
+pub fn current_prepare_user_write(ptr: *const u8, len: usize) {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let start = ptr as usize;
+    inner
+        .memory_set
+        .prepare_user_write(VirtAddr::from(start), VirtAddr::from(start + len));
+}
+
+/// Deliver signal to process, unless the process ignores it. A pending stop
+/// is cancelled by SIGCONT and the other way round, as on Linux.
+pub fn send_signal(process: &ProcessControlBlock, signal: SignalFlags) {
+    let mut inner = process.inner_exclusive_access();
+    let sig = signal.bits().trailing_zeros() as usize;
+    if inner.signal_actions.table[sig].handler == SIG_IGN
+        && !signal.intersects(SignalFlags::SIGKILL | SignalFlags::SIGSTOP)
+    {
+        return;
+    }
+    if signal == SignalFlags::SIGCONT {
+        inner.signals.remove(SignalFlags::stop_signals());
+        // a stop not reported yet is stale once continued
+        inner.stop_signal = None;
+    } else if signal.stops() {
+        inner.signals.remove(SignalFlags::SIGCONT);
+    }
+    inner.signals.insert(signal);
+    drop(inner);
+    // interrupt a waitpid so that the signal can be handled
+    process.child_waiters.wake_all();
+    if signal.intersects(SignalFlags::SIGCONT | SignalFlags::SIGKILL) {
+        process.stop_waiters.wake_all();
+    }
 }
 
 pub fn current_add_signal(signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
-    task_inner.signals |= signal;
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.signals |= signal;
 }
 
 pub fn check_signals_error_of_current() -> Option<(i32, &'static str)> {
-    let task = current_task().unwrap();
-    let task_inner = task.inner_exclusive_access();
-    task_inner.signals.check_error()
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    process_inner.signals.check_error()
 }
 
 fn call_kernel_signal_handler(signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
     match signal {
-        SignalFlags::SIGSTOP => {
-            task_inner.frozen = true;
-            task_inner.signals ^= SignalFlags::SIGSTOP;
+        _ if signal.stops() => {
+            process_inner.frozen = true;
+            process_inner.signals ^= signal;
+            process_inner.stop_signal = Some(signal.bits().trailing_zeros() as usize);
+            process_inner.stop_reported = false;
+            // the parent may be waiting for the stop with WUNTRACED
+            let parent = process_inner.parent.as_ref().and_then(Weak::upgrade);
+            drop(process_inner);
+            if let Some(parent) = parent {
+                parent.child_waiters.wake_all();
+            }
         }
         SignalFlags::SIGCONT => {
-            if task_inner.signals.contains(SignalFlags::SIGCONT) {
-                task_inner.signals ^= SignalFlags::SIGCONT;
-                task_inner.frozen = false;
+            if process_inner.signals.contains(SignalFlags::SIGCONT) {
+                process_inner.signals ^= SignalFlags::SIGCONT;
+                process_inner.frozen = false;
+                process_inner.stop_signal = None;
             }
         }
         _ => {
             // TODO implement instantlly kill
-            task_inner.killed = true;
+            process_inner.killed = true;
         }
     }
 }
 
 fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
-    let task = current_task().unwrap();
-    let mut task_inner = task.inner_exclusive_access();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
 
-    let handler = task_inner.signal_actions.table[sig].handler;
-    if handler != 0 {
+    let handler = process_inner.signal_actions.table[sig].handler;
+    if handler == SIG_IGN {
+        process_inner.signals ^= signal;
+    } else if handler != SIG_DFL {
         // user handler
         // change current mask
-        task_inner.signal_mask = task_inner.signal_actions.table[sig].mask;
+        process_inner.signal_mask = process_inner.signal_actions.table[sig].mask;
         // handle flag
-        task_inner.handling_sig = sig as isize;
-        task_inner.signals ^= signal;
+        process_inner.handling_sig = sig as isize;
+        process_inner.signals ^= signal;
         // backup trapframe
-        let mut trap_ctx = task_inner.get_trap_cx();
-        task_inner.trap_ctx_backup = Some(*trap_ctx);
+        let mut trap_ctx = current_trap_cx();
+        process_inner.trap_ctx_backup = Some(*trap_ctx);
         // modify trapframe
         trap_ctx.sepc = handler;
         // put args (a0)
@@ -182,22 +475,29 @@ fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
     }
 }
 
+// Whether the kernel acts on the signal instead of a user handler.
+fn is_kernel_signal(signal: SignalFlags, handler: usize) -> bool {
+    signal == SignalFlags::SIGKILL
+        || signal == SignalFlags::SIGSTOP
+        || signal == SignalFlags::SIGCONT
+        || signal == SignalFlags::SIGDEF
+        // the job control stop signals stop the process by default
+        || (signal.stops() && handler == SIG_DFL)
+}
+
 fn check_pending_signals() {
     for sig in 0..(MAX_SIG + 1) {
-        let task = current_task().unwrap();
-        let task_inner = task.inner_exclusive_access();
+        let process = current_process();
+        let process_inner = process.inner_exclusive_access();
         let signal = SignalFlags::from_bits(1 << sig).unwrap();
+        let handler = process_inner.signal_actions.table[sig].handler;
         // if we should handle this signal
-        if task_inner.signals.contains(signal) && (!task_inner.signal_mask.contains(signal)) {
+        if process_inner.signals.contains(signal) && (!process_inner.signal_mask.contains(signal)) {
             // current no handling signal
-            if task_inner.handling_sig == -1 {
-                drop(task_inner);
-                drop(task);
-                if signal == SignalFlags::SIGKILL
-                    || signal == SignalFlags::SIGSTOP
-                    || signal == SignalFlags::SIGCONT
-                    || signal == SignalFlags::SIGDEF
-                {
+            if process_inner.handling_sig == -1 {
+                drop(process_inner);
+                drop(process);
+                if is_kernel_signal(signal, handler) {
                     // signal is a kernel signal
                     call_kernel_signal_handler(signal);
                 } else {
@@ -207,17 +507,13 @@ fn check_pending_signals() {
                 }
             } else {
                 // not already handled
-                if !task_inner.signal_actions.table[task_inner.handling_sig as usize]
+                if !process_inner.signal_actions.table[process_inner.handling_sig as usize]
                     .mask
                     .contains(signal)
                 {
-                    drop(task_inner);
-                    drop(task);
-                    if signal == SignalFlags::SIGKILL
-                        || signal == SignalFlags::SIGSTOP
-                        || signal == SignalFlags::SIGCONT
-                        || signal == SignalFlags::SIGDEF
-                    {
+                    drop(process_inner);
+                    drop(process);
+                    if is_kernel_signal(signal, handler) {
                         // signal is a kernel signal
                         call_kernel_signal_handler(signal);
                     } else {
@@ -231,21 +527,31 @@ fn check_pending_signals() {
     }
 }
 
-// loop check until not frozen or killed
+// handle the pending signals, sleeping while frozen until continued or killed
 pub fn handle_signals() {
     check_pending_signals();
     loop {
-        let task = current_task().unwrap();
-        let task_inner = task.inner_exclusive_access();
-        let frozen_flag = task_inner.frozen;
-        let killed_flag = task_inner.killed;
-        drop(task_inner);
-        drop(task);
-        if (!frozen_flag) || killed_flag {
+        let process = current_process();
+        let process_inner = process.inner_exclusive_access();
+        if !process_inner.frozen || process_inner.killed {
             break;
         }
-        check_pending_signals();
-        suspend_current_and_run_next()
+        if process_inner
+            .signals
+            .intersects(SignalFlags::SIGCONT | SignalFlags::SIGKILL)
+        {
+            drop(process_inner);
+            drop(process);
+            check_pending_signals();
+            continue;
+        }
+        // send_signal takes the lock before waking, so it can not slip
+        // between the check above and the sleep
+        let stop_waiters = Arc::clone(&process.stop_waiters);
+        stop_waiters.wait_with(move || {
+            drop(process_inner);
+            true
+        });
     }
 }
 
@@ -1,104 +0,0 @@
-use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE};
-use crate::mm::{MapPermission, VirtAddr, KERNEL_SPACE};
-use crate::sync::UPSafeCell;
-use alloc::vec::Vec;
-use lazy_static::*;
-
-pub struct PidHandle(pub usize);
-
-struct PidAllocator {
-    current: usize,
-    recycled: Vec<usize>,
-}
-
-impl PidAllocator {
-    pub fn new() -> Self {
-        PidAllocator {
-            current: 0,
-            recycled: Vec::new(),
-        }
-    }
-    pub fn alloc(&mut self) -> PidHandle {
-        if let Some(pid) = self.recycled.pop() {
-            PidHandle(pid)
-        } else {
-            self.current += 1;
-            PidHandle(self.current - 1)
-        }
-    }
-    pub fn dealloc(&mut self, pid: usize) {
-        assert!(pid < self.current);
-        assert!(
-            !self.recycled.iter().any(|ppid| *ppid == pid),
-            "pid {} has been deallocated!",
-            pid
-        );
-        self.recycled.push(pid);
-    }
-}
-
-lazy_static! {
-    static ref PID_ALLOCATOR: UPSafeCell<PidAllocator> =
-        unsafe { UPSafeCell::new(PidAllocator::new()) };
-}
-
-impl Drop for PidHandle {
-    fn drop(&mut self) {
-        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
-    }
-}
-
-pub fn pid_alloc() -> PidHandle {
-    PID_ALLOCATOR.exclusive_access().alloc()
-}
-
-pub fn kernel_stack_position(pid: usize) -> (usize, usize) {
-    let top = TRAMPOLINE - pid * (KERNEL_STACK_SIZE + PAGE_SIZE);
-    let bottom = top - KERNEL_STACK_SIZE;
-    (bottom, top)
-}
-
-pub struct KernelStack {
-    pid: usize,
-}
-
-impl KernelStack {
-    pub fn new(pid: &PidHandle) -> Self {
-        let pid = pid.0;
-        let (bottom, top) = kernel_stack_position(pid);
-        if let Err(e) = KERNEL_SPACE.exclusive_access().insert_framed_area(
-            bottom.into(),
-            top.into(),
-            MapPermission::R | MapPermission::W,
-        ) {
-            error!("Cannot allocate kernel stack for {}, {}", pid, e);
-            return Self { pid: usize::MAX };
-        }
-        Self { pid }
-    }
-
-    pub fn get_top(&self) -> usize {
-        let (_, top) = kernel_stack_position(self.pid);
-        top
-    }
-
-    pub fn put_on_top<T>(&self, value: T) -> *mut T
-    where
-        T: Sized,
-    {
-        let top = self.get_top();
-        let ptr_mut = (top - core::mem::size_of::<T>()) as *mut T;
-        unsafe { *ptr_mut = value };
-        ptr_mut
-    }
-}
-
-impl Drop for KernelStack {
-    fn drop(&mut self) {
-        let (kernel_stack_bottom, _) = kernel_stack_position(self.pid);
-        let kernel_stack_bottom_va: VirtAddr = kernel_stack_bottom.into();
-        KERNEL_SPACE
-            .exclusive_access()
-            .remove_area_with_start_vpn(kernel_stack_bottom_va.into());
-    }
-}
@@ -0,0 +1,369 @@
+use crate::fs::{FileDescriptor, HeldInode, Stdin, Stdout, ROOT_INODE};
+use crate::mm::{translated_refmut, MemorySet, VirtAddr, KERNEL_SPACE};
+use crate::sync::{Condvar, DeadlockDetector, Mutex, Semaphore, SpinLock, SpinLockGuard};
+use crate::trap::{trap_handler, TrapContext};
+
+use super::id::{pid_alloc, PidHandle, RecycleAllocator};
+use super::manager::insert_into_pid2process;
+use super::{
+    Credential, MailBox, RUsage, SignalAction, SignalActions, SignalFlags, TaskControlBlock,
+    WaitQueue, SIG_IGN,
+};
+
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+
+// the resources shared by all threads of a process
+pub struct ProcessControlBlock {
+    // immutable
+    pub pid: PidHandle,
+    // threads in waitpid, woken up when a child exits or a signal arrives
+    pub child_waiters: Arc<WaitQueue>,
+    // threads of a stopped process, woken up by SIGCONT or SIGKILL
+    pub stop_waiters: Arc<WaitQueue>,
+    // mutable
+    inner: SpinLock<ProcessControlBlockInner>,
+}
+
+pub struct ProcessControlBlockInner {
+    pub is_zombie: bool,
+    pub memory_set: MemorySet,
+    pub parent: Option<Weak<ProcessControlBlock>>,
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    pub exit_code: i32,
+    pub fd_table: Vec<Option<FileDescriptor>>,
+    pub signals: SignalFlags,
+    pub signal_mask: SignalFlags,
+    // the signal which is being handling
+    pub handling_sig: isize,
+    // Signal actions
+    pub signal_actions: SignalActions,
+    // if the task is killed
+    pub killed: bool,
+    // if the task is frozen by a signal
+    pub frozen: bool,
+    // the signal which has stopped the process, none once it is continued
+    pub stop_signal: Option<usize>,
+    // whether the stop has been reported by waitpid with WUNTRACED
+    pub stop_reported: bool,
+    // the process group and the session, for job control
+    pub pgid: usize,
+    pub sid: usize,
+    pub trap_ctx_backup: Option<TrapContext>,
+    pub mailbox: MailBox,
+    // current working directory
+    pub cwd: HeldInode,
+    // who the process acts for, kept across fork and exec
+    pub cred: Credential,
+    // threads indexed by tid, None if the slot has been reaped
+    pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
+    pub task_res_allocator: RecycleAllocator,
+    // synchronization primitives shared by the threads, indexed by their ids
+    pub mutex_list: Vec<Option<Arc<dyn Mutex>>>,
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    // tracks who holds and waits for the mutexes and semaphores
+    pub deadlock_detector: DeadlockDetector,
+    pub rusage: RUsage,
+    // the sum of the reaped children and their descendants
+    pub children_rusage: RUsage,
+}
+
+impl ProcessControlBlockInner {
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+
+    pub fn alloc_tid(&mut self) -> usize {
+        self.task_res_allocator.alloc()
+    }
+
+    pub fn dealloc_tid(&mut self, tid: usize) {
+        self.deadlock_detector.remove_thread(tid);
+        self.task_res_allocator.dealloc(tid)
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.tasks.iter().filter(|task| task.is_some()).count()
+    }
+
+    pub fn get_task(&self, tid: usize) -> Arc<TaskControlBlock> {
+        self.tasks[tid].as_ref().unwrap().clone()
+    }
+}
+
+impl ProcessControlBlock {
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, ProcessControlBlockInner> {
+        self.inner.lock()
+    }
+
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+
+    // create a process with a single main thread, the thread is not added to the scheduler
+    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+        // map user space memory set
+        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        // alloc a pid
+        let pid_handle = pid_alloc();
+        // it leads a new session and group
+        let pid = pid_handle.0;
+        let process = Arc::new(Self {
+            pid: pid_handle,
+            child_waiters: Arc::new(WaitQueue::new()),
+            stop_waiters: Arc::new(WaitQueue::new()),
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
+                memory_set,
+                parent: None,
+                children: Vec::new(),
+                exit_code: 0,
+                fd_table: vec![
+                    // 0 -> stdin
+                    Some(FileDescriptor::new(Arc::new(Stdin::new()), false)),
+                    // 1 -> stdout
+                    Some(FileDescriptor::new(Arc::new(Stdout), false)),
+                    // 2 -> stderr
+                    Some(FileDescriptor::new(Arc::new(Stdout), false)),
+                ],
+                signals: SignalFlags::empty(),
+                signal_mask: SignalFlags::empty(),
+                handling_sig: -1,
+                signal_actions: SignalActions::default(),
+                killed: false,
+                frozen: false,
+                stop_signal: None,
+                stop_reported: false,
+                pgid: pid,
+                sid: pid,
+                trap_ctx_backup: None,
+                mailbox: MailBox::new(),
+                cwd: HeldInode::new(ROOT_INODE.clone()),
+                cred: Credential::root(),
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detector: DeadlockDetector::new(),
+                rusage: RUsage::new(),
+                children_rusage: RUsage::new(),
+            }),
+        });
+        // create the main thread, this maps its user stack and trap context
+        let task =
+            Arc::new(TaskControlBlock::new(Arc::clone(&process), ustack_base, true).unwrap());
+        // prepare TrapContext in user space
+        let task_inner = task.inner_exclusive_access();
+        let trap_cx = task_inner.get_trap_cx();
+        let ustack_top = task_inner.res.as_ref().unwrap().ustack_top();
+        let kstack_top = task.kstack.get_top();
+        drop(task_inner);
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            ustack_top,
+            KERNEL_SPACE.lock().token(),
+            kstack_top,
+            trap_handler as usize,
+        );
+        process.inner_exclusive_access().tasks.push(Some(task));
+        insert_into_pid2process(process.getpid(), Arc::clone(&process));
+        process
+    }
+
+    // Only a single-threaded process can exec, the main thread keeps its tid
+    // and kernel stack but gets a fresh user stack and trap context.
+    pub fn exec(self: &Arc<Self>, elf_data: &[u8], args: Vec<String>) {
+        assert_eq!(self.inner_exclusive_access().thread_count(), 1);
+        // memory_set with elf program headers/trampoline
+        let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
+        let new_token = memory_set.token();
+        // substitute memory_set
+        let mut inner = self.inner_exclusive_access();
+        inner.memory_set = memory_set;
+        // the handlers are gone with the old image, the ignored signals stay ignored
+        for action in inner.signal_actions.table.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+        // so are the descriptors marked close-on-exec
+        for fd in inner.fd_table.iter_mut() {
+            if fd.as_ref().map_or(false, |fd| fd.cloexec) {
+                *fd = None;
+            }
+        }
+        drop(inner);
+        // alloc user resources for the main thread again in the new memory_set
+        let task = self.inner_exclusive_access().get_task(0);
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.res.as_mut().unwrap().ustack_base = ustack_base;
+        // the old image is gone, there is no way back if this fails
+        task_inner
+            .res
+            .as_ref()
+            .unwrap()
+            .alloc_user_res()
+            .expect("no memory for the user stack of exec");
+        task_inner.trap_cx_ppn = task_inner.res.as_ref().unwrap().trap_cx_ppn();
+        let mut user_sp = task_inner.res.as_ref().unwrap().ustack_top();
+        // the user stack is lazy, fault in the pages holding the arguments
+        let args_size = (args.len() + 1) * core::mem::size_of::<usize>()
+            + args.iter().map(|arg| arg.len() + 1).sum::<usize>();
+        self.inner_exclusive_access()
+            .memory_set
+            .prepare_user_write(VirtAddr::from(user_sp - args_size), VirtAddr::from(user_sp));
+        // push arguments on user stack
+        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
+        let argv_base = user_sp;
+        let mut argv: Vec<_> = (0..=args.len())
+            .map(|arg| {
+                translated_refmut(
+                    new_token,
+                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
+                )
+                .unwrap()
+            })
+            .collect();
+        *argv[args.len()] = 0;
+        for i in 0..args.len() {
+            user_sp -= args[i].len() + 1;
+            *argv[i] = user_sp;
+            let mut p = user_sp;
+            for c in args[i].as_bytes() {
+                *translated_refmut(new_token, p as *mut u8).unwrap() = *c;
+                p += 1;
+            }
+            *translated_refmut(new_token, p as *mut u8).unwrap() = 0;
+        }
+        // make the user_sp aligned to 8B for k210 platform
+        user_sp -= user_sp % core::mem::size_of::<usize>();
+        // initialize trap_cx
+        let mut trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.lock().token(),
+            task.kstack.get_top(),
+            trap_handler as usize,
+        );
+        // argc argv
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        *task_inner.get_trap_cx() = trap_cx;
+    }
+
+    // Only a single-threaded process can fork, the child gets a copy of the
+    // main thread.
+    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+        let mut parent = self.inner_exclusive_access();
+        assert_eq!(parent.thread_count(), 1);
+        // make a copy of memory space, user stacks and trap contexts included
+        let memory_set = MemorySet::from_existed_userspace(&mut parent.memory_set);
+        // alloc a pid
+        let pid = pid_alloc();
+        // copy fds that are not busy(Option::None) from parent, with their flags
+        let new_fd_table = parent.fd_table.clone();
+        // create child process pcb
+        let child = Arc::new(Self {
+            pid,
+            child_waiters: Arc::new(WaitQueue::new()),
+            stop_waiters: Arc::new(WaitQueue::new()),
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_zombie: false,
+                memory_set,
+                parent: Some(Arc::downgrade(self)),
+                children: Vec::new(),
+                exit_code: 0,
+                fd_table: new_fd_table,
+                // TODO shall we inherit from parent?
+                signals: SignalFlags::empty(),
+                // inherit the signal_mask and signal_action
+                signal_mask: parent.signal_mask,
+                handling_sig: -1,
+                signal_actions: parent.signal_actions.clone(),
+                killed: false,
+                frozen: false,
+                stop_signal: None,
+                stop_reported: false,
+                pgid: parent.pgid,
+                sid: parent.sid,
+                trap_ctx_backup: None,
+                mailbox: MailBox::new(),
+                cwd: parent.cwd.clone(),
+                cred: parent.cred,
+                tasks: Vec::new(),
+                task_res_allocator: RecycleAllocator::new(),
+                // kernel locks are not shared with the child
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detector: DeadlockDetector::new(),
+                rusage: RUsage::new(),
+                children_rusage: RUsage::new(),
+            }),
+        });
+        // add child
+        parent.children.push(Arc::clone(&child));
+        let parent_task = parent.get_task(0);
+        drop(parent);
+        // create main thread of child process, its user resources have been copied
+        let parent_task_inner = parent_task.inner_exclusive_access();
+        let task = Arc::new(
+            TaskControlBlock::new(
+                Arc::clone(&child),
+                parent_task_inner.res.as_ref().unwrap().ustack_base(),
+                false,
+            )
+            .unwrap(),
+        );
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.priority = parent_task_inner.priority;
+        task_inner.pass = parent_task_inner.pass;
+        task_inner.rt = parent_task_inner.rt.map(|rt| rt.restarted());
+        drop(parent_task_inner);
+        // modify kstack_top in trap_cx of this thread
+        let trap_cx = task_inner.get_trap_cx();
+        trap_cx.kernel_sp = task.kstack.get_top();
+        drop(task_inner);
+        // attach task to child process
+        child
+            .inner_exclusive_access()
+            .tasks
+            .push(Some(Arc::clone(&task)));
+        insert_into_pid2process(child.getpid(), Arc::clone(&child));
+        child
+    }
+
+    // create a child process directly from elf data, without copying the parent's memory
+    pub fn spawn(self: &Arc<Self>, elf_data: &[u8]) -> Arc<Self> {
+        let child = Self::new(elf_data);
+        let mut inner = child.inner_exclusive_access();
+        let parent = self.inner_exclusive_access();
+        inner.parent = Some(Arc::downgrade(self));
+        inner.cwd = parent.cwd.clone();
+        inner.cred = parent.cred;
+        // as a forked child, it stays in the group and session of the parent
+        inner.pgid = parent.pgid;
+        inner.sid = parent.sid;
+        drop(parent);
+        drop(inner);
+        self.inner_exclusive_access()
+            .children
+            .push(Arc::clone(&child));
+        child
+    }
+}
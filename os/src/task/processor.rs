@@ -1,9 +1,19 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::cell::RefMut;
 use lazy_static::*;
 
-use crate::sync::UPSafeCell;
+use crate::config::MAX_HARTS;
+use crate::fs::poll_stdin;
+use crate::smp::hart_id;
+use crate::sync::{park_held_locks, UPSafeCell};
+use crate::timer::{check_timer, get_time_us};
 
-use super::{task::TaskControlBlock, TaskContext, TaskStatus, TrapContext, __switch, fetch_task};
+use super::{
+    account_time, fetch_task, ProcessControlBlock, TaskContext, TaskControlBlock, TaskStatus,
+    TrapContext, __switch,
+};
 
 pub struct Processor {
     current: Option<Arc<TaskControlBlock>>,
@@ -32,24 +42,35 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+    // one for each hart, only ever touched by its own hart
+    static ref PROCESSORS: Vec<UPSafeCell<Processor>> = (0..MAX_HARTS)
+        .map(|_| unsafe { UPSafeCell::new(Processor::new()) })
+        .collect();
+}
+
+fn processor() -> RefMut<'static, Processor> {
+    PROCESSORS[hart_id()].exclusive_access()
 }
 
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
+    processor().take_current()
 }
 
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().clone_current()
+    processor().clone_current()
+}
+
+pub fn current_process() -> Arc<ProcessControlBlock> {
+    current_task().unwrap().process.upgrade().unwrap()
 }
 
 pub fn current_pid() -> usize {
-    current_task().unwrap().getpid()
+    current_process().getpid()
 }
 
 pub fn current_user_token() -> usize {
     let task = current_task().unwrap();
-    let token = task.inner_exclusive_access().get_user_token();
+    let token = task.get_user_token();
     token
 }
 
@@ -60,32 +81,72 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
         .get_trap_cx()
 }
 
-// idle task
+// where the trap context of the current thread lives in user space
+pub fn current_trap_cx_user_va() -> usize {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .res
+        .as_ref()
+        .unwrap()
+        .trap_cx_user_va()
+}
+
+// idle task, every hart runs one
 pub fn run_tasks() {
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
+        let mut processor = processor();
         if let Some(task) = fetch_task() {
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
-            // access coming task TCB exclusively
-            let mut task_inner = task.inner_exclusive_access();
+            // access coming task TCB exclusively, a thread woken up right after
+            // it blocked may still be switching out on another hart
+            let mut task_inner = loop {
+                let task_inner = task.inner_exclusive_access();
+                if !task_inner.on_cpu {
+                    break task_inner;
+                }
+                drop(task_inner);
+                core::hint::spin_loop();
+            };
+            // its process may have exited after it was fetched
+            if task_inner.task_status != TaskStatus::Ready {
+                continue;
+            }
             let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
             task_inner.task_status = TaskStatus::Running;
+            task_inner.on_cpu = true;
+            // the time before now has not been spent by this thread
+            task_inner.time_mark_us = get_time_us();
             drop(task_inner);
             // release coming task TCB manually
-            processor.current = Some(task);
+            processor.current = Some(Arc::clone(&task));
             // release processor manually
             drop(processor);
             unsafe {
+                // the kernel stack may have been mapped after this hart last
+                // looked at it
+                asm!("sfence.vma");
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+            // the thread has given up the cpu in the kernel
+            account_time(&task, false);
+            // its context has been saved, other harts may switch to it now
+            task.inner_exclusive_access().on_cpu = false;
+        } else {
+            drop(processor);
+            // nothing is ready, the threads waiting for the time or
+            // the console may be woken up
+            check_timer();
+            poll_stdin();
         }
     }
 }
 
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    let mut processor = processor();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
+    park_held_locks();
     unsafe {
         __switch(switched_task_cx_ptr, idle_task_cx_ptr);
     }
@@ -0,0 +1,29 @@
+// resources consumed by the threads of a process, the times are in microseconds,
+// it is also what getrusage writes to the user space
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RUsage {
+    pub utime_us: usize,
+    pub stime_us: usize,
+    pub syscalls: usize,
+    pub page_faults: usize,
+    // the cpu is given up by the thread itself
+    pub voluntary_switches: usize,
+    // the cpu is taken away by the timer
+    pub involuntary_switches: usize,
+}
+
+impl RUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, other: &RUsage) {
+        self.utime_us += other.utime_us;
+        self.stime_us += other.stime_us;
+        self.syscalls += other.syscalls;
+        self.page_faults += other.page_faults;
+        self.voluntary_switches += other.voluntary_switches;
+        self.involuntary_switches += other.involuntary_switches;
+    }
+}
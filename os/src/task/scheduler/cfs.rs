@@ -0,0 +1,76 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+
+use super::Scheduler;
+use crate::task::{TaskControlBlock, DEFAULT_PRIORITY};
+use crate::timer::get_time_us;
+
+// a running thread is preempted only if it is ahead by this much, in microseconds
+const MIN_GRANULARITY_US: usize = 1000;
+
+// The ready thread with the smallest virtual runtime runs next, the virtual runtime
+// grows by the time spent on the cpu divided by priority / DEFAULT_PRIORITY,
+// so it goes as fast as the real time for the default priority.
+// It is kept in the pass of the thread.
+pub struct CfsScheduler {
+    // keyed by (virtual runtime, kernel stack id)
+    ready: BTreeMap<(usize, usize), Arc<TaskControlBlock>>,
+    // the smallest virtual runtime seen, a new or waking thread starts from here
+    min_vruntime: usize,
+}
+
+impl CfsScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready: BTreeMap::new(),
+            min_vruntime: 0,
+        }
+    }
+
+    // Add the time spent by the running task since the last charge to its virtual
+    // runtime and return the new virtual runtime. Each thread keeps the time of
+    // its own last charge, the harts pick and charge threads independently.
+    fn charge(&mut self, task: &Arc<TaskControlBlock>) -> usize {
+        let now = get_time_us();
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.pass +=
+            (now - task_inner.charged_at_us) * DEFAULT_PRIORITY / task_inner.priority;
+        task_inner.charged_at_us = now;
+        task_inner.pass
+    }
+}
+
+impl Scheduler for CfsScheduler {
+    fn enqueue(&mut self, task: Arc<TaskControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.pass = task_inner.pass.max(self.min_vruntime);
+        let vruntime = task_inner.pass;
+        drop(task_inner);
+        self.ready.insert((vruntime, task.kstack_id()), task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let key = *self.ready.keys().next()?;
+        self.min_vruntime = self.min_vruntime.max(key.0);
+        let task = self.ready.remove(&key)?;
+        task.inner_exclusive_access().charged_at_us = get_time_us();
+        Some(task)
+    }
+
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        let id = task.kstack_id();
+        self.ready.retain(|&(_, kstack_id), _| kstack_id != id);
+    }
+
+    fn on_tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let vruntime = self.charge(task);
+        match self.ready.keys().next() {
+            Some(&(first, _)) => first + MIN_GRANULARITY_US < vruntime,
+            None => false,
+        }
+    }
+
+    fn on_yield(&mut self, task: &Arc<TaskControlBlock>) {
+        self.charge(task);
+    }
+}
@@ -0,0 +1,74 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+
+const LEVELS: usize = 4;
+// all threads go back to the top level this often, so that none starves
+const BOOST_TICKS: usize = 1000;
+
+// A thread starts at level 0 and the non-empty level with the smallest number runs first.
+// A thread which has used up the time slice of its level, 2^level ticks, moves one level
+// down, yielding or blocking does not reset the ticks used.
+pub struct MlfqScheduler {
+    queues: [VecDeque<Arc<TaskControlBlock>>; LEVELS],
+    ticks: usize,
+}
+
+impl MlfqScheduler {
+    pub fn new() -> Self {
+        Self {
+            queues: Default::default(),
+            ticks: 0,
+        }
+    }
+
+    fn boost(&mut self) {
+        for level in 1..LEVELS {
+            while let Some(task) = self.queues[level].pop_front() {
+                let mut task_inner = task.inner_exclusive_access();
+                task_inner.level = 0;
+                task_inner.slice_ticks = 0;
+                drop(task_inner);
+                self.queues[0].push_back(task);
+            }
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn enqueue(&mut self, task: Arc<TaskControlBlock>) {
+        let level = task.inner_exclusive_access().level;
+        self.queues[level].push_back(task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        for queue in self.queues.iter_mut() {
+            queue.retain(|t| !Arc::ptr_eq(t, task));
+        }
+    }
+
+    fn on_tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut task_inner = task.inner_exclusive_access();
+        self.ticks += 1;
+        if self.ticks % BOOST_TICKS == 0 {
+            task_inner.level = 0;
+            task_inner.slice_ticks = 0;
+            drop(task_inner);
+            self.boost();
+            return true;
+        }
+        task_inner.slice_ticks += 1;
+        if task_inner.slice_ticks < 1 << task_inner.level {
+            return false;
+        }
+        task_inner.level = (task_inner.level + 1).min(LEVELS - 1);
+        task_inner.slice_ticks = 0;
+        true
+    }
+}
@@ -0,0 +1,57 @@
+//! Scheduling policies, one of them is selected at build time by the
+//! `sched-rr`, `sched-stride`, `sched-mlfq` or `sched-cfs` feature,
+//! the stride scheduler is used if none is given. Real-time threads are kept
+//! apart by the [`RtScheduler`] and always run first.
+
+use alloc::sync::Arc;
+
+use super::TaskControlBlock;
+
+#[cfg(feature = "sched-cfs")]
+mod cfs;
+#[cfg(feature = "sched-mlfq")]
+mod mlfq;
+#[cfg(feature = "sched-rr")]
+mod rr;
+mod rt;
+#[cfg(not(any(feature = "sched-rr", feature = "sched-mlfq", feature = "sched-cfs")))]
+mod stride;
+
+#[cfg(any(
+    all(feature = "sched-rr", feature = "sched-stride"),
+    all(feature = "sched-rr", feature = "sched-mlfq"),
+    all(feature = "sched-rr", feature = "sched-cfs"),
+    all(feature = "sched-stride", feature = "sched-mlfq"),
+    all(feature = "sched-stride", feature = "sched-cfs"),
+    all(feature = "sched-mlfq", feature = "sched-cfs"),
+))]
+compile_error!("select at most one of the sched-* features");
+
+pub use rt::{RtParams, RtPolicy, RtScheduler, MAX_RT_PRIORITY};
+
+#[cfg(feature = "sched-cfs")]
+pub type SelectedScheduler = cfs::CfsScheduler;
+#[cfg(feature = "sched-mlfq")]
+pub type SelectedScheduler = mlfq::MlfqScheduler;
+#[cfg(feature = "sched-rr")]
+pub type SelectedScheduler = rr::RoundRobinScheduler;
+#[cfg(not(any(feature = "sched-rr", feature = "sched-mlfq", feature = "sched-cfs")))]
+pub type SelectedScheduler = stride::StrideScheduler;
+
+/// A scheduling policy, it owns the ready threads and decides which one runs next.
+/// The ready threads are told apart by their kernel stack ids.
+pub trait Scheduler {
+    /// A thread becomes ready: it is new, preempted, yielding or woken up.
+    fn enqueue(&mut self, task: Arc<TaskControlBlock>);
+    /// Take the thread to run next out of the ready set.
+    fn pick_next(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// Take a ready thread out of the ready set, its process has exited.
+    fn remove(&mut self, task: &Arc<TaskControlBlock>);
+    /// A timer tick while task is running, return true if it should give up the cpu.
+    fn on_tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        true
+    }
+    /// The running task gives up the cpu other than on a tick: it yields,
+    /// blocks, or makes way for a real-time thread.
+    fn on_yield(&mut self, _task: &Arc<TaskControlBlock>) {}
+}
@@ -0,0 +1,32 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+
+// the ready threads run in turn for one tick each, priorities are ignored
+pub struct RoundRobinScheduler {
+    queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl RoundRobinScheduler {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for RoundRobinScheduler {
+    fn enqueue(&mut self, task: Arc<TaskControlBlock>) {
+        self.queue.push_back(task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.queue.pop_front()
+    }
+
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.queue.retain(|t| !Arc::ptr_eq(t, task));
+    }
+}
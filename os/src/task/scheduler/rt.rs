@@ -0,0 +1,162 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+
+use crate::task::TaskControlBlock;
+use crate::timer::get_time_us;
+
+pub const MAX_RT_PRIORITY: usize = 99;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RtPolicy {
+    // runs until it blocks or yields, the higher priority first
+    Fifo,
+    // earliest deadline first, ahead of all fifo threads
+    Deadline,
+}
+
+/// The real-time parameters of a thread and the state of its current period.
+/// A thread may run for runtime_us in every period_us, it is throttled until
+/// the next period once the budget is used up.
+#[derive(Copy, Clone, Debug)]
+pub struct RtParams {
+    pub policy: RtPolicy,
+    // fifo only, 1..=MAX_RT_PRIORITY
+    pub priority: usize,
+    pub runtime_us: usize,
+    // relative to the start of a period, deadline only
+    pub deadline_us: usize,
+    pub period_us: usize,
+    period_start_us: usize,
+    budget_us: usize,
+    // when the thread was last charged, it may become real-time while running
+    charged_at_us: usize,
+}
+
+impl RtParams {
+    pub fn new(
+        policy: RtPolicy,
+        priority: usize,
+        runtime_us: usize,
+        deadline_us: usize,
+        period_us: usize,
+    ) -> Self {
+        let now = get_time_us();
+        Self {
+            policy,
+            priority,
+            runtime_us,
+            deadline_us,
+            period_us,
+            period_start_us: now,
+            budget_us: runtime_us,
+            charged_at_us: now,
+        }
+    }
+
+    /// The same parameters with a period starting now, for a new thread.
+    pub fn restarted(&self) -> Self {
+        Self::new(
+            self.policy,
+            self.priority,
+            self.runtime_us,
+            self.deadline_us,
+            self.period_us,
+        )
+    }
+
+    // Move on to the period now is in and refill the budget, if the current one is over.
+    fn replenish(&mut self, now: usize) {
+        if now >= self.next_period_us() {
+            let periods = (now - self.period_start_us) / self.period_us;
+            self.period_start_us += periods * self.period_us;
+            self.budget_us = self.runtime_us;
+        }
+    }
+
+    // Take the time since the last charge out of the budget.
+    fn charge(&mut self, now: usize) {
+        self.replenish(now);
+        self.budget_us = self.budget_us.saturating_sub(now - self.charged_at_us);
+        self.charged_at_us = now;
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.budget_us == 0
+    }
+
+    pub fn next_period_us(&self) -> usize {
+        self.period_start_us + self.period_us
+    }
+
+    // Ready threads are ordered by this key, deadline threads by their absolute
+    // deadlines come first, then fifo threads by their priorities.
+    fn key(&self) -> (usize, usize) {
+        match self.policy {
+            RtPolicy::Deadline => (0, self.period_start_us + self.deadline_us),
+            RtPolicy::Fifo => (1, MAX_RT_PRIORITY - self.priority),
+        }
+    }
+}
+
+/// The ready real-time threads, any of them runs before the threads of the
+/// selected scheduler. A thread which has used up its budget is throttled by
+/// the timer tick, the budget may be overrun by up to one tick.
+pub struct RtScheduler {
+    // keyed by (class, deadline or priority, arrival), the arrival keeps fifo order
+    ready: BTreeMap<(usize, usize, usize), Arc<TaskControlBlock>>,
+    arrivals: usize,
+}
+
+impl RtScheduler {
+    pub fn new() -> Self {
+        Self {
+            ready: BTreeMap::new(),
+            arrivals: 0,
+        }
+    }
+
+    pub fn enqueue(&mut self, task: Arc<TaskControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        let rt = task_inner.rt.as_mut().unwrap();
+        rt.replenish(get_time_us());
+        let (class, order) = rt.key();
+        drop(task_inner);
+        self.arrivals += 1;
+        self.ready.insert((class, order, self.arrivals), task);
+    }
+
+    pub fn pick_next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let key = *self.ready.keys().next()?;
+        let task = self.ready.remove(&key)?;
+        task.inner_exclusive_access()
+            .rt
+            .as_mut()
+            .unwrap()
+            .charged_at_us = get_time_us();
+        Some(task)
+    }
+
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        self.ready.retain(|_, t| !Arc::ptr_eq(t, task));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty()
+    }
+
+    /// Take the time spent by the running real-time thread since the last charge
+    /// out of its budget.
+    pub fn charge(&self, task: &Arc<TaskControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.rt.as_mut().unwrap().charge(get_time_us());
+    }
+
+    /// Whether a ready thread should run before the running real-time thread.
+    pub fn preempts(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let (class, order) = task.inner_exclusive_access().rt.as_ref().unwrap().key();
+        match self.ready.keys().next() {
+            Some(&(first_class, first_order, _)) => (first_class, first_order) < (class, order),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+
+use stride_sched::StrideQueue;
+
+use super::Scheduler;
+use crate::task::TaskControlBlock;
+
+// every thread runs in turn with the smallest pass, which grows by
+// BIG_STRIDE / priority each time the thread gets ready
+pub struct StrideScheduler {
+    queue: StrideQueue,
+    // ready threads, keyed by their kernel stack id
+    ready: BTreeMap<usize, Arc<TaskControlBlock>>,
+}
+
+impl StrideScheduler {
+    pub fn new() -> Self {
+        Self {
+            queue: StrideQueue::new(),
+            ready: BTreeMap::new(),
+        }
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn enqueue(&mut self, task: Arc<TaskControlBlock>) {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.pass = self
+            .queue
+            .push(task.kstack_id(), task_inner.pass, task_inner.priority);
+        drop(task_inner);
+        self.ready.insert(task.kstack_id(), task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let next_id = self.queue.pop()?;
+        self.ready.remove(&next_id)
+    }
+
+    // the entry is taken out of the queue too, the same kernel stack id may be
+    // queued again with another pass
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) {
+        if self.ready.remove(&task.kstack_id()).is_some() {
+            self.queue.remove(task.kstack_id());
+        }
+    }
+}
@@ -40,6 +40,15 @@ bitflags! {
 }
 
 impl SignalFlags {
+    /// The signals which stop the process unless it handles them.
+    pub fn stop_signals() -> Self {
+        Self::SIGSTOP | Self::SIGTSTP | Self::SIGTTIN | Self::SIGTTOU
+    }
+
+    pub fn stops(&self) -> bool {
+        Self::stop_signals().contains(*self)
+    }
+
     pub fn check_error(&self) -> Option<(i32, &'static str)> {
         if self.contains(Self::SIGINT) {
             Some((-2, "Killed, SIGINT=2"))
@@ -1,60 +1,62 @@
-use crate::config::TRAP_CONTEXT;
-use crate::fs::{File, Stdin, Stdout};
-use crate::mm::{translated_refmut, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
-use crate::sync::UPSafeCell;
-use crate::trap::{trap_handler, TrapContext};
+use crate::errno::SysError;
+use crate::mm::PhysPageNum;
+use crate::sync::{SpinLock, SpinLockGuard};
+use crate::trap::TrapContext;
 
-use super::pid::{pid_alloc, KernelStack, PidHandle};
-use super::{SignalActions, SignalFlags, TaskContext};
+use super::id::{kstack_alloc, KernelStack, TaskUserRes};
+use super::scheduler::RtParams;
+use super::{ProcessControlBlock, TaskContext};
 
-use alloc::{
-    string::String,
-    sync::{Arc, Weak},
-    vec,
-    vec::Vec,
-};
-use core::cell::RefMut;
+use alloc::sync::{Arc, Weak};
+
+// a thread with a higher priority gets more cpu time, the stride scheduler
+// needs the priorities to be in MIN_PRIORITY..=MAX_PRIORITY
+pub const MIN_PRIORITY: usize = 2;
+pub const MAX_PRIORITY: usize = 1024;
+pub const DEFAULT_PRIORITY: usize = 16;
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum TaskStatus {
     UnInit,
     Ready,
     Running,
-    Zombie,
+    Blocked,
     Exited,
 }
 
+// a thread of a process, the unit the scheduler works on
 pub struct TaskControlBlock {
     // immutable
-    pub pid: PidHandle,
-    pub kernel_stack: KernelStack,
+    pub process: Weak<ProcessControlBlock>,
+    pub kstack: KernelStack,
     // mutable
-    inner: UPSafeCell<TaskControlBlockInner>,
+    inner: SpinLock<TaskControlBlockInner>,
 }
 
 pub struct TaskControlBlockInner {
+    // released as soon as the thread exits
+    pub res: Option<TaskUserRes>,
     pub trap_cx_ppn: PhysPageNum,
-    pub base_size: usize,
     pub priority: usize,
+    // stride scheduler: the pass, fair scheduler: the virtual runtime
     pub pass: usize,
+    // fair scheduler: when the virtual runtime was last charged
+    pub charged_at_us: usize,
+    // multi-level feedback queue: the level and the ticks used in its time slice
+    pub level: usize,
+    pub slice_ticks: usize,
+    // the real-time parameters, none for a thread of the selected scheduler
+    pub rt: Option<RtParams>,
     pub task_cx: TaskContext,
     pub task_status: TaskStatus,
-    pub memory_set: MemorySet,
-    pub parent: Option<Weak<TaskControlBlock>>,
-    pub children: Vec<Arc<TaskControlBlock>>,
-    pub exit_code: i32,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
-    pub signals: SignalFlags,
-    pub signal_mask: SignalFlags,
-    // the signal which is being handling
-    pub handling_sig: isize,
-    // Signal actions
-    pub signal_actions: SignalActions,
-    // if the task is killed
-    pub killed: bool,
-    // if the task is frozen by a signal
-    pub frozen: bool,
-    pub trap_ctx_backup: Option<TrapContext>,
+    // set from being switched to until the context is saved again,
+    // another hart must not switch to the thread or reap it meanwhile
+    pub on_cpu: bool,
+    // a wakeup came in before the thread got to sleep
+    pub wakeup_pending: bool,
+    pub exit_code: Option<i32>,
+    // when the time of the thread was last counted into its process
+    pub time_mark_us: usize,
 }
 
 impl TaskControlBlockInner {
@@ -62,10 +64,6 @@ impl TaskControlBlockInner {
         self.trap_cx_ppn.get_mut()
     }
 
-    pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
-    }
-
     pub fn get_status(&self) -> TaskStatus {
         self.task_status
     }
@@ -73,197 +71,64 @@ impl TaskControlBlockInner {
     pub fn set_prio(&mut self, prio: usize) {
         self.priority = prio;
     }
+}
 
-    pub fn is_zombie(&self) -> bool {
-        self.task_status == TaskStatus::Zombie
+impl TaskControlBlock {
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, TaskControlBlockInner> {
+        self.inner.lock()
     }
 
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            fd
-        } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
-        }
+    pub fn new(
+        process: Arc<ProcessControlBlock>,
+        ustack_base: usize,
+        alloc_user_res: bool,
+    ) -> Result<Self, SysError> {
+        let res = TaskUserRes::new(Arc::clone(&process), ustack_base, alloc_user_res)?;
+        let trap_cx_ppn = res.trap_cx_ppn();
+        let kstack = kstack_alloc();
+        let kstack_top = kstack.get_top();
+        Ok(Self {
+            process: Arc::downgrade(&process),
+            kstack,
+            inner: SpinLock::new(TaskControlBlockInner {
+                res: Some(res),
+                trap_cx_ppn,
+                priority: DEFAULT_PRIORITY,
+                pass: 0,
+                charged_at_us: 0,
+                level: 0,
+                slice_ticks: 0,
+                rt: None,
+                task_cx: TaskContext::goto_trap_return(kstack_top),
+                task_status: TaskStatus::Ready,
+                on_cpu: false,
+                wakeup_pending: false,
+                exit_code: None,
+                time_mark_us: 0,
+            }),
+        })
     }
-}
 
-impl TaskControlBlock {
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
-        self.inner.exclusive_access()
+    pub fn get_user_token(&self) -> usize {
+        let process = self.process.upgrade().unwrap();
+        let inner = process.inner_exclusive_access();
+        inner.memory_set.token()
     }
 
-    pub fn getpid(&self) -> usize {
-        self.pid.0
+    // id of the thread inside its process
+    pub fn gettid(&self) -> usize {
+        self.inner_exclusive_access().res.as_ref().unwrap().tid
     }
 
-    pub fn new(elf_data: &[u8]) -> Self {
-        // map user space memory set
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        let task_status = TaskStatus::Ready;
-        // alloc a pid and a kernel stack in kernel space
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        // push a task context which goes to trap_return to the top of kernel stack
-        let task_control_block = Self {
-            pid: pid_handle,
-            kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(TaskControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: user_sp,
-                    priority: 16,
-                    pass: 0,
-                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                    task_status,
-                    memory_set,
-                    parent: None,
-                    children: Vec::new(),
-                    exit_code: 0,
-                    fd_table: vec![
-                        // 0 -> stdin
-                        Some(Arc::new(Stdin)),
-                        // 1 -> stdout
-                        Some(Arc::new(Stdout)),
-                        // 2 -> stderr
-                        Some(Arc::new(Stdout)),
-                    ],
-                    signals: SignalFlags::empty(),
-                    signal_mask: SignalFlags::empty(),
-                    handling_sig: -1,
-                    signal_actions: SignalActions::default(),
-                    killed: false,
-                    frozen: false,
-                    trap_ctx_backup: None,
-                })
-            },
-        };
-        // prepare TrapContext in user space
-        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
-        *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            kernel_stack_top,
-            trap_handler as usize,
-        );
-        task_control_block
+    // id of the thread among all threads, used by the task manager
+    pub fn kstack_id(&self) -> usize {
+        self.kstack.0
     }
 
-    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
-        // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, mut user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        // push arguments on user stack
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    memory_set.token(),
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(memory_set.token(), p as *mut u8) = *c;
-                p += 1;
-            }
-            *translated_refmut(memory_set.token(), p as *mut u8) = 0;
+    /// Wait until the thread is off the cpu of any hart.
+    pub fn wait_off_cpu(&self) {
+        while self.inner_exclusive_access().on_cpu {
+            core::hint::spin_loop();
         }
-        // make the user_sp aligned to 8B for k210 platform
-        user_sp -= user_sp % core::mem::size_of::<usize>();
-        // substitute memory_set
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        let mut inner = self.inner_exclusive_access();
-        inner.memory_set = memory_set;
-        // update trap_cx ppn
-        inner.trap_cx_ppn = trap_cx_ppn;
-        // initialize trap_cx
-        let mut trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            self.kernel_stack.get_top(),
-            trap_handler as usize,
-        );
-        // argc argv
-        trap_cx.x[10] = args.len();
-        trap_cx.x[11] = argv_base;
-        *inner.get_trap_cx() = trap_cx;
-    }
-    pub fn fork(self: &Arc<TaskControlBlock>) -> Arc<TaskControlBlock> {
-        // get parent PCB
-        let mut parent_inner = self.inner.exclusive_access();
-        // make a copy of memory space
-        let memory_set = MemorySet::from_existed_userspace(&parent_inner.memory_set);
-        // allocate a pid and kernel stack
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernelStack::new(&pid_handle);
-        let kernel_stack_top = kernel_stack.get_top();
-        // get trap context ppn
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        // copy fds that are not busy(Option::None) from parent
-        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
-        for fd in parent_inner.fd_table.iter() {
-            if let Some(file) = fd {
-                new_fd_table.push(Some(file.clone()));
-            } else {
-                new_fd_table.push(None);
-            }
-        }
-        // create inner
-        let task_control_block_inner = unsafe {
-            UPSafeCell::new(TaskControlBlockInner {
-                trap_cx_ppn: trap_cx_ppn,
-                base_size: parent_inner.base_size,
-                priority: parent_inner.priority,
-                pass: parent_inner.pass,
-                task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-                task_status: TaskStatus::Ready,
-                memory_set,
-                parent: Some(Arc::downgrade(self)),
-                children: Vec::new(),
-                exit_code: 0,
-                fd_table: new_fd_table,
-                // TODO shall we inherit from parent?
-                signals: SignalFlags::empty(),
-                // inherit the signal_mask and signal_action
-                signal_mask: parent_inner.signal_mask,
-                handling_sig: -1,
-                signal_actions: parent_inner.signal_actions.clone(),
-                killed: false,
-                frozen: false,
-                trap_ctx_backup: None,
-            })
-        };
-        // modify kernel sp in child's trap_cx
-        let trap_cx = task_control_block_inner.exclusive_access().get_trap_cx();
-        trap_cx.kernel_sp = kernel_stack_top;
-        // create child's PCB
-        let task_control_block = Arc::new(TaskControlBlock {
-            pid: pid_handle,
-            kernel_stack,
-            inner: task_control_block_inner,
-        });
-        // add child to parent
-        parent_inner.children.push(task_control_block.clone());
-        // return
-        task_control_block
     }
 }
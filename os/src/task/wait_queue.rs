@@ -0,0 +1,76 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use crate::sync::SpinLock;
+
+use super::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+
+// threads sleeping until some event happens, they are woken up in fifo order
+pub struct WaitQueue {
+    queue: SpinLock<VecDeque<Arc<TaskControlBlock>>>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: SpinLock::new(VecDeque::new()),
+        }
+    }
+
+    /// Put the current thread to sleep until it is woken up from this queue.
+    /// The caller must not hold any borrow the waker needs, and should check
+    /// its condition again after waking up.
+    pub fn wait(&self) {
+        self.wait_with(|| true);
+    }
+
+    /// Like `wait`, but call release once the thread is in the queue, it drops
+    /// the lock guarding the condition waited for, so that a waker on another
+    /// hart taking the lock afterwards finds the thread. Return at once if
+    /// release returns false.
+    pub fn wait_with(&self, release: impl FnOnce() -> bool) {
+        let task = current_task().unwrap();
+        self.queue.lock().push_back(Arc::clone(&task));
+        if !release() {
+            self.queue.lock().retain(|t| !Arc::ptr_eq(t, &task));
+            return;
+        }
+        // a thread is taken out of the queue when it is woken up, a stale
+        // wakeup, e.g. from an earlier timer, does not end the wait
+        loop {
+            block_current_and_run_next();
+            if !self.queue.lock().iter().any(|t| Arc::ptr_eq(t, &task)) {
+                return;
+            }
+        }
+    }
+
+    /// Wake up the thread waiting for the longest time, return false if there is none.
+    pub fn wake_one(&self) -> bool {
+        loop {
+            let task = self.queue.lock().pop_front();
+            match task {
+                Some(task) => {
+                    // its process may have exited while it was waiting
+                    if wakeup_task(task) {
+                        return true;
+                    }
+                }
+                None => return false,
+            }
+        }
+    }
+
+    pub fn wake_all(&self) {
+        while self.wake_one() {}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,12 @@
+use core::cmp::Ordering;
+
 use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
+use crate::sync::SpinNoIrq;
+use crate::task::{wakeup_task, TaskControlBlock};
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use lazy_static::*;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 10_000;
@@ -16,3 +23,56 @@ pub fn get_time_us() -> usize {
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
+
+// a blocked thread to be woken up once the time reaches expire_us
+pub struct Timer {
+    pub expire_us: usize,
+    pub task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire_us == other.expire_us
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// reversed, so that the earliest timer is on the top of the max-heap
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.expire_us.cmp(&self.expire_us)
+    }
+}
+
+lazy_static! {
+    static ref TIMERS: SpinNoIrq<BinaryHeap<Timer>> = SpinNoIrq::new(BinaryHeap::new());
+}
+
+/// Wake up task when the time reaches expire_us, the task is expected to be
+/// blocked by then. A task which is not blocked is left alone, but one that has
+/// been blocked for another reason is woken up, so the sleeper should check the
+/// time again after waking up.
+pub fn add_timer(expire_us: usize, task: Arc<TaskControlBlock>) {
+    TIMERS.lock().push(Timer { expire_us, task });
+}
+
+/// Wake up the tasks whose timers have expired, called on every timer
+/// interrupt and whenever the cpu is idle.
+pub fn check_timer() {
+    let now = get_time_us();
+    let mut timers = TIMERS.lock();
+    while let Some(timer) = timers.peek() {
+        if timer.expire_us > now {
+            break;
+        }
+        let timer = timers.pop().unwrap();
+        wakeup_task(timer.task);
+    }
+}
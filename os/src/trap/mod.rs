@@ -7,13 +7,18 @@ use riscv::register::{
     sie, stval, stvec,
 };
 
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::config::TRAMPOLINE;
+use crate::fs::poll_stdin;
+use crate::mm::{MapPermission, VirtAddr};
+use crate::smp::{enter_kernel, leave_kernel, take_ipi, IPI_RESCHEDULE};
 use crate::syscall::syscall;
 use crate::task::{
-    check_signals_error_of_current, current_add_signal, current_trap_cx, current_user_token,
-    exit_current_and_run_next, handle_signals, suspend_current_and_run_next, SignalFlags,
+    check_signals_error_of_current, current_add_signal, current_handle_page_fault, current_trap_cx,
+    current_trap_cx_user_va, current_trap_enter, current_trap_leave, current_user_token,
+    exit_current_and_run_next, handle_signals, leave_current_if_exited,
+    preempt_current_and_run_next, resched_current_and_run_next, SignalFlags,
 };
-use crate::timer::set_next_trigger;
+use crate::timer::{check_timer, set_next_trigger};
 
 pub use context::TrapContext;
 
@@ -41,9 +46,13 @@ fn set_kernel_trap_entry() {
 
 #[no_mangle]
 pub fn trap_return() -> ! {
+    // the process may have exited on another hart meanwhile
+    leave_current_if_exited();
+    current_trap_leave();
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    let trap_cx_ptr = current_trap_cx_user_va();
     let user_satp = current_user_token();
+    leave_kernel(user_satp);
     extern "C" {
         fn __alltraps();
         fn __restore();
@@ -63,9 +72,15 @@ pub fn trap_return() -> ! {
 
 #[no_mangle]
 pub fn trap_handler() -> ! {
+    // before taking any lock, a hart in tlb_shootdown may be waiting for this
+    enter_kernel();
     set_kernel_trap_entry();
     let scause = scause::read();
     let stval = stval::read();
+    current_trap_enter(matches!(
+        scause.cause(),
+        Trap::Exception(Exception::UserEnvCall)
+    ));
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             let mut cx = current_trap_cx();
@@ -78,12 +93,22 @@ pub fn trap_handler() -> ! {
             cx = current_trap_cx();
             cx.x[10] = result;
         }
-        Trap::Exception(Exception::StoreFault)
-        | Trap::Exception(Exception::StorePageFault)
-        | Trap::Exception(Exception::InstructionFault)
+        Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::InstructionPageFault)
-        | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
+            // lazy pages fault on the first access, copy on write pages on the first store
+            let perm = match scause.cause() {
+                Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+                Trap::Exception(Exception::InstructionPageFault) => MapPermission::X,
+                _ => MapPermission::R,
+            };
+            if !current_handle_page_fault(VirtAddr::from(stval), perm) {
+                current_add_signal(SignalFlags::SIGSEGV);
+            }
+        }
+        Trap::Exception(Exception::StoreFault)
+        | Trap::Exception(Exception::InstructionFault)
+        | Trap::Exception(Exception::LoadFault) => {
             current_add_signal(SignalFlags::SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
@@ -91,7 +116,16 @@ pub fn trap_handler() -> ! {
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
-            suspend_current_and_run_next();
+            check_timer();
+            poll_stdin();
+            preempt_current_and_run_next();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            // a TLB flush has been done by take_ipi, a thread of an exited
+            // process leaves at trap_return
+            if take_ipi() & IPI_RESCHEDULE != 0 {
+                resched_current_and_run_next();
+            }
         }
         _ => {
             panic!(
@@ -123,3 +157,10 @@ pub fn enable_timer_interrupt() {
         sie::set_stimer();
     }
 }
+
+// the other harts interrupt this one through SBI
+pub fn enable_software_interrupt() {
+    unsafe {
+        sie::set_ssoft();
+    }
+}
@@ -0,0 +1,223 @@
+//! The ready queue of the stride scheduler. It knows nothing about threads,
+//! only ids, passes and priorities, so it can be tested on the host.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::collections::BinaryHeap;
+use core::cmp::Ordering;
+
+/// The stride of priority 1, a thread advances its pass by BIG_STRIDE / priority.
+/// Priorities are in 2..=BIG_STRIDE, so a stride is between 1 and BIG_STRIDE / 2.
+pub const BIG_STRIDE: usize = 1 << 20;
+
+pub fn stride(priority: usize) -> usize {
+    BIG_STRIDE / priority
+}
+
+/// Compare two passes which may have wrapped around.
+/// It is right as long as they are less than half the range of usize apart.
+pub fn pass_cmp(a: usize, b: usize) -> Ordering {
+    (a.wrapping_sub(b) as isize).cmp(&0)
+}
+
+// ordered so that the max-heap BinaryHeap pops the smallest pass first,
+// and the smallest id among equal passes
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Stride {
+    id: usize,
+    pass: usize,
+}
+
+impl Stride {
+    pub fn new(id: usize, pass: usize) -> Self {
+        Self { id, pass }
+    }
+}
+
+impl Ord for Stride {
+    fn cmp(&self, other: &Self) -> Ordering {
+        pass_cmp(other.pass, self.pass).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl PartialOrd for Stride {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct StrideQueue {
+    heap: BinaryHeap<Stride>,
+    // the pass of the last popped entry, no queued pass is behind it
+    current: usize,
+}
+
+impl StrideQueue {
+    pub fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            current: 0,
+        }
+    }
+
+    /// Queue id with its pass advanced by the stride of priority, return the new pass.
+    ///
+    /// A pass behind the current one, of a new thread or one that has been sleeping,
+    /// is moved up to it first. Sleeping earns no credit that way, and all queued
+    /// passes stay within BIG_STRIDE / 2 of each other for the wrapping comparison.
+    pub fn push(&mut self, id: usize, pass: usize, priority: usize) -> usize {
+        let pass = match pass_cmp(pass, self.current) {
+            Ordering::Less => self.current,
+            _ => pass,
+        };
+        let pass = pass.wrapping_add(stride(priority));
+        self.heap.push(Stride::new(id, pass));
+        pass
+    }
+
+    /// Take the id with the smallest pass out of the queue.
+    pub fn pop(&mut self) -> Option<usize> {
+        let next = self.heap.pop()?;
+        self.current = next.pass;
+        Some(next.id)
+    }
+
+    /// Take the entry of id out of the queue, wherever it is.
+    pub fn remove(&mut self, id: usize) {
+        let heap = core::mem::take(&mut self.heap);
+        self.heap = heap.into_iter().filter(|entry| entry.id != id).collect();
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl Default for StrideQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn starting_at(current: usize) -> StrideQueue {
+        StrideQueue {
+            heap: BinaryHeap::new(),
+            current,
+        }
+    }
+
+    #[test]
+    fn smallest_pass_first() {
+        let mut heap = BinaryHeap::new();
+        heap.push(Stride::new(1, 200));
+        heap.push(Stride::new(2, 300));
+        heap.push(Stride::new(0, 100));
+        let order: Vec<usize> = core::iter::from_fn(|| heap.pop().map(|s| s.id)).collect();
+        assert_eq!(order, [0, 1, 2]);
+    }
+
+    #[test]
+    fn equal_passes_by_id() {
+        let mut heap = BinaryHeap::new();
+        heap.push(Stride::new(2, 7));
+        heap.push(Stride::new(0, 7));
+        heap.push(Stride::new(1, 7));
+        let order: Vec<usize> = core::iter::from_fn(|| heap.pop().map(|s| s.id)).collect();
+        assert_eq!(order, [0, 1, 2]);
+    }
+
+    #[test]
+    fn order_across_wraparound() {
+        let before = Stride::new(0, usize::MAX - 10);
+        let after = Stride::new(1, 5);
+        // the smaller pass is the greater entry of the max-heap
+        assert_eq!(before.cmp(&after), Ordering::Greater);
+        assert_eq!(after.cmp(&before), Ordering::Less);
+        let mut heap = BinaryHeap::new();
+        heap.push(after);
+        heap.push(before);
+        assert_eq!(heap.pop(), Some(before));
+        assert_eq!(heap.pop(), Some(after));
+    }
+
+    #[test]
+    fn partial_cmp_agrees_with_cmp() {
+        let passes = [0, 1, BIG_STRIDE, usize::MAX - BIG_STRIDE, usize::MAX];
+        for (i, &a) in passes.iter().enumerate() {
+            for (j, &b) in passes.iter().enumerate() {
+                let (x, y) = (Stride::new(i, a), Stride::new(j, b));
+                assert_eq!(x.partial_cmp(&y), Some(x.cmp(&y)));
+                assert_eq!(x == y, x.cmp(&y) == Ordering::Equal);
+            }
+        }
+    }
+
+    #[test]
+    fn share_follows_priority_across_wraparound() {
+        let priorities = [2, 4, 8];
+        let start = usize::MAX - 100 * BIG_STRIDE;
+        let mut queue = starting_at(start);
+        let mut passes = [start; 3];
+        let mut runs = [0; 3];
+        for (id, pass) in passes.iter_mut().enumerate() {
+            *pass = queue.push(id, *pass, priorities[id]);
+        }
+        for _ in 0..7000 {
+            let id = queue.pop().unwrap();
+            runs[id] += 1;
+            passes[id] = queue.push(id, passes[id], priorities[id]);
+        }
+        // the passes have wrapped around by now
+        assert!(passes.iter().all(|&pass| pass < start));
+        for (id, expected) in [1000, 2000, 4000].into_iter().enumerate() {
+            assert!(runs[id] + 1 >= expected && runs[id] <= expected + 1);
+        }
+    }
+
+    #[test]
+    fn sleeper_earns_no_credit() {
+        let mut queue = StrideQueue::new();
+        let mut passes = [0; 3];
+        for (id, pass) in passes.iter_mut().enumerate().take(2) {
+            *pass = queue.push(id, *pass, 16);
+        }
+        for _ in 0..100 {
+            let id = queue.pop().unwrap();
+            passes[id] = queue.push(id, passes[id], 16);
+        }
+        // a thread that slept through all of it comes back with pass 0
+        passes[2] = queue.push(2, passes[2], 16);
+        let mut runs = [0; 3];
+        for _ in 0..30 {
+            let id = queue.pop().unwrap();
+            runs[id] += 1;
+            passes[id] = queue.push(id, passes[id], 16);
+        }
+        assert_eq!(runs, [10, 10, 10]);
+    }
+
+    #[test]
+    fn removed_entry_is_gone() {
+        let mut queue = StrideQueue::new();
+        let pass0 = queue.push(0, 0, 16);
+        queue.push(1, 0, 8);
+        // id 0 is removed and queued again with a larger pass
+        queue.remove(0);
+        assert_eq!(queue.len(), 1);
+        queue.push(0, pass0 + 2 * stride(8), 16);
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(0));
+        assert!(queue.is_empty());
+    }
+}
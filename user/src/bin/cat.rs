@@ -5,14 +5,14 @@
 extern crate user_lib;
 extern crate alloc;
 
-use user_lib::{close, open, read, OpenFlags};
+use user_lib::{close, errno, open, read, strerror, OpenFlags};
 
 #[no_mangle]
 pub fn main(argc: usize, argv: &[&str]) -> i32 {
     assert!(argc == 2);
     let fd = open(argv[1], OpenFlags::RDONLY);
     if fd == -1 {
-        panic!("Error occured when opening file");
+        panic!("{}: {}", argv[1], strerror(errno()));
     }
     let fd = fd as usize;
     let mut buf = [0u8; 256];
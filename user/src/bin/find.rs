@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{
+    close, errno, fstat, getdents, open, strerror, DirEntries, OpenFlags, Stat, StatMode, DT_DIR,
+};
+
+// print path and everything under it whose name is name, or all if it is None
+fn find(path: &str, file_name: &str, is_dir: bool, name: Option<&str>) {
+    if name.map_or(true, |name| name == file_name) {
+        println!("{}", path);
+    }
+    if !is_dir {
+        return;
+    }
+    let mut c_path = String::from(path);
+    c_path.push('\0');
+    let fd = open(c_path.as_str(), OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if fd == -1 {
+        println!("find: {}: {}", path, strerror(errno()));
+        return;
+    }
+    let fd = fd as usize;
+    // read the whole directory first, so only one descriptor is open at a time
+    let mut entries: Vec<(String, bool)> = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let size = getdents(fd, &mut buf);
+        if size <= 0 {
+            break;
+        }
+        for entry in DirEntries::new(&buf[..size as usize]) {
+            if entry.name != "." && entry.name != ".." {
+                entries.push((String::from(entry.name), entry.file_type == DT_DIR));
+            }
+        }
+    }
+    close(fd);
+    for (entry_name, entry_is_dir) in entries.iter() {
+        let mut entry_path = String::from(path);
+        if !entry_path.ends_with('/') {
+            entry_path.push('/');
+        }
+        entry_path.push_str(entry_name);
+        find(&entry_path, entry_name, *entry_is_dir, name);
+    }
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    let mut path = ".";
+    let mut name = None;
+    let mut i = 1;
+    while i < argc {
+        if argv[i] == "-name" && i + 1 < argc {
+            name = Some(argv[i + 1]);
+            i += 2;
+        } else {
+            path = argv[i];
+            i += 1;
+        }
+    }
+    let mut c_path = String::from(path);
+    c_path.push('\0');
+    let fd = open(c_path.as_str(), OpenFlags::RDONLY);
+    if fd == -1 {
+        println!("find: {}: {}", path, strerror(errno()));
+        return 1;
+    }
+    let stat = Stat::new();
+    fstat(fd as usize, &stat);
+    close(fd as usize);
+    let file_name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path);
+    find(
+        path,
+        file_name,
+        stat.mode.file_type() == StatMode::DIR,
+        name,
+    );
+    0
+}
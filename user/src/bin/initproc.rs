@@ -3,28 +3,38 @@
 
 extern crate user_lib;
 
-use user_lib::{exec, fork, wait, yield_};
+use user_lib::{exec, exit, fork, wait, yield_};
+
+// start a login on the console, it becomes the shell of whoever logs in
+fn spawn_login() -> isize {
+    let pid = fork();
+    if pid == 0 {
+        exec("login\0", &[core::ptr::null::<u8>()]);
+        exit(-1);
+    }
+    pid
+}
 
 #[no_mangle]
 fn main() -> i32 {
-    if fork() == 0 {
-        exec("user_shell\0", &[core::ptr::null::<u8>()]);
-    } else {
-        loop {
-            let mut exit_code: i32 = 0;
-            let pid = wait(&mut exit_code);
-            if pid == -1 {
-                yield_();
-                continue;
-            }
-            /*
-            println!(
-                "[initproc] Released a zombie process, pid={}, exit_code={}",
-                pid,
-                exit_code,
-            );
-            */
+    let mut login = spawn_login();
+    loop {
+        let mut exit_code: i32 = 0;
+        let pid = wait(&mut exit_code);
+        if pid == -1 {
+            yield_();
+            continue;
+        }
+        // a new login once the last session is over
+        if pid == login {
+            login = spawn_login();
         }
+        /*
+        println!(
+            "[initproc] Released a zombie process, pid={}, exit_code={}",
+            pid,
+            exit_code,
+        );
+        */
     }
-    0
 }
@@ -0,0 +1,157 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::console::getchar;
+use user_lib::{
+    chdir, chown, close, exec, exit, mkdir, open, read, setgid, setuid, stat, OpenFlags, Stat,
+};
+
+const LF: u8 = 0x0au8;
+const CR: u8 = 0x0du8;
+const DL: u8 = 0x7fu8;
+const BS: u8 = 0x08u8;
+
+const PASSWD: &str = "/etc/passwd\0";
+
+/// A line of /etc/passwd, `name:password:uid:gid:gecos:home:shell`. The
+/// password is kept in the clear, an empty one asks for none.
+struct Passwd {
+    name: String,
+    password: String,
+    uid: u32,
+    gid: u32,
+    home: String,
+    shell: String,
+}
+
+impl Passwd {
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() != 7 {
+            return None;
+        }
+        Some(Self {
+            name: String::from(fields[0]),
+            password: String::from(fields[1]),
+            uid: fields[2].parse().ok()?,
+            gid: fields[3].parse().ok()?,
+            home: String::from(fields[5]),
+            shell: String::from(fields[6]),
+        })
+    }
+}
+
+fn read_passwd() -> Vec<Passwd> {
+    let fd = open(PASSWD, OpenFlags::RDONLY);
+    if fd < 0 {
+        return Vec::new();
+    }
+    let mut data = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let len = read(fd as usize, &mut buf);
+        if len <= 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..len as usize]);
+    }
+    close(fd as usize);
+    core::str::from_utf8(&data)
+        .unwrap_or("")
+        .lines()
+        .filter_map(Passwd::parse)
+        .collect()
+}
+
+/// Read a line from the console, showing what is typed only when echo is set.
+fn read_line(echo: bool) -> String {
+    let mut line = String::new();
+    loop {
+        let c = getchar();
+        match c {
+            LF | CR => {
+                println!("");
+                return line;
+            }
+            BS | DL => {
+                if line.pop().is_some() && echo {
+                    print!("{}", BS as char);
+                    print!(" ");
+                    print!("{}", BS as char);
+                }
+            }
+            _ => {
+                if echo {
+                    print!("{}", c as char);
+                }
+                line.push(c as char);
+            }
+        }
+    }
+}
+
+/// Create the missing directories on the way to home, the last one owned by
+/// the user logging in.
+fn make_home(entry: &Passwd) {
+    let st = Stat::new();
+    let home = entry.home.clone() + "\0";
+    if stat(home.as_str(), &st) == 0 {
+        return;
+    }
+    let mut path = String::new();
+    for component in entry.home.split('/').filter(|name| !name.is_empty()) {
+        path.push('/');
+        path.push_str(component);
+        path.push('\0');
+        mkdir(path.as_str());
+        path.pop();
+    }
+    chown(home.as_str(), entry.uid, entry.gid);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    loop {
+        print!("login: ");
+        let name = read_line(true);
+        if name.is_empty() {
+            continue;
+        }
+        let users = read_passwd();
+        let entry = users.iter().find(|entry| entry.name == name);
+        // an unknown name is asked for a password too, not to tell it apart
+        let password = if !matches!(entry, Some(entry) if entry.password.is_empty()) {
+            print!("Password: ");
+            read_line(false)
+        } else {
+            String::new()
+        };
+        let entry = match entry {
+            Some(entry) if entry.password == password => entry,
+            _ => {
+                println!("Login incorrect");
+                continue;
+            }
+        };
+        make_home(entry);
+        // the group first, it can not be changed once the user is not root
+        if setgid(entry.gid) != 0 || setuid(entry.uid) != 0 {
+            println!("login: can not switch to {}", entry.name);
+            exit(1);
+        }
+        if chdir((entry.home.clone() + "\0").as_str()) != 0 {
+            println!("No directory {}, logging in with home /", entry.home);
+            chdir("/\0");
+        }
+        let shell = entry.shell.clone() + "\0";
+        exec(shell.as_str(), &[core::ptr::null::<u8>()]);
+        println!("login: can not run {}", entry.shell);
+        exit(1);
+    }
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{
+    close, errno, getdents, open, strerror, DirEntries, OpenFlags, DT_DIR, DT_LNK, ENOTDIR,
+};
+
+// list the directory at path, with `.` and `..` if all is set
+fn ls(path: &str, all: bool) -> i32 {
+    let mut c_path = String::from(path);
+    c_path.push('\0');
+    let fd = open(c_path.as_str(), OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    if fd == -1 {
+        if errno() == ENOTDIR {
+            println!("{}", path);
+            return 0;
+        }
+        println!("ls: {}: {}", path, strerror(errno()));
+        return 1;
+    }
+    let fd = fd as usize;
+    let mut names: Vec<String> = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let size = getdents(fd, &mut buf);
+        if size <= 0 {
+            break;
+        }
+        for entry in DirEntries::new(&buf[..size as usize]) {
+            if !all && (entry.name == "." || entry.name == "..") {
+                continue;
+            }
+            let mut name = String::from(entry.name);
+            match entry.file_type {
+                DT_DIR => name.push('/'),
+                DT_LNK => name.push('@'),
+                _ => {}
+            }
+            names.push(name);
+        }
+    }
+    close(fd);
+    names.sort();
+    for name in names.iter() {
+        println!("{}", name);
+    }
+    0
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    let all = argv.iter().skip(1).any(|&arg| arg == "-a");
+    let paths: Vec<&str> = argv[1..argc]
+        .iter()
+        .copied()
+        .filter(|&arg| arg != "-a")
+        .collect();
+    if paths.is_empty() {
+        return ls(".", all);
+    }
+    let mut exit_code = 0;
+    for (i, path) in paths.iter().enumerate() {
+        if paths.len() > 1 {
+            if i > 0 {
+                println!("");
+            }
+            println!("{}:", path);
+        }
+        exit_code |= ls(path, all);
+    }
+    exit_code
+}
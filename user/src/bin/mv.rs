@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{close, errno, open, renameat2, strerror, OpenFlags, RENAME_NOREPLACE};
+
+// a path with the NUL the syscalls need
+fn c_path(path: &str) -> String {
+    let mut c_path = String::from(path);
+    c_path.push('\0');
+    c_path
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    // -n keeps the files already there
+    let no_clobber = argv[1..argc].contains(&"-n");
+    let paths: Vec<&str> = argv[1..argc]
+        .iter()
+        .copied()
+        .filter(|&arg| arg != "-n")
+        .collect();
+    if paths.len() < 2 {
+        println!("usage: mv [-n] SOURCE... DEST");
+        return 1;
+    }
+    let (dest, sources) = paths.split_last().unwrap();
+    let fd = open(c_path(dest).as_str(), OpenFlags::DIRECTORY);
+    let into_dir = fd >= 0;
+    if into_dir {
+        close(fd as usize);
+    } else if sources.len() > 1 {
+        println!("mv: {}: not a directory", dest);
+        return 1;
+    }
+    let flags = if no_clobber { RENAME_NOREPLACE } else { 0 };
+    let mut exit_code = 0;
+    for source in sources.iter() {
+        let target = if into_dir {
+            let name = source.trim_end_matches('/').rsplit('/').next().unwrap();
+            let mut target = String::from(dest.trim_end_matches('/'));
+            target.push('/');
+            target.push_str(name);
+            target
+        } else {
+            String::from(*dest)
+        };
+        if renameat2(c_path(source).as_str(), c_path(&target).as_str(), flags) == -1 {
+            println!("mv: {} -> {}: {}", source, target, strerror(errno()));
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
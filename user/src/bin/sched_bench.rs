@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, get_time, getrusage, set_priority, sleep, waitpid, RUsage, RUSAGE_SELF,
+};
+
+/*
+调度器对比：分别以 SCHED=rr/stride/mlfq/cfs 编译内核后运行，
+比较计算型进程的完成时间、CPU 时间和交互型进程的平均唤醒延迟。
+*/
+
+const CPU_WORK_MS: isize = 300;
+const CPU_PRIORITIES: [isize; 3] = [8, 16, 32];
+const INTERACTIVE_ROUNDS: usize = 20;
+const INTERACTIVE_SLEEP_MS: usize = 10;
+
+// spin until the process itself has used CPU_WORK_MS of user time
+fn cpu_bound(priority: isize) -> ! {
+    set_priority(priority);
+    let start = get_time();
+    let mut usage = RUsage::default();
+    loop {
+        getrusage(RUSAGE_SELF, &mut usage);
+        if usage.utime_us as isize >= CPU_WORK_MS * 1000 {
+            break;
+        }
+    }
+    println!(
+        "cpu bound, priority {}: turnaround {}ms, {} involuntary switches",
+        priority,
+        get_time() - start,
+        usage.involuntary_switches
+    );
+    exit(0)
+}
+
+// sleep a little and measure how late it gets the cpu back
+fn interactive() -> ! {
+    let start = get_time();
+    let mut total_delay = 0;
+    for _ in 0..INTERACTIVE_ROUNDS {
+        let before = get_time();
+        sleep(INTERACTIVE_SLEEP_MS);
+        total_delay += get_time() - before - INTERACTIVE_SLEEP_MS as isize;
+    }
+    println!(
+        "interactive: turnaround {}ms, average wakeup delay {}ms",
+        get_time() - start,
+        total_delay / INTERACTIVE_ROUNDS as isize
+    );
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time();
+    let mut pids = [0; CPU_PRIORITIES.len() + 1];
+    for (i, priority) in CPU_PRIORITIES.iter().enumerate() {
+        pids[i] = fork();
+        if pids[i] == 0 {
+            cpu_bound(*priority);
+        }
+    }
+    pids[CPU_PRIORITIES.len()] = fork();
+    if pids[CPU_PRIORITIES.len()] == 0 {
+        interactive();
+    }
+    let mut exit_code: i32 = 0;
+    for pid in pids.iter() {
+        assert_eq!(waitpid(*pid as usize, &mut exit_code), *pid);
+    }
+    println!("sched_bench: all done in {}ms", get_time() - start);
+    0
+}
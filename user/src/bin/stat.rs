@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use user_lib::{errno, lstat, readlink, strerror, Stat, StatMode, TimeSpec};
+
+// the type and the permission bits as ls shows them, like drwxr-xr-x
+fn mode_string(mode: StatMode) -> String {
+    let mut s = String::from(match mode.file_type() {
+        StatMode::DIR => "d",
+        StatMode::LNK => "l",
+        _ => "-",
+    });
+    let perm = mode.perm();
+    for (i, c) in "rwxrwxrwx".chars().enumerate() {
+        s.push(if perm & (0o400 >> i) != 0 { c } else { '-' });
+    }
+    s
+}
+
+fn print_time(name: &str, time: &TimeSpec) {
+    println!("{}: {}.{:09}", name, time.sec, time.nsec);
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc < 2 {
+        println!("usage: stat FILE...");
+        return 1;
+    }
+    let mut exit_code = 0;
+    for path in argv[1..argc].iter() {
+        let mut c_path = String::from(*path);
+        c_path.push('\0');
+        let stat = Stat::new();
+        if lstat(c_path.as_str(), &stat) == -1 {
+            println!("stat: {}: {}", path, strerror(errno()));
+            exit_code = 1;
+            continue;
+        }
+        if stat.mode.file_type() == StatMode::LNK {
+            let mut target = [0u8; 256];
+            let len = readlink(c_path.as_str(), &mut target).max(0) as usize;
+            let target = core::str::from_utf8(&target[..len]).unwrap_or("?");
+            println!("  File: {} -> {}", path, target);
+        } else {
+            println!("  File: {}", path);
+        }
+        let file_type = match stat.mode.file_type() {
+            StatMode::DIR => "directory",
+            StatMode::LNK => "symbolic link",
+            _ => "regular file",
+        };
+        println!(
+            "  Size: {}  Blocks: {}  IO Block: {}  {}",
+            stat.size, stat.blocks, stat.blksize, file_type
+        );
+        println!(" Inode: {}  Links: {}", stat.ino, stat.nlink);
+        println!(
+            "Access: ({:04o}/{})  Uid: {}  Gid: {}",
+            stat.mode.perm(),
+            mode_string(stat.mode),
+            stat.uid,
+            stat.gid
+        );
+        print_time("Access", &stat.atime);
+        print_time("Modify", &stat.mtime);
+        print_time("Change", &stat.ctime);
+    }
+    exit_code
+}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time, getrusage, set_priority, wait, RUsage, RUSAGE_SELF};
+
+/*
+各进程的 CPU 时间应与其优先级大致成正比，输出每个进程的 utime 以及 utime / priority。
+*/
+
+const PRIORITIES: [isize; 5] = [5, 6, 7, 8, 9];
+const PERIOD_MS: isize = 1000;
+
+fn spin(priority: isize, start: isize) -> ! {
+    set_priority(priority);
+    while get_time() < start + PERIOD_MS {}
+    let mut usage = RUsage::default();
+    getrusage(RUSAGE_SELF, &mut usage);
+    println!(
+        "priority {}: utime {}us, {}us per priority, {} involuntary switches",
+        priority,
+        usage.utime_us,
+        usage.utime_us / priority as usize,
+        usage.involuntary_switches
+    );
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time();
+    for priority in PRIORITIES {
+        if fork() == 0 {
+            spin(priority, start);
+        }
+    }
+    let mut exit_code: i32 = 0;
+    for _ in PRIORITIES {
+        wait(&mut exit_code);
+    }
+    println!("stride_bench done.");
+    0
+}
@@ -0,0 +1,127 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chmod, chown, close, errno, exec, mkdir, open, read, rmdir, sleep, stat, unlink, utimensat,
+    write, OpenFlags, Stat, StatMode, TimeSpec, EACCES, EINVAL, UTIME_NOW, UTIME_OMIT,
+};
+
+/*
+理想结果：新建的文件与目录属于创建者并带有默认权限位，stat 给出大小、块数与三个时间，
+写入更新 mtime/ctime、读取更新 atime，chmod/chown 只改 ctime，utimensat 可设定或省略时间，
+没有 x 位的文件不能 exec，输出 Test chmod OK!
+*/
+
+fn time_of(time: &TimeSpec) -> (usize, usize) {
+    (time.sec, time.nsec)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fname = "chmod_file\0";
+    let fd = open(fname, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(mkdir("chmod_dir\0"), 0);
+
+    // the defaults, owned by the creator which is root
+    let st = Stat::new();
+    assert_eq!(stat(fname, &st), 0);
+    assert_eq!(st.mode.file_type(), StatMode::FILE);
+    assert_eq!(st.mode.perm(), 0o644);
+    assert_eq!((st.uid, st.gid), (0, 0));
+    assert_eq!((st.size, st.blocks), (0, 0));
+    assert_eq!(time_of(&st.mtime), time_of(&st.ctime));
+    let dir_st = Stat::new();
+    assert_eq!(stat("chmod_dir\0", &dir_st), 0);
+    assert_eq!(dir_st.mode.file_type(), StatMode::DIR);
+    assert_eq!(dir_st.mode.perm(), 0o755);
+
+    // a write changes the data, a read only reads it
+    sleep(2);
+    assert_eq!(write(fd, &[b'x'; 600]), 600);
+    let written = Stat::new();
+    assert_eq!(stat(fname, &written), 0);
+    assert_eq!(
+        (written.size, written.blocks, written.blksize),
+        (600, 2, 512)
+    );
+    assert!(time_of(&written.mtime) > time_of(&st.mtime));
+    assert_eq!(time_of(&written.ctime), time_of(&written.mtime));
+    assert_eq!(time_of(&written.atime), time_of(&st.atime));
+    sleep(2);
+    let fd2 = open(fname, OpenFlags::RDONLY) as usize;
+    let mut buf = [0u8; 16];
+    assert_eq!(read(fd2, &mut buf), 16);
+    close(fd2);
+    let read_st = Stat::new();
+    assert_eq!(stat(fname, &read_st), 0);
+    assert!(time_of(&read_st.atime) > time_of(&written.mtime));
+    assert_eq!(time_of(&read_st.mtime), time_of(&written.mtime));
+
+    // chmod and chown keep the type and the data time
+    sleep(2);
+    assert_eq!(chmod(fname, 0o4750), 0);
+    assert_eq!(chown(fname, 1000, 100), 0);
+    assert_eq!(chown(fname, u32::MAX, 200), 0);
+    let changed = Stat::new();
+    assert_eq!(stat(fname, &changed), 0);
+    assert_eq!(changed.mode.file_type(), StatMode::FILE);
+    assert_eq!(changed.mode.perm(), 0o4750);
+    assert_eq!((changed.uid, changed.gid), (1000, 200));
+    assert!(time_of(&changed.ctime) > time_of(&read_st.ctime));
+    assert_eq!(time_of(&changed.mtime), time_of(&written.mtime));
+
+    // root opens whatever the bits say
+    assert_eq!(chmod(fname, 0), 0);
+    let fd2 = open(fname, OpenFlags::RDWR);
+    assert!(fd2 > 0);
+    close(fd2 as usize);
+
+    // times given, omitted, and now
+    let times = [
+        TimeSpec {
+            sec: 0,
+            nsec: UTIME_OMIT,
+        },
+        TimeSpec {
+            sec: 1_000_000,
+            nsec: 5,
+        },
+    ];
+    assert_eq!(utimensat(fname, Some(&times)), 0);
+    let stamped = Stat::new();
+    assert_eq!(stat(fname, &stamped), 0);
+    assert_eq!(time_of(&stamped.mtime), (1_000_000, 5));
+    assert_eq!(time_of(&stamped.atime), time_of(&read_st.atime));
+    assert_eq!(utimensat(fname, None), 0);
+    assert_eq!(stat(fname, &stamped), 0);
+    assert!(time_of(&stamped.mtime) < (1_000_000, 5));
+    assert_eq!(time_of(&stamped.atime), time_of(&stamped.mtime));
+    let bad = [
+        TimeSpec {
+            sec: 0,
+            nsec: UTIME_NOW,
+        },
+        TimeSpec {
+            sec: 0,
+            nsec: 1_000_000_000,
+        },
+    ];
+    assert_eq!(utimensat(fname, Some(&bad)), -1);
+    assert_eq!(errno(), EINVAL);
+
+    // no x bit at all, not even root may run it
+    assert_eq!(chmod(fname, 0o644), 0);
+    assert_eq!(exec(fname, &[core::ptr::null::<u8>()]), -1);
+    assert_eq!(errno(), EACCES);
+
+    close(fd);
+    assert_eq!(unlink(fname), 0);
+    assert_eq!(rmdir("chmod_dir\0"), 0);
+    println!("Test chmod OK!");
+    0
+}
@@ -0,0 +1,100 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    enable_deadlock_detect, errno, exit, mutex_blocking_create, mutex_lock, mutex_unlock,
+    thread_create, waittid, yield_, EDEADLK,
+};
+
+/*
+理想结果：按相同顺序加锁的线程都能完成，按相反顺序加锁时恰好一个线程得到 EDEADLK，
+输出 Test deadlock0 OK!
+*/
+
+const ROUNDS: usize = 20;
+
+static mut MUTEXES: [usize; 2] = [0; 2];
+static mut HOLDING: [bool; 2] = [false; 2];
+static mut RESULTS: [isize; 2] = [0; 2];
+
+// every thread locks the mutexes in the same order
+fn same_order() -> ! {
+    let (first, second) = unsafe { (MUTEXES[0], MUTEXES[1]) };
+    for _ in 0..ROUNDS {
+        assert_eq!(mutex_lock(first), 0);
+        yield_();
+        assert_eq!(mutex_lock(second), 0);
+        yield_();
+        assert_eq!(mutex_unlock(second), 0);
+        assert_eq!(mutex_unlock(first), 0);
+    }
+    exit(0)
+}
+
+// thread idx holds MUTEXES[idx] and then waits for the other one
+fn opposite_order(idx: usize) -> ! {
+    let (first, second) = unsafe { (MUTEXES[idx], MUTEXES[1 - idx]) };
+    assert_eq!(mutex_lock(first), 0);
+    unsafe {
+        HOLDING[idx] = true;
+        while !HOLDING[1 - idx] {
+            yield_();
+        }
+    }
+    let ret = mutex_lock(second);
+    unsafe {
+        RESULTS[idx] = if ret == 0 { 0 } else { errno() };
+    }
+    if ret == 0 {
+        assert_eq!(mutex_unlock(second), 0);
+    }
+    assert_eq!(mutex_unlock(first), 0);
+    exit(0)
+}
+
+fn run(entries: [usize; 2], args: [usize; 2]) {
+    let mut exit_code: i32 = 0;
+    let tids = [
+        thread_create(entries[0], args[0]),
+        thread_create(entries[1], args[1]),
+    ];
+    for tid in tids.iter() {
+        assert!(*tid > 0);
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize, &mut exit_code), *tid);
+        assert_eq!(exit_code, 0);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(enable_deadlock_detect(true), 0);
+    unsafe {
+        MUTEXES = [
+            mutex_blocking_create() as usize,
+            mutex_blocking_create() as usize,
+        ];
+    }
+    // safe: the same locking order never deadlocks
+    run([same_order as usize; 2], [0; 2]);
+    // unsafe: the thread which closes the cycle is refused, the other goes on
+    run([opposite_order as usize; 2], [0, 1]);
+    let results = unsafe { RESULTS };
+    assert!(
+        results == [0, EDEADLK] || results == [EDEADLK, 0],
+        "unexpected results {:?}",
+        results
+    );
+    // locking a mutex held by the caller itself
+    let mutex = unsafe { MUTEXES[0] };
+    assert_eq!(mutex_lock(mutex), 0);
+    assert_eq!(mutex_lock(mutex), -1);
+    assert_eq!(errno(), EDEADLK);
+    assert_eq!(mutex_unlock(mutex), 0);
+    println!("Test deadlock0 OK!");
+    0
+}
@@ -0,0 +1,115 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    enable_deadlock_detect, errno, exit, semaphore_create, semaphore_down, semaphore_up,
+    thread_create, waittid, yield_, EDEADLK,
+};
+
+/*
+理想结果：资源足够时所有线程都能拿到信号量，交叉等待时恰好一个线程得到 EDEADLK，
+等待已退出的线程拿走的信号量得到 EDEADLK，
+输出 Test deadlock1 OK!
+*/
+
+const THREAD_NUM: usize = 3;
+const ROUNDS: usize = 20;
+
+static mut SEMS: [usize; 2] = [0; 2];
+static mut HOLDING: [bool; 2] = [false; 2];
+static mut RESULTS: [isize; 2] = [0; 2];
+
+// take one unit of a semaphore with two units and then one unit of the other
+fn take_both() -> ! {
+    let (pool, single) = unsafe { (SEMS[0], SEMS[1]) };
+    for _ in 0..ROUNDS {
+        assert_eq!(semaphore_down(pool), 0);
+        yield_();
+        assert_eq!(semaphore_down(single), 0);
+        yield_();
+        assert_eq!(semaphore_up(single), 0);
+        assert_eq!(semaphore_up(pool), 0);
+    }
+    exit(0)
+}
+
+// thread idx holds the only unit of SEMS[idx] and then waits for the other one
+fn cross_wait(idx: usize) -> ! {
+    let (first, second) = unsafe { (SEMS[idx], SEMS[1 - idx]) };
+    assert_eq!(semaphore_down(first), 0);
+    unsafe {
+        HOLDING[idx] = true;
+        while !HOLDING[1 - idx] {
+            yield_();
+        }
+    }
+    let ret = semaphore_down(second);
+    unsafe {
+        RESULTS[idx] = if ret == 0 { 0 } else { errno() };
+    }
+    if ret == 0 {
+        assert_eq!(semaphore_up(second), 0);
+    }
+    assert_eq!(semaphore_up(first), 0);
+    exit(0)
+}
+
+// take a unit of SEMS[0] and exit without giving it back
+fn keep_unit() -> ! {
+    assert_eq!(semaphore_down(unsafe { SEMS[0] }), 0);
+    exit(0)
+}
+
+fn wait_all(tids: &[isize]) {
+    let mut exit_code: i32 = 0;
+    for tid in tids.iter() {
+        assert!(*tid > 0);
+        assert_eq!(waittid(*tid as usize, &mut exit_code), *tid);
+        assert_eq!(exit_code, 0);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(enable_deadlock_detect(true), 0);
+    // safe: whoever holds the single unit can always finish
+    unsafe {
+        SEMS = [semaphore_create(2) as usize, semaphore_create(1) as usize];
+    }
+    let mut tids = [0; THREAD_NUM];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(take_both as usize, 0);
+    }
+    wait_all(&tids);
+    // unsafe: the thread which closes the cycle is refused, the other goes on
+    unsafe {
+        SEMS = [semaphore_create(1) as usize, semaphore_create(1) as usize];
+    }
+    let tids = [
+        thread_create(cross_wait as usize, 0),
+        thread_create(cross_wait as usize, 1),
+    ];
+    wait_all(&tids);
+    let results = unsafe { RESULTS };
+    assert!(
+        results == [0, EDEADLK] || results == [EDEADLK, 0],
+        "unexpected results {:?}",
+        results
+    );
+    // the unit kept by an exited thread is never given back, waiting for it is
+    // refused until another thread puts one back by signalling
+    let sem = semaphore_create(1) as usize;
+    unsafe {
+        SEMS[0] = sem;
+    }
+    wait_all(&[thread_create(keep_unit as usize, 0)]);
+    assert_eq!(semaphore_down(sem), -1);
+    assert_eq!(errno(), EDEADLK);
+    assert_eq!(semaphore_up(sem), 0);
+    assert_eq!(semaphore_down(sem), 0);
+    println!("Test deadlock1 OK!");
+    0
+}
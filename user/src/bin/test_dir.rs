@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, errno, getcwd, mkdir, open, read, rmdir, unlink, write, OpenFlags, EEXIST,
+    ENOENT, ENOTDIR, ENOTEMPTY,
+};
+
+/*
+理想结果：输出 Test dir OK!
+*/
+
+fn assert_cwd(expected: &str) {
+    let mut buf = [0u8; 64];
+    let len = getcwd(&mut buf);
+    assert_eq!(len as usize, expected.len() + 1);
+    assert_eq!(
+        core::str::from_utf8(&buf[..expected.len()]).unwrap(),
+        expected
+    );
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_cwd("/");
+    assert_eq!(mkdir("dir0\0"), 0);
+    assert_eq!(mkdir("dir0\0"), -1);
+    assert_eq!(errno(), EEXIST);
+    assert_eq!(mkdir("dir0/dir1\0"), 0);
+    // create a file with a relative path and read it back with an absolute one
+    assert_eq!(chdir("dir0/dir1\0"), 0);
+    assert_cwd("/dir0/dir1");
+    let test_str = "Hello, directory!";
+    let fd = open("file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+    assert_eq!(chdir("../..\0"), 0);
+    assert_cwd("/");
+    let fd = open("/dir0/./dir1/../dir1/file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buffer = [0u8; 100];
+    let len = read(fd as usize, &mut buffer) as usize;
+    close(fd as usize);
+    assert_eq!(test_str, core::str::from_utf8(&buffer[..len]).unwrap());
+    assert_eq!(chdir("/dir0/dir1/file\0"), -1);
+    assert_eq!(errno(), ENOTDIR);
+    // directories must be empty before removing
+    assert_eq!(rmdir("dir0\0"), -1);
+    assert_eq!(errno(), ENOTEMPTY);
+    assert_eq!(unlink("dir0/dir1/file\0"), 0);
+    assert_eq!(rmdir("dir0/dir1\0"), 0);
+    assert_eq!(rmdir("dir0\0"), 0);
+    assert_eq!(chdir("dir0\0"), -1);
+    assert_eq!(errno(), ENOENT);
+    println!("Test dir OK!");
+    0
+}
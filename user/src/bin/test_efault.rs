@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, errno, exec, exit, fork, getpid, kill, mkdir, open, pipe, read, sys_waitpid,
+    waitpid, write, OpenFlags, EFAULT, EINVAL,
+};
+
+/*
+理想结果：传给系统调用的路径、参数或缓冲区指针不可访问、或内核要写入只读的页时返回 EFAULT 而内核不会 panic，
+越界的信号编号返回 EINVAL，写不了状态的 waitpid 不会回收子进程，
+输出 Test efault OK!
+*/
+
+// a path the process can not read, nothing is mapped at the first page
+fn bad_path() -> &'static str {
+    unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(0x10 as *const u8, 1)) }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(open(bad_path(), OpenFlags::RDONLY), -1);
+    assert_eq!(errno(), EFAULT);
+    assert_eq!(mkdir(bad_path()), -1);
+    assert_eq!(errno(), EFAULT);
+    assert_eq!(chdir(bad_path()), -1);
+    assert_eq!(errno(), EFAULT);
+    assert_eq!(exec(bad_path(), &[core::ptr::null::<u8>()]), -1);
+    assert_eq!(errno(), EFAULT);
+    // a good path with an argument which is not
+    assert_eq!(
+        exec("test_efault\0", &[0x10 as *const u8, core::ptr::null()]),
+        -1
+    );
+    assert_eq!(errno(), EFAULT);
+    let bad_fds = unsafe { &mut *(0x10 as *mut [usize; 2]) };
+    assert_eq!(pipe(bad_fds), -1);
+    assert_eq!(errno(), EFAULT);
+
+    // the kernel writes nowhere the process may not, e.g. its own code
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], b"code"), 4);
+    let text = unsafe { core::slice::from_raw_parts_mut(main as usize as *mut u8, 4) };
+    assert_eq!(read(pipe_fd[0], text), -1);
+    assert_eq!(errno(), EFAULT);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // a child is not lost to a bad status pointer, and a null one asks for none
+    let pid = fork();
+    if pid == 0 {
+        exit(3);
+    }
+    let bad_status = unsafe { &mut *(0x10 as *mut i32) };
+    assert_eq!(waitpid(pid as usize, bad_status), -1);
+    assert_eq!(errno(), EFAULT);
+    assert_eq!(sys_waitpid(pid, core::ptr::null_mut(), 0), pid);
+
+    for signum in [-1, 32, 64] {
+        assert_eq!(kill(getpid() as usize, signum), -1);
+        assert_eq!(errno(), EINVAL);
+    }
+    println!("Test efault OK!");
+    0
+}
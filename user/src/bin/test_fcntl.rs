@@ -0,0 +1,123 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, dup2, dup3, errno, exec, exit, fcntl, fork, open, pipe, read, unlink, waitpid, write,
+    OpenFlags, EAGAIN, EBADF, EINVAL, FD_CLOEXEC, F_DUPFD, F_DUPFD_CLOEXEC, F_GETFD, F_GETFL,
+    F_SETFD, F_SETFL,
+};
+
+/*
+理想结果：dup2/dup3 复制到指定的描述符，F_DUPFD 取不小于参数的最小空闲描述符，
+FD_CLOEXEC 的描述符在 exec 后被关闭，非阻塞的管道在无数据或写满时返回 EAGAIN，
+F_GETFL 返回打开时的 O_APPEND，
+输出 Test fcntl OK!
+*/
+
+// the descriptors the exec'd image checks
+const KEPT_FD: usize = 10;
+const CLOSED_FD: usize = 11;
+
+#[no_mangle]
+pub fn main(argc: usize, _argv: &[&str]) -> i32 {
+    if argc > 1 {
+        // after exec, only the descriptor without FD_CLOEXEC is left
+        assert_eq!(fcntl(KEPT_FD, F_GETFD, 0), 0);
+        assert_eq!(fcntl(CLOSED_FD, F_GETFD, 0), -1);
+        assert_eq!(errno(), EBADF);
+        exit(0);
+    }
+
+    // dup2 replaces the target, dup3 refuses the same descriptor
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(dup2(pipe_fd[1], 8), 8);
+    assert_eq!(write(8, b"x"), 1);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(pipe_fd[0], &mut buf), 1);
+    assert_eq!(&buf, b"x");
+    assert_eq!(dup2(8, 8), 8);
+    assert_eq!(dup3(8, 8, OpenFlags::empty()), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(dup2(30, 8), -1);
+    assert_eq!(errno(), EBADF);
+    assert_eq!(dup3(pipe_fd[0], 8, OpenFlags::CLOEXEC), 8);
+    assert_eq!(fcntl(8, F_GETFD, 0), FD_CLOEXEC as isize);
+
+    // F_DUPFD takes the lowest free descriptor not below its argument
+    assert_eq!(fcntl(pipe_fd[0], F_DUPFD, 8), 9);
+    assert_eq!(fcntl(9, F_GETFD, 0), 0);
+    assert_eq!(fcntl(pipe_fd[0], F_DUPFD_CLOEXEC, 20), 20);
+    assert_eq!(fcntl(20, F_GETFD, 0), FD_CLOEXEC as isize);
+    assert_eq!(fcntl(20, F_SETFD, 0), 0);
+    assert_eq!(fcntl(20, F_GETFD, 0), 0);
+    for fd in [8, 9, 20] {
+        assert_eq!(close(fd), 0);
+    }
+
+    // a nonblocking pipe does not sleep when it is empty or full, and the
+    // flag is shared by the duplicates
+    assert_eq!(
+        fcntl(pipe_fd[0], F_SETFL, OpenFlags::NONBLOCK.bits() as usize),
+        0
+    );
+    let read_end = fcntl(pipe_fd[0], F_DUPFD, 0) as usize;
+    assert_ne!(
+        fcntl(read_end, F_GETFL, 0) & OpenFlags::NONBLOCK.bits() as isize,
+        0
+    );
+    assert_eq!(read(read_end, &mut buf), -1);
+    assert_eq!(errno(), EAGAIN);
+    assert_eq!(
+        fcntl(pipe_fd[1], F_SETFL, OpenFlags::NONBLOCK.bits() as usize),
+        0
+    );
+    let data = [b'y'; 64];
+    let written = write(pipe_fd[1], &data);
+    assert!(written > 0 && written < data.len() as isize);
+    assert_eq!(write(pipe_fd[1], &data), -1);
+    assert_eq!(errno(), EAGAIN);
+    let mut big = [0u8; 64];
+    assert_eq!(read(read_end, &mut big), written);
+    close(read_end);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    // F_GETFL reports the access mode and O_APPEND the file was opened with
+    let fd = open(
+        "fcntl_append\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::APPEND,
+    );
+    assert!(fd > 0);
+    let flags = OpenFlags::from_bits_truncate(fcntl(fd as usize, F_GETFL, 0) as u32);
+    assert!(flags.contains(OpenFlags::WRONLY | OpenFlags::APPEND));
+    close(fd as usize);
+    unlink("fcntl_append\0");
+
+    // the descriptors with FD_CLOEXEC are closed by exec
+    let pid = fork();
+    if pid == 0 {
+        let mut pipe_fd = [0usize; 2];
+        assert_eq!(pipe(&mut pipe_fd), 0);
+        assert_eq!(dup2(pipe_fd[0], KEPT_FD), KEPT_FD as isize);
+        assert_eq!(
+            dup3(pipe_fd[1], CLOSED_FD, OpenFlags::CLOEXEC),
+            CLOSED_FD as isize
+        );
+        let args = [
+            "test_fcntl\0".as_ptr(),
+            "child\0".as_ptr(),
+            core::ptr::null(),
+        ];
+        exec("test_fcntl\0", &args);
+        panic!("exec failed");
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("Test fcntl OK!");
+    0
+}
@@ -3,7 +3,7 @@
 
 #[macro_use]
 extern crate user_lib;
-use user_lib::{close, fstat, link, open, read, unlink, write, OpenFlags, Stat};
+use user_lib::{close, fstat, link, lseek, open, read, unlink, write, OpenFlags, Stat, SEEK_SET};
 
 /// 测试 link/unlink，输出　Test link OK! 就算正确。
 
@@ -40,7 +40,25 @@ pub fn main() -> i32 {
     assert_eq!(stat2.nlink, 1);
     close(fd);
     unlink(lname0);
-    // It's Ok if you don't delete the inode and data blocks.
+
+    // the file outlives its last name while it is open, and a file created
+    // meanwhile does not get its inode
+    let fd = open(fname, OpenFlags::CREATE | OpenFlags::RDWR) as usize;
+    assert_eq!(unlink(fname), 0);
+    let other = open(lname0, OpenFlags::CREATE | OpenFlags::RDWR) as usize;
+    write(other, b"other");
+    write(fd, test_str.as_bytes());
+    fstat(fd, &stat2);
+    assert_eq!(stat2.nlink, 0);
+    lseek(fd, 0, SEEK_SET);
+    let read_len = read(fd, &mut buf) as usize;
+    assert_eq!(&buf[..read_len], test_str.as_bytes());
+    close(fd);
+    lseek(other, 0, SEEK_SET);
+    let read_len = read(other, &mut buf) as usize;
+    assert_eq!(&buf[..read_len], b"other");
+    close(other);
+    unlink(lname0);
     println!("Test link OK!");
     0
 }
@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, errno, fstat, getdents, lseek, mkdir, open, rmdir, unlink, DirEntries, OpenFlags, Stat,
+    DT_DIR, DT_REG, EINVAL, EISDIR, ENOTDIR, SEEK_SET,
+};
+
+/*
+理想结果：目录可以只读打开，getdents 列出其中的 .、..、文件与子目录及其类型和 inode 编号，
+缓冲区放不下一项时返回 EINVAL，小缓冲区可以分多次读完，对普通文件返回 ENOTDIR，
+输出 Test getdents OK!
+*/
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir("gd_dir\0"), 0);
+    assert_eq!(mkdir("gd_dir/sub\0"), 0);
+    let fd = open("gd_dir/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let file_stat = Stat::new();
+    assert_eq!(fstat(fd as usize, &file_stat), 0);
+    close(fd as usize);
+
+    // a directory opens for reading only
+    assert_eq!(open("gd_dir\0", OpenFlags::RDWR), -1);
+    assert_eq!(errno(), EISDIR);
+    assert_eq!(open("gd_dir/file\0", OpenFlags::DIRECTORY), -1);
+    assert_eq!(errno(), ENOTDIR);
+    let fd = open("gd_dir\0", OpenFlags::RDONLY | OpenFlags::DIRECTORY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+
+    // all the entries at once, then the end
+    let mut buf = [0u8; 256];
+    let size = getdents(fd, &mut buf);
+    assert!(size > 0);
+    let mut found = [false; 4];
+    for entry in DirEntries::new(&buf[..size as usize]) {
+        match entry.name {
+            "." => {
+                assert_eq!(entry.file_type, DT_DIR);
+                found[0] = true;
+            }
+            ".." => {
+                assert_eq!(entry.file_type, DT_DIR);
+                found[1] = true;
+            }
+            "sub" => {
+                assert_eq!(entry.file_type, DT_DIR);
+                found[2] = true;
+            }
+            "file" => {
+                assert_eq!(entry.file_type, DT_REG);
+                assert_eq!(entry.ino, file_stat.ino);
+                found[3] = true;
+            }
+            name => panic!("unexpected entry {}", name),
+        }
+    }
+    assert!(found.iter().all(|&found| found));
+    assert_eq!(getdents(fd, &mut buf), 0);
+
+    // one entry per call with a small buffer, after rewinding
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    let mut small = [0u8; 8];
+    assert_eq!(getdents(fd, &mut small), -1);
+    assert_eq!(errno(), EINVAL);
+    let mut one = [0u8; 32];
+    let mut count = 0;
+    loop {
+        let size = getdents(fd, &mut one);
+        assert!(size >= 0);
+        if size == 0 {
+            break;
+        }
+        assert_eq!(DirEntries::new(&one[..size as usize]).count(), 1);
+        count += 1;
+    }
+    assert_eq!(count, 4);
+    close(fd);
+
+    // a regular file has no entries
+    let fd = open("gd_dir/file\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(getdents(fd, &mut buf), -1);
+    assert_eq!(errno(), ENOTDIR);
+    close(fd);
+
+    assert_eq!(unlink("gd_dir/file\0"), 0);
+    assert_eq!(rmdir("gd_dir/sub\0"), 0);
+    assert_eq!(rmdir("gd_dir\0"), 0);
+    println!("Test getdents OK!");
+    0
+}
@@ -0,0 +1,109 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    errno, exit, fork, getpgid, getpid, getsid, kill, killpg, setpgid, setsid, sigaction, waitpid,
+    waitpid_options, wifstopped, wstopsig, yield_, SignalAction, EAGAIN, EPERM, ESRCH, SIGCONT,
+    SIGKILL, SIGTSTP, SIG_IGN, WNOHANG, WUNTRACED,
+};
+
+/*
+理想结果：setsid/setpgid/getpgid 符合会话与进程组的规则，kill 能发给整个进程组，
+SIGTSTP 使子进程停止并被 WUNTRACED 的 waitpid 报告，SIGCONT 使其继续，
+被忽略的 SIGTSTP 不再停止进程，输出 Test jobctl OK!
+*/
+
+fn spin() -> ! {
+    loop {
+        yield_();
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut status: i32 = 0;
+    let sid = getsid(0);
+    assert!(sid >= 0);
+
+    // a child starts a session of its own, and can not do it twice as the
+    // leader of its group
+    let pid = fork();
+    if pid == 0 {
+        let me = getpid();
+        assert_eq!(getsid(0), sid);
+        assert_eq!(setsid(), me);
+        assert_eq!(getsid(0), me);
+        assert_eq!(getpgid(0), me);
+        assert_eq!(setsid(), -1);
+        assert_eq!(errno(), EPERM);
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert_eq!(status, 0);
+
+    // two children in a group led by the first, killed together
+    let first = fork();
+    if first == 0 {
+        spin();
+    }
+    assert_eq!(setpgid(first as usize, 0), 0);
+    assert_eq!(getpgid(first as usize), first);
+    let second = fork();
+    if second == 0 {
+        spin();
+    }
+    assert_eq!(setpgid(second as usize, first as usize), 0);
+    assert_eq!(getpgid(second as usize), first);
+    // no such group in the session, and no such child
+    assert_eq!(setpgid(second as usize, 0x7fff_0000), -1);
+    assert_eq!(errno(), EPERM);
+    assert_eq!(setpgid(0x7fff_0000, 0), -1);
+    assert_eq!(errno(), ESRCH);
+    assert_eq!(killpg(first as usize, SIGKILL), 0);
+    assert_eq!(waitpid(first as usize, &mut status), first);
+    assert_eq!(status, -9);
+    assert_eq!(waitpid(second as usize, &mut status), second);
+    assert_eq!(status, -9);
+    assert_eq!(killpg(first as usize, SIGKILL), -1);
+    assert_eq!(errno(), ESRCH);
+
+    // a stopped child is reported once, and runs again after SIGCONT
+    let pid = fork();
+    if pid == 0 {
+        spin();
+    }
+    assert_eq!(kill(pid as usize, SIGTSTP), 0);
+    assert_eq!(waitpid_options(pid, &mut status, WUNTRACED), pid);
+    assert!(wifstopped(status));
+    assert_eq!(wstopsig(status), SIGTSTP);
+    assert_eq!(waitpid_options(pid, &mut status, WNOHANG | WUNTRACED), -1);
+    assert_eq!(errno(), EAGAIN);
+    assert_eq!(kill(pid as usize, SIGCONT), 0);
+    assert_eq!(waitpid_options(pid, &mut status, WNOHANG | WUNTRACED), -1);
+    assert_eq!(errno(), EAGAIN);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid_options(pid, &mut status, WUNTRACED), pid);
+    assert!(!wifstopped(status));
+    assert_eq!(status, -9);
+
+    // an ignored SIGTSTP does not stop the child
+    let pid = fork();
+    if pid == 0 {
+        let action = SignalAction {
+            handler: SIG_IGN,
+            ..Default::default()
+        };
+        let old_action = SignalAction::default();
+        assert_eq!(sigaction(SIGTSTP, &action, &old_action), 0);
+        assert_eq!(kill(getpid() as usize, SIGTSTP), 0);
+        exit(3);
+    }
+    assert_eq!(waitpid_options(pid, &mut status, WUNTRACED), pid);
+    assert!(!wifstopped(status));
+    assert_eq!(status, 3);
+    println!("Test jobctl OK!");
+    0
+}
@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, errno, ftruncate, lseek, open, pipe, pread, pwrite, read, unlink, write, OpenFlags,
+    EBADF, EINVAL, ESPIPE, SEEK_CUR, SEEK_END, SEEK_SET,
+};
+
+/*
+理想结果：lseek 按 SEEK_SET/SEEK_CUR/SEEK_END 移动偏移，越过文件末尾写入后中间读出 0，
+pread/pwrite 不改变文件偏移，ftruncate 截短或以 0 扩展文件，管道上的 lseek 返回 ESPIPE，
+输出 Test lseek OK!
+*/
+
+const BLOCK_SZ: usize = 512;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fname = "lseek_test\0";
+    let fd = open(fname, OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"0123456789"), 10);
+
+    // the three origins, and the bad ones
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 10);
+    assert_eq!(lseek(fd, 2, SEEK_SET), 2);
+    let mut buf = [0u8; 4];
+    assert_eq!(read(fd, &mut buf), 4);
+    assert_eq!(&buf, b"2345");
+    assert_eq!(lseek(fd, -3, SEEK_CUR), 3);
+    assert_eq!(lseek(fd, -1, SEEK_END), 9);
+    assert_eq!(read(fd, &mut buf), 1);
+    assert_eq!(buf[0], b'9');
+    assert_eq!(lseek(fd, -11, SEEK_END), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(lseek(fd, 0, 3), -1);
+    assert_eq!(errno(), EINVAL);
+    // an offset which overflows
+    assert_eq!(lseek(fd, isize::MAX, SEEK_END), -1);
+    assert_eq!(errno(), EINVAL);
+
+    // a write past the end leaves a hole of zeros
+    assert_eq!(lseek(fd, 20, SEEK_SET), 20);
+    assert_eq!(write(fd, b"end"), 3);
+    let mut all = [0xffu8; 32];
+    assert_eq!(pread(fd, &mut all, 0), 23);
+    assert_eq!(&all[..10], b"0123456789");
+    assert!(all[10..20].iter().all(|&b| b == 0));
+    assert_eq!(&all[20..23], b"end");
+
+    // pread and pwrite do not move the offset
+    assert_eq!(lseek(fd, 5, SEEK_SET), 5);
+    assert_eq!(pwrite(fd, b"ab", 1), 2);
+    assert_eq!(pread(fd, &mut buf, 0), 4);
+    assert_eq!(&buf, b"0ab3");
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 5);
+
+    // ftruncate cuts the file and extends it with zeros, without moving
+    // the offset
+    assert_eq!(ftruncate(fd, 4), 0);
+    assert_eq!(lseek(fd, 0, SEEK_END), 4);
+    assert_eq!(ftruncate(fd, 3 * BLOCK_SZ), 0);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 4);
+    let mut block = [0xffu8; BLOCK_SZ];
+    assert_eq!(pread(fd, &mut block, 0), BLOCK_SZ as isize);
+    assert_eq!(&block[..4], b"0ab3");
+    assert!(block[4..].iter().all(|&b| b == 0));
+    assert_eq!(pread(fd, &mut block, 2 * BLOCK_SZ), BLOCK_SZ as isize);
+    assert!(block.iter().all(|&b| b == 0));
+    assert_eq!(pread(fd, &mut block, 3 * BLOCK_SZ), 0);
+    assert_eq!(ftruncate(fd, 0), 0);
+    assert_eq!(read(fd, &mut buf), 0);
+    close(fd);
+
+    // only a descriptor open for writing can be truncated
+    let fd = open(fname, OpenFlags::RDONLY) as usize;
+    assert_eq!(ftruncate(fd, 0), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(pwrite(fd, b"x", 0), -1);
+    assert_eq!(errno(), EBADF);
+    close(fd);
+    assert_eq!(unlink(fname), 0);
+
+    // a pipe has no offset
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(lseek(pipe_fd[0], 0, SEEK_SET), -1);
+    assert_eq!(errno(), ESPIPE);
+    assert_eq!(pwrite(pipe_fd[1], b"x", 0), -1);
+    assert_eq!(errno(), ESPIPE);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    println!("Test lseek OK!");
+    0
+}
@@ -20,6 +20,9 @@ fn main() -> i32 {
     assert_eq!(mmap(start + len + 1, len, prot), -1);
     assert_eq!(mmap(start + len, len, 0), -1);
     assert_eq!(mmap(start + len, len, prot | 8), -1);
+    // an empty range, and one which wraps around
+    assert_eq!(mmap(start + len, 0, prot), -1);
+    assert_eq!(mmap(start + len, usize::MAX - start, prot), -1);
     println!("Test 04_4 test OK!");
     0
 }
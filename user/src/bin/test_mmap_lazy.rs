@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{mmap, munmap};
+
+/*
+理想结果：输出 Test lazy mmap OK!
+映射远大于物理内存的区域，只有访问过的页才会分配物理页帧。
+*/
+
+#[no_mangle]
+fn main() -> i32 {
+    let start: usize = 0x10000000;
+    // 64 MiB, much larger than the physical memory
+    let len: usize = 4096 * 16384;
+    let prot: usize = 3;
+    assert_eq!(0, mmap(start, len, prot));
+    // touch one byte every 1 MiB
+    for i in (start..(start + len)).step_by(4096 * 256) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, 0);
+            *addr = (i >> 20) as u8;
+        }
+    }
+    for i in (start..(start + len)).step_by(4096 * 256) {
+        let addr: *mut u8 = i as *mut u8;
+        unsafe {
+            assert_eq!(*addr, (i >> 20) as u8);
+        }
+    }
+    // unmap a hole in the middle, both sides stay mapped
+    assert_eq!(munmap(start + 4096 * 256, 4096 * 256), 0);
+    assert_eq!(munmap(start + 4096 * 256, 4096), -1);
+    assert_eq!(mmap(start + 4096 * 256, 4096, prot), 0);
+    unsafe {
+        assert_eq!(*(start as *const u8), (start >> 20) as u8);
+        assert_eq!(
+            *((start + 4096 * 512) as *const u8),
+            ((start >> 20) + 2) as u8
+        );
+    }
+    assert_eq!(munmap(start, len), -1);
+    println!("Test lazy mmap OK!");
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{errno, exit, mmap, munmap, thread_create, waittid, EINVAL};
+
+/*
+理想结果：mmap 和 munmap 不能碰到线程的用户栈、陷入上下文和跳板页，之后仍能创建线程，
+输出 Test mmap thread OK!
+*/
+
+const PAGE_SIZE: usize = 4096;
+// the trampoline is the last page, the trap context of the main thread below it
+const TRAP_CONTEXT: usize = 0usize.wrapping_sub(2 * PAGE_SIZE);
+
+fn thread_main() -> ! {
+    exit(7)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let prot = 3;
+    // the trap context of the second thread, the main one and the trampoline
+    for start in [
+        TRAP_CONTEXT - PAGE_SIZE,
+        TRAP_CONTEXT,
+        TRAP_CONTEXT + PAGE_SIZE,
+    ] {
+        assert_eq!(mmap(start, PAGE_SIZE, prot), -1);
+        assert_eq!(errno(), EINVAL);
+    }
+    // the user stack of this thread
+    let local = 0usize;
+    let stack_page = &local as *const usize as usize & !(PAGE_SIZE - 1);
+    assert_eq!(munmap(stack_page, PAGE_SIZE), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(mmap(stack_page + 4 * PAGE_SIZE, PAGE_SIZE, prot), -1);
+    assert_eq!(errno(), EINVAL);
+
+    let tid = thread_create(thread_main as usize, 0);
+    assert!(tid > 0);
+    let mut exit_code = 0;
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    assert_eq!(exit_code, 7);
+    println!("Test mmap thread OK!");
+    0
+}
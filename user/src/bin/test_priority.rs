@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, errno, exit, fork, getpriority, pipe, read, setpriority, sleep, waitpid, write,
+    DEFAULT_PRIORITY, EINVAL, ESRCH, MAX_PRIORITY, MIN_PRIORITY, PRIO_PROCESS,
+};
+
+/*
+理想结果：进程可以读取和设置自己或其他进程的优先级，子进程继承父进程的优先级，
+非法的优先级和不存在的进程分别返回 EINVAL 和 ESRCH，输出 Test priority OK!
+*/
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(getpriority(PRIO_PROCESS, 0), DEFAULT_PRIORITY);
+    for prio in [MIN_PRIORITY - 1, MAX_PRIORITY + 1, -1] {
+        assert_eq!(setpriority(PRIO_PROCESS, 0, prio), -1);
+        assert_eq!(errno(), EINVAL);
+    }
+    assert_eq!(setpriority(PRIO_PROCESS + 1, 0, DEFAULT_PRIORITY), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(setpriority(PRIO_PROCESS, 0, 32), 0);
+    assert_eq!(getpriority(PRIO_PROCESS, 0), 32);
+
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[0]);
+        assert_eq!(getpriority(PRIO_PROCESS, 0), 32);
+        write(pipe_fd[1], &[0]);
+        close(pipe_fd[1]);
+        // the parent changes the priority of this process
+        for _ in 0..100 {
+            if getpriority(PRIO_PROCESS, 0) == 100 {
+                exit(0);
+            }
+            sleep(10);
+        }
+        exit(1);
+    }
+    close(pipe_fd[1]);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(pipe_fd[0], &mut buf), 1);
+    close(pipe_fd[0]);
+    assert_eq!(setpriority(PRIO_PROCESS, pid as usize, 100), 0);
+    assert_eq!(getpriority(PRIO_PROCESS, pid as usize), 100);
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // the child has been reaped
+    assert_eq!(getpriority(PRIO_PROCESS, pid as usize), -1);
+    assert_eq!(errno(), ESRCH);
+    assert_eq!(setpriority(PRIO_PROCESS, pid as usize, 100), -1);
+    assert_eq!(errno(), ESRCH);
+    println!("Test priority OK!");
+    0
+}
@@ -0,0 +1,123 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, errno, fstat, lseek, mkdir, open, read, rename, renameat2, rmdir, unlink, write,
+    OpenFlags, Stat, EEXIST, EINVAL, EISDIR, ENOENT, ENOTDIR, ENOTEMPTY, RENAME_EXCHANGE,
+    RENAME_NOREPLACE, SEEK_SET,
+};
+
+/*
+理想结果：rename 在同一目录和不同目录间移动文件与目录，替换已有的文件，
+RENAME_NOREPLACE 不替换已有文件，被替换的文件在关闭前仍可读写，RENAME_EXCHANGE 交换两项，非法的移动返回相应错误，
+输出 Test rename OK!
+*/
+
+fn create(path: &str, content: &str) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, content.as_bytes());
+    close(fd as usize);
+}
+
+fn assert_content(path: &str, content: &str) {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf) as usize;
+    close(fd as usize);
+    assert_eq!(core::str::from_utf8(&buf[..len]).unwrap(), content);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir("mv_a\0"), 0);
+    assert_eq!(mkdir("mv_b\0"), 0);
+    create("mv_a/x\0", "x");
+    create("mv_a/y\0", "y");
+
+    // rename in place, then move to another directory
+    assert_eq!(rename("mv_a/x\0", "mv_a/x1\0"), 0);
+    assert_eq!(open("mv_a/x\0", OpenFlags::RDONLY), -1);
+    assert_eq!(errno(), ENOENT);
+    assert_content("mv_a/x1\0", "x");
+    assert_eq!(rename("mv_a/x1\0", "mv_b/x\0"), 0);
+    assert_content("mv_b/x\0", "x");
+    assert_eq!(rename("mv_a/none\0", "mv_b/none\0"), -1);
+    assert_eq!(errno(), ENOENT);
+
+    // replace a file, unless told not to
+    assert_eq!(renameat2("mv_a/y\0", "mv_b/x\0", RENAME_NOREPLACE), -1);
+    assert_eq!(errno(), EEXIST);
+    assert_eq!(rename("mv_a/y\0", "mv_b/x\0"), 0);
+    assert_content("mv_b/x\0", "y");
+    assert_eq!(open("mv_a/y\0", OpenFlags::RDONLY), -1);
+    // the replaced file stays usable through a descriptor still open on it,
+    // a file created meanwhile does not get its inode
+    let fd = open("mv_b/x\0", OpenFlags::RDWR);
+    assert!(fd > 0);
+    create("mv_a/w\0", "y");
+    assert_eq!(rename("mv_a/w\0", "mv_b/x\0"), 0);
+    create("mv_a/v\0", "v");
+    assert_eq!(write(fd as usize, b"old"), 3);
+    let stat = Stat::new();
+    fstat(fd as usize, &stat);
+    assert_eq!(stat.nlink, 0);
+    lseek(fd as usize, 0, SEEK_SET);
+    let mut buf = [0u8; 8];
+    assert_eq!(read(fd as usize, &mut buf), 3);
+    assert_eq!(&buf[..3], b"old");
+    close(fd as usize);
+    assert_content("mv_b/x\0", "y");
+    assert_content("mv_a/v\0", "v");
+    assert_eq!(unlink("mv_a/v\0"), 0);
+
+    // swap two entries, which must both exist
+    create("mv_a/z\0", "z");
+    assert_eq!(renameat2("mv_a/z\0", "mv_b/x\0", RENAME_EXCHANGE), 0);
+    assert_content("mv_a/z\0", "y");
+    assert_content("mv_b/x\0", "z");
+    assert_eq!(renameat2("mv_a/z\0", "mv_b/none\0", RENAME_EXCHANGE), -1);
+    assert_eq!(errno(), ENOENT);
+    assert_eq!(
+        renameat2("mv_a/z\0", "mv_b/x\0", RENAME_EXCHANGE | RENAME_NOREPLACE),
+        -1
+    );
+    assert_eq!(errno(), EINVAL);
+
+    // move a directory, its link count follows
+    assert_eq!(mkdir("mv_a/d\0"), 0);
+    create("mv_a/d/f\0", "f");
+    assert_eq!(rename("mv_a/d\0", "mv_b/d\0"), 0);
+    assert_content("mv_b/d/f\0", "f");
+    assert_content("mv_b/d/../x\0", "z");
+    let stat = Stat::new();
+    let fd = open("mv_b\0", OpenFlags::RDONLY);
+    fstat(fd as usize, &stat);
+    close(fd as usize);
+    assert_eq!(stat.nlink, 3);
+    // not below itself, and only over an empty directory
+    assert_eq!(rename("mv_b\0", "mv_b/d/b\0"), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(rename("mv_b/d\0", "mv_b/x\0"), -1);
+    assert_eq!(errno(), ENOTDIR);
+    assert_eq!(rename("mv_b/x\0", "mv_b/d\0"), -1);
+    assert_eq!(errno(), EISDIR);
+    assert_eq!(rename("mv_b/d\0", "mv_a\0"), -1);
+    assert_eq!(errno(), ENOTEMPTY);
+    assert_eq!(mkdir("mv_a/e\0"), 0);
+    assert_eq!(rename("mv_b/d\0", "mv_a/e\0"), 0);
+    assert_content("mv_a/e/f\0", "f");
+
+    assert_eq!(unlink("mv_a/e/f\0"), 0);
+    assert_eq!(rmdir("mv_a/e\0"), 0);
+    assert_eq!(unlink("mv_a/z\0"), 0);
+    assert_eq!(unlink("mv_b/x\0"), 0);
+    assert_eq!(rmdir("mv_a\0"), 0);
+    assert_eq!(rmdir("mv_b\0"), 0);
+    println!("Test rename OK!");
+    0
+}
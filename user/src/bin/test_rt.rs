@@ -0,0 +1,132 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    errno, exit, fork, get_time, getrusage, sched_getattr, sched_setattr, waitpid, RUsage,
+    SchedAttr, EINVAL, RUSAGE_SELF, SCHED_DEADLINE, SCHED_FIFO, SCHED_NORMAL,
+};
+
+/*
+理想结果：非法的调度参数返回 EINVAL，实时进程先于普通进程运行，
+用完预算后被限流到下一个周期，输出 Test rt OK!
+*/
+
+const SPIN_MS: isize = 300;
+
+// Spin for SPIN_MS and return the share of the cpu this process got, in percent.
+fn spin() -> i32 {
+    let start = get_time();
+    while get_time() < start + SPIN_MS {}
+    let mut usage = RUsage::default();
+    getrusage(RUSAGE_SELF, &mut usage);
+    ((usage.utime_us + usage.stime_us) / 10 / SPIN_MS as usize) as i32
+}
+
+fn spawn_spinner(attr: &SchedAttr) -> usize {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(sched_setattr(0, attr), 0);
+        exit(spin());
+    }
+    pid as usize
+}
+
+fn reap(pid: usize) -> i32 {
+    let mut exit_code: i32 = -1;
+    assert_eq!(waitpid(pid, &mut exit_code), pid as isize);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut attr = SchedAttr::default();
+    assert_eq!(sched_getattr(0, &mut attr), 0);
+    assert_eq!(attr.policy, SCHED_NORMAL);
+
+    let fifo = SchedAttr {
+        policy: SCHED_FIFO,
+        priority: 10,
+        runtime_us: 10_000,
+        deadline_us: 0,
+        period_us: 50_000,
+    };
+    let invalid = [
+        SchedAttr {
+            priority: 0,
+            ..fifo
+        },
+        SchedAttr {
+            priority: 100,
+            ..fifo
+        },
+        SchedAttr {
+            runtime_us: 0,
+            ..fifo
+        },
+        SchedAttr {
+            runtime_us: 60_000,
+            ..fifo
+        },
+        SchedAttr {
+            policy: SCHED_DEADLINE,
+            ..fifo
+        },
+        SchedAttr {
+            policy: SCHED_DEADLINE,
+            priority: 0,
+            deadline_us: 5_000,
+            ..fifo
+        },
+        SchedAttr { policy: 2, ..fifo },
+    ];
+    for attr in invalid.iter() {
+        assert_eq!(sched_setattr(0, attr), -1);
+        assert_eq!(errno(), EINVAL);
+    }
+
+    // the attributes can be read back, by another process too
+    let pid = fork();
+    if pid == 0 {
+        let deadline = SchedAttr {
+            policy: SCHED_DEADLINE,
+            priority: 0,
+            runtime_us: 1_000,
+            deadline_us: 5_000,
+            period_us: 20_000,
+        };
+        assert_eq!(sched_setattr(0, &deadline), 0);
+        let mut attr = SchedAttr::default();
+        assert_eq!(sched_getattr(0, &mut attr), 0);
+        assert_eq!(attr.policy, SCHED_DEADLINE);
+        assert_eq!(attr.deadline_us, 5_000);
+        exit(0);
+    }
+    assert_eq!(reap(pid as usize), 0);
+
+    // alone, a throttled process gets about its budget only
+    let share = reap(spawn_spinner(&fifo));
+    println!("fifo 10ms/50ms alone: {}% cpu", share);
+    assert!(share < 40);
+
+    // it runs first while it has budget left, the normal one gets the rest
+    let fifo = SchedAttr {
+        runtime_us: 40_000,
+        ..fifo
+    };
+    let normal = SchedAttr::default();
+    let rt_pid = spawn_spinner(&fifo);
+    let normal_pid = spawn_spinner(&normal);
+    let rt_share = reap(rt_pid);
+    let normal_share = reap(normal_pid);
+    println!(
+        "fifo 40ms/50ms: {}% cpu, normal: {}% cpu",
+        rt_share, normal_share
+    );
+    assert!(rt_share >= 60);
+    assert!(normal_share > 0);
+    println!("Test rt OK!");
+    0
+}
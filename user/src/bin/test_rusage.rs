@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    errno, exit, fork, get_time, getpid, getrusage, mmap, times, waitpid, yield_, RUsage, Tms,
+    EINVAL, RUSAGE_CHILDREN, RUSAGE_SELF,
+};
+
+/*
+理想结果：系统调用、缺页、主动切换次数和 CPU 时间都被记录，子进程的用量在回收后计入父进程，
+输出 Test rusage OK!
+*/
+
+const CALLS: usize = 100;
+const PAGES: usize = 4;
+const PAGE_SIZE: usize = 4096;
+
+fn spin(period_ms: isize) {
+    let start = get_time();
+    while get_time() < start + period_ms {}
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut before = RUsage::default();
+    let mut after = RUsage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut before), 0);
+    for _ in 0..CALLS {
+        getpid();
+        yield_();
+    }
+    // mmap areas are allocated on the first access
+    let start: usize = 0x10000000;
+    assert_eq!(mmap(start, PAGES * PAGE_SIZE, 3), 0);
+    for i in 0..PAGES {
+        unsafe {
+            *((start + i * PAGE_SIZE) as *mut u8) = i as u8;
+        }
+    }
+    spin(50);
+    assert_eq!(getrusage(RUSAGE_SELF, &mut after), 0);
+    assert!(after.syscalls >= before.syscalls + 2 * CALLS);
+    assert!(after.voluntary_switches >= before.voluntary_switches + CALLS);
+    assert!(after.page_faults >= before.page_faults + PAGES);
+    assert!(after.utime_us > before.utime_us);
+    assert!(after.stime_us > before.stime_us);
+
+    // the usage of a child is ours once it is reaped
+    let pid = fork();
+    if pid == 0 {
+        for _ in 0..CALLS {
+            getpid();
+        }
+        spin(50);
+        exit(0);
+    }
+    let mut children = RUsage::default();
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut children), 0);
+    assert_eq!(children.syscalls, 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut children), 0);
+    assert!(children.syscalls >= CALLS);
+    assert!(children.utime_us > 0);
+
+    let mut tms = Tms::default();
+    assert!(times(&mut tms) > 0);
+    assert!(tms.utime >= after.utime_us);
+    assert_eq!(tms.cutime, children.utime_us);
+    assert_eq!(getrusage(1, &mut children), -1);
+    assert_eq!(errno(), EINVAL);
+    println!("Test rusage OK!");
+    0
+}
@@ -0,0 +1,138 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, chmod, close, errno, exit, fork, getegid, geteuid, getgid, getpid, getpriority, getuid,
+    kill, mkdir, open, rmdir, sched_setattr, setgid, setpriority, setuid, sleep, stat, unlink,
+    waitpid, write, OpenFlags, SchedAttr, Stat, EACCES, EPERM, MIN_PRIORITY, PRIO_PROCESS,
+    SCHED_FIFO, SIGKILL, SIGUSR1,
+};
+
+/*
+理想结果：进程从 root 开始，子进程 setgid/setuid 为普通用户后不能再切回 root，
+不能向 root 的进程发信号或修改其优先级与调度策略、不能提高自己的优先级或使用实时调度、不能 chmod 或读取 root 的私有文件、不能截断只读的文件、不能穿过没有 x 位的目录、不能在根目录下创建文件，
+但可以向同一用户的进程发信号，输出 Test setuid OK!
+*/
+
+const USER: u32 = 1000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fname = "/setuid_private\0";
+    let fd = open(fname, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, b"secret");
+    close(fd as usize);
+    assert_eq!(chmod(fname, 0o600), 0);
+    let public = "/setuid_public\0";
+    let fd = open(public, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, b"public");
+    close(fd as usize);
+    assert_eq!(mkdir("/setuid_dir\0"), 0);
+    let fd = open("/setuid_dir/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(chmod("/setuid_dir\0", 0o700), 0);
+
+    // root, and so are the children until they say otherwise
+    assert_eq!((getuid(), geteuid(), getgid(), getegid()), (0, 0, 0, 0));
+    let parent = getpid() as usize;
+    let pid = fork();
+    if pid == 0 {
+        // the group first, an ordinary user can not change it any more
+        assert_eq!(setgid(USER), 0);
+        assert_eq!(setuid(USER), 0);
+        assert_eq!(
+            (getuid(), geteuid(), getgid(), getegid()),
+            (USER, USER, USER, USER)
+        );
+        assert_eq!(setuid(0), -1);
+        assert_eq!(errno(), EPERM);
+        assert_eq!(setgid(0), -1);
+        assert_eq!(errno(), EPERM);
+        assert_eq!(setuid(USER), 0);
+
+        // the files and the processes of root are out of reach
+        assert_eq!(kill(parent, SIGUSR1), -1);
+        assert_eq!(errno(), EPERM);
+        assert_eq!(setpriority(PRIO_PROCESS, parent, 16), -1);
+        assert_eq!(errno(), EPERM);
+        assert_eq!(sched_setattr(parent, &SchedAttr::default()), -1);
+        assert_eq!(errno(), EPERM);
+        assert_eq!(chmod(fname, 0o644), -1);
+        assert_eq!(errno(), EPERM);
+        assert_eq!(open(fname, OpenFlags::RDONLY), -1);
+        assert_eq!(errno(), EACCES);
+        // readable is not writable, not even to clear it on open
+        let fd = open(public, OpenFlags::RDONLY);
+        assert!(fd > 0);
+        close(fd as usize);
+        assert_eq!(open(public, OpenFlags::RDONLY | OpenFlags::TRUNC), -1);
+        assert_eq!(errno(), EACCES);
+        assert_eq!(open(public, OpenFlags::CREATE), -1);
+        assert_eq!(errno(), EACCES);
+        // its own priority only goes down, and real-time is for root
+        let prio = getpriority(PRIO_PROCESS, 0);
+        assert!(prio > MIN_PRIORITY);
+        assert_eq!(setpriority(PRIO_PROCESS, 0, prio - 1), 0);
+        assert_eq!(setpriority(PRIO_PROCESS, 0, prio), -1);
+        assert_eq!(errno(), EPERM);
+        let fifo = SchedAttr {
+            policy: SCHED_FIFO,
+            priority: 10,
+            runtime_us: 10_000,
+            deadline_us: 0,
+            period_us: 50_000,
+        };
+        assert_eq!(sched_setattr(0, &fifo), -1);
+        assert_eq!(errno(), EPERM);
+
+        // nothing is reached through a directory without its x bit
+        assert_eq!(open("/setuid_dir/file\0", OpenFlags::RDONLY), -1);
+        assert_eq!(errno(), EACCES);
+        assert_eq!(stat("/setuid_dir/file\0", &Stat::new()), -1);
+        assert_eq!(errno(), EACCES);
+        assert_eq!(chdir("/setuid_dir\0"), -1);
+        assert_eq!(errno(), EACCES);
+        assert_eq!(
+            open("/setuid_new\0", OpenFlags::CREATE | OpenFlags::WRONLY),
+            -1
+        );
+        assert_eq!(errno(), EACCES);
+
+        // a process of the same user can be signaled, it inherits the ids
+        let grandchild = fork();
+        if grandchild == 0 {
+            assert_eq!(getuid(), USER);
+            loop {
+                sleep(10);
+            }
+        }
+        assert_eq!(kill(grandchild as usize, SIGKILL), 0);
+        let mut exit_code = 0;
+        assert_eq!(waitpid(grandchild as usize, &mut exit_code), grandchild);
+        exit(0);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    let st = Stat::new();
+    assert_eq!(stat(public, &st), 0);
+    assert_eq!(st.size, 6);
+    assert_eq!(unlink(public), 0);
+    assert_eq!(unlink("/setuid_dir/file\0"), 0);
+    assert_eq!(rmdir("/setuid_dir\0"), 0);
+
+    // root itself is not bound by the bits
+    let fd = open(fname, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(unlink(fname), 0);
+    println!("Test setuid OK!");
+    0
+}
@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{errno, exit, get_time, nanosleep, sleep, thread_create, waittid, TimeSpec, EINVAL};
+
+/*
+理想结果：睡眠时间不短于请求的时间，睡眠时间不同的线程按到期顺序醒来，
+输出 Test sleep2 OK!
+*/
+
+const THREAD_NUM: usize = 3;
+const PERIODS_MS: [usize; THREAD_NUM] = [300, 100, 200];
+
+static WOKEN: AtomicUsize = AtomicUsize::new(0);
+static mut ORDER: [usize; THREAD_NUM] = [0; THREAD_NUM];
+
+fn sleeper(period_ms: usize) -> ! {
+    let start = get_time();
+    sleep(period_ms);
+    assert!(get_time() - start >= period_ms as isize);
+    let idx = WOKEN.fetch_add(1, Ordering::SeqCst);
+    unsafe {
+        ORDER[idx] = period_ms;
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let req = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    assert_eq!(nanosleep(&req), -1);
+    assert_eq!(errno(), EINVAL);
+    let start = get_time();
+    let req = TimeSpec {
+        sec: 0,
+        nsec: 50_000_000,
+    };
+    assert_eq!(nanosleep(&req), 0);
+    assert!(get_time() - start >= 50);
+    // the threads wake up in the order of their deadlines
+    let mut tids = [0; THREAD_NUM];
+    for (i, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(sleeper as usize, PERIODS_MS[i]);
+        assert!(*tid > 0);
+    }
+    let mut exit_code: i32 = 0;
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize, &mut exit_code), *tid);
+        assert_eq!(exit_code, 0);
+    }
+    assert_eq!(unsafe { ORDER }, [100, 200, 300]);
+    println!("Test sleep2 OK!");
+    0
+}
@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, errno, lstat, mkdir, open, read, readlink, rmdir, stat, symlink, unlink, write,
+    OpenFlags, Stat, StatMode, EEXIST, EINVAL, ELOOP, ENOENT,
+};
+
+/*
+理想结果：符号链接保存目标路径，打开时沿链接找到目标（包括路径中间的目录链接与相对路径），
+悬空链接在 O_CREAT 时创建目标，O_NOFOLLOW 与循环链接返回 ELOOP，lstat 报告 S_IFLNK，
+输出 Test symlink OK!
+*/
+
+fn read_all(path: &str, flags: OpenFlags) -> isize {
+    let fd = open(path, flags);
+    if fd < 0 {
+        return fd;
+    }
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    assert_eq!(&buf[..len as usize], b"target");
+    len
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir("sl_dir\0"), 0);
+    let fd = open("sl_dir/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, b"target");
+    close(fd as usize);
+
+    // the target is kept as given, relative to the directory of the link
+    assert_eq!(symlink("file\0", "sl_dir/link\0"), 0);
+    assert_eq!(symlink("file\0", "sl_dir/link\0"), -1);
+    assert_eq!(errno(), EEXIST);
+    let mut buf = [0u8; 32];
+    assert_eq!(readlink("sl_dir/link\0", &mut buf), 4);
+    assert_eq!(&buf[..4], b"file");
+    assert_eq!(readlink("sl_dir/file\0", &mut buf), -1);
+    assert_eq!(errno(), EINVAL);
+    assert_eq!(read_all("sl_dir/link\0", OpenFlags::RDONLY), 6);
+
+    // a link to a directory in the middle of a path, and a link to a link
+    assert_eq!(symlink("/sl_dir\0", "sl_dlink\0"), 0);
+    assert_eq!(read_all("sl_dlink/link\0", OpenFlags::RDONLY), 6);
+    assert_eq!(symlink("sl_dlink/link\0", "sl_link2\0"), 0);
+    assert_eq!(read_all("sl_link2\0", OpenFlags::RDONLY), 6);
+    assert_eq!(chdir("sl_dlink\0"), 0);
+    assert_eq!(read_all("file\0", OpenFlags::RDONLY), 6);
+    assert_eq!(chdir("/\0"), 0);
+
+    // lstat sees the link, stat what it points to
+    let st = Stat::new();
+    assert_eq!(lstat("sl_link2\0", &st), 0);
+    assert_eq!(st.mode.file_type(), StatMode::LNK);
+    assert_eq!(stat("sl_link2\0", &st), 0);
+    assert_eq!(st.mode.file_type(), StatMode::FILE);
+    assert_eq!(stat("sl_dlink\0", &st), 0);
+    assert_eq!(st.mode.file_type(), StatMode::DIR);
+
+    // O_NOFOLLOW and loops
+    assert_eq!(read_all("sl_link2\0", OpenFlags::NOFOLLOW), -1);
+    assert_eq!(errno(), ELOOP);
+    assert_eq!(symlink("sl_loop_b\0", "sl_loop_a\0"), 0);
+    assert_eq!(symlink("sl_loop_a\0", "sl_loop_b\0"), 0);
+    assert_eq!(open("sl_loop_a\0", OpenFlags::RDONLY), -1);
+    assert_eq!(errno(), ELOOP);
+
+    // a dangling link is created through with O_CREAT only
+    assert_eq!(symlink("sl_dir/new\0", "sl_dangling\0"), 0);
+    assert_eq!(open("sl_dangling\0", OpenFlags::RDONLY), -1);
+    assert_eq!(errno(), ENOENT);
+    let fd = open("sl_dangling\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, b"target");
+    close(fd as usize);
+    assert_eq!(read_all("sl_dir/new\0", OpenFlags::RDONLY), 6);
+
+    // unlink removes the link, not the target
+    for link in [
+        "sl_dangling\0",
+        "sl_loop_a\0",
+        "sl_loop_b\0",
+        "sl_link2\0",
+        "sl_dlink\0",
+        "sl_dir/link\0",
+    ] {
+        assert_eq!(unlink(link), 0);
+    }
+    assert_eq!(read_all("sl_dir/file\0", OpenFlags::RDONLY), 6);
+    assert_eq!(unlink("sl_dir/file\0"), 0);
+    assert_eq!(unlink("sl_dir/new\0"), 0);
+    assert_eq!(rmdir("sl_dir\0"), 0);
+    println!("Test symlink OK!");
+    0
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    errno, exit, mutex_blocking_create, mutex_create, mutex_lock, mutex_unlock, thread_create,
+    waittid, yield_, EINVAL, EPERM,
+};
+
+/*
+理想结果：自旋锁和阻塞锁保护下的计数都不会丢失，输出 Test sync0 OK!
+*/
+
+const THREAD_NUM: usize = 4;
+const PER_THREAD: usize = 50;
+
+static mut COUNTER: usize = 0;
+
+// the yield in the critical section makes every unprotected update racy
+fn adder(mutex_id: usize) -> ! {
+    for _ in 0..PER_THREAD {
+        assert_eq!(mutex_lock(mutex_id), 0);
+        unsafe {
+            let old = COUNTER;
+            yield_();
+            COUNTER = old + 1;
+        }
+        assert_eq!(mutex_unlock(mutex_id), 0);
+    }
+    exit(0)
+}
+
+fn run_adders(mutex_id: usize) {
+    unsafe {
+        COUNTER = 0;
+    }
+    let mut tids = [0; THREAD_NUM];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(adder as usize, mutex_id);
+        assert!(*tid > 0);
+    }
+    let mut exit_code: i32 = 0;
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize, &mut exit_code), *tid);
+        assert_eq!(exit_code, 0);
+    }
+    assert_eq!(unsafe { COUNTER }, THREAD_NUM * PER_THREAD);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let spin = mutex_create();
+    let blocking = mutex_blocking_create();
+    assert!(spin >= 0 && blocking >= 0 && spin != blocking);
+    run_adders(spin as usize);
+    run_adders(blocking as usize);
+    // unlocking a free mutex is an error
+    assert_eq!(mutex_unlock(blocking as usize), -1);
+    assert_eq!(errno(), EPERM);
+    assert_eq!(mutex_lock(100), -1);
+    assert_eq!(errno(), EINVAL);
+    println!("Test sync0 OK!");
+    0
+}
@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, semaphore_create, semaphore_down, semaphore_up, thread_create, waittid};
+
+/*
+理想结果：生产者和消费者通过信号量按顺序交接全部数据，输出 Test sync1 OK!
+*/
+
+const BUFFER_SIZE: usize = 4;
+const ITEMS: usize = 32;
+
+static mut BUFFER: [usize; BUFFER_SIZE] = [0; BUFFER_SIZE];
+static mut SUM: usize = 0;
+
+// semaphores ids: empty slots, filled slots
+static mut SEM_EMPTY: usize = 0;
+static mut SEM_FULL: usize = 0;
+
+fn producer() -> ! {
+    for i in 0..ITEMS {
+        unsafe {
+            semaphore_down(SEM_EMPTY);
+            BUFFER[i % BUFFER_SIZE] = i + 1;
+            semaphore_up(SEM_FULL);
+        }
+    }
+    exit(0)
+}
+
+fn consumer() -> ! {
+    for i in 0..ITEMS {
+        unsafe {
+            semaphore_down(SEM_FULL);
+            assert_eq!(BUFFER[i % BUFFER_SIZE], i + 1);
+            SUM += BUFFER[i % BUFFER_SIZE];
+            semaphore_up(SEM_EMPTY);
+        }
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        SEM_EMPTY = semaphore_create(BUFFER_SIZE) as usize;
+        SEM_FULL = semaphore_create(0) as usize;
+    }
+    // start the consumer first so that it has to sleep on an empty buffer
+    let consumer_tid = thread_create(consumer as usize, 0);
+    let producer_tid = thread_create(producer as usize, 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waittid(consumer_tid as usize, &mut exit_code), consumer_tid);
+    assert_eq!(waittid(producer_tid as usize, &mut exit_code), producer_tid);
+    assert_eq!(unsafe { SUM }, ITEMS * (ITEMS + 1) / 2);
+    println!("Test sync1 OK!");
+    0
+}
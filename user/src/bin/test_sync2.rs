@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_create, condvar_signal, condvar_wait, errno, exit, mutex_blocking_create, mutex_lock,
+    mutex_unlock, thread_create, waittid, yield_, EPERM,
+};
+
+/*
+理想结果：等待者在条件满足后才被唤醒，输出 Test sync2 OK!
+*/
+
+static mut READY: bool = false;
+static mut MUTEX_ID: usize = 0;
+static mut CONDVAR_ID: usize = 0;
+
+fn waiter() -> ! {
+    unsafe {
+        mutex_lock(MUTEX_ID);
+        while !READY {
+            assert_eq!(condvar_wait(CONDVAR_ID, MUTEX_ID), 0);
+        }
+        mutex_unlock(MUTEX_ID);
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        MUTEX_ID = mutex_blocking_create() as usize;
+        CONDVAR_ID = condvar_create() as usize;
+        // waiting without holding the mutex is an error
+        assert_eq!(condvar_wait(CONDVAR_ID, MUTEX_ID), -1);
+        assert_eq!(errno(), EPERM);
+    }
+    let tid = thread_create(waiter as usize, 0);
+    // give the waiter a chance to sleep on the condvar
+    for _ in 0..10 {
+        yield_();
+    }
+    unsafe {
+        mutex_lock(MUTEX_ID);
+        READY = true;
+        condvar_signal(CONDVAR_ID);
+        mutex_unlock(MUTEX_ID);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    assert_eq!(exit_code, 0);
+    println!("Test sync2 OK!");
+    0
+}
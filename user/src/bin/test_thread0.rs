@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{errno, exit, gettid, thread_create, waittid, yield_, EDEADLK, ESRCH};
+
+/*
+理想结果：三个线程交替写入各自的结果，全部结束后，输出 Test thread0 OK!
+*/
+
+const THREAD_NUM: usize = 3;
+const ROUNDS: usize = 100;
+
+static mut RESULTS: [usize; THREAD_NUM] = [0; THREAD_NUM];
+
+struct Argument {
+    idx: usize,
+    exit_code: i32,
+}
+
+fn thread_main(arg: *const Argument) -> ! {
+    let arg = unsafe { &*arg };
+    for _ in 0..ROUNDS {
+        unsafe {
+            RESULTS[arg.idx] += arg.idx + 1;
+        }
+        yield_();
+    }
+    exit(arg.exit_code)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    let args = [
+        Argument {
+            idx: 0,
+            exit_code: 1,
+        },
+        Argument {
+            idx: 1,
+            exit_code: 2,
+        },
+        Argument {
+            idx: 2,
+            exit_code: 3,
+        },
+    ];
+    let mut tids = [0; THREAD_NUM];
+    for (i, arg) in args.iter().enumerate() {
+        tids[i] = thread_create(thread_main as usize, arg as *const _ as usize);
+        assert_eq!(tids[i], i as isize + 1);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waittid(0, &mut exit_code), -1);
+    assert_eq!(errno(), EDEADLK);
+    for (i, tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(*tid as usize, &mut exit_code), *tid);
+        assert_eq!(exit_code, args[i].exit_code);
+        // the thread has been reaped
+        assert_eq!(waittid(*tid as usize, &mut exit_code), -1);
+        assert_eq!(errno(), ESRCH);
+    }
+    for i in 0..THREAD_NUM {
+        assert_eq!(unsafe { RESULTS[i] }, (i + 1) * ROUNDS);
+    }
+    // the tids are reused after the threads are reaped
+    let tid = thread_create(thread_main as usize, &args[0] as *const _ as usize);
+    assert_eq!(tid, tids[THREAD_NUM - 1]);
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    println!("Test thread0 OK!");
+    0
+}
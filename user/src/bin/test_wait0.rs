@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, errno, exit, fork, getpid, kill, pipe, read, sigaction, sigreturn, waitpid, waitpid_nb,
+    write, yield_, SignalAction, EAGAIN, SIGUSR1,
+};
+
+/*
+理想结果：waitpid 在子进程退出前睡眠，读管道在有数据或写端全部关闭时被唤醒，
+等待中的信号能被处理，输出 Test wait0 OK!
+*/
+
+const ROUNDS: usize = 50;
+static STR: &str = "Hello, wait queue!";
+static mut HANDLED: bool = false;
+
+fn busy() {
+    for _ in 0..ROUNDS {
+        yield_();
+    }
+}
+
+fn handler() {
+    unsafe {
+        HANDLED = true;
+    }
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut exit_code: i32 = 0;
+    // the child is still running, waitpid_nb returns at once
+    let pid = fork();
+    if pid == 0 {
+        busy();
+        exit(7);
+    }
+    assert_eq!(waitpid_nb(pid as usize, &mut exit_code), -1);
+    assert_eq!(errno(), EAGAIN);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+
+    // the reader sleeps until the data arrives and then until the end of file
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        let mut buffer = [0u8; 32];
+        let len_read = read(pipe_fd[0], &mut buffer) as usize;
+        assert_eq!(core::str::from_utf8(&buffer[..len_read]).unwrap(), STR);
+        assert_eq!(read(pipe_fd[0], &mut buffer), 0);
+        close(pipe_fd[0]);
+        exit(0);
+    }
+    close(pipe_fd[0]);
+    busy();
+    assert_eq!(write(pipe_fd[1], STR.as_bytes()), STR.len() as isize);
+    busy();
+    close(pipe_fd[1]);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // a signal sent to a sleeping waitpid is handled before it goes on waiting
+    let mut action = SignalAction::default();
+    let old_action = SignalAction::default();
+    action.handler = handler as usize;
+    assert_eq!(sigaction(SIGUSR1, &action, &old_action), 0);
+    let parent = getpid();
+    let pid = fork();
+    if pid == 0 {
+        busy();
+        assert_eq!(kill(parent as usize, SIGUSR1), 0);
+        busy();
+        exit(0);
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert!(unsafe { HANDLED });
+    println!("Test wait0 OK!");
+    0
+}
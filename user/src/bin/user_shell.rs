@@ -13,70 +13,395 @@ const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
 const LINE_START: &str = ">> ";
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 use user_lib::console::getchar;
-use user_lib::{close, dup, exec, fork, open, pipe, waitpid, OpenFlags};
+use user_lib::{
+    chdir, close, dup2, errno, exec, exit, fork, getpid, ioctl, killpg, open, pipe, setpgid,
+    setsid, sigaction, strerror, tcsetpgrp, waitpid_options, wifstopped, write, OpenFlags,
+    SignalAction, SIGCONT, SIGINT, SIGTSTP, SIG_DFL, SIG_IGN, TIOCSCTTY, WNOHANG, WUNTRACED,
+};
+
+// a word or an operator of a command line
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Pipe,
+    Background,
+    // the redirection operator, after the fd it applies to if one is given
+    Redirect(Option<usize>, RedirectOp),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RedirectOp {
+    // <
+    Input,
+    // >
+    Output,
+    // >>
+    Append,
+    // <& and >&
+    Dup,
+    // <<<
+    HereString,
+}
+
+#[derive(Debug)]
+enum Redirect {
+    // open path with flags as fd
+    File {
+        fd: usize,
+        path: String,
+        flags: OpenFlags,
+    },
+    // make fd a copy of from
+    Dup {
+        fd: usize,
+        from: usize,
+    },
+    // feed the text and a newline to fd
+    HereString {
+        fd: usize,
+        text: String,
+    },
+}
+
+// Split a command line into words and operators. Single quotes keep
+// everything literally, double quotes keep all but the backslash escapes
+// of `"` and `\`, and a backslash outside quotes escapes any character.
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    // whether a word has been started, it may be an empty quoted string
+    let mut in_word = false;
+    // whether the word has no quoted or escaped part, only then can it be
+    // the fd of a redirection
+    let mut plain = true;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => {
+                if in_word {
+                    tokens.push(Token::Word(core::mem::take(&mut word)));
+                    in_word = false;
+                    plain = true;
+                }
+            }
+            '\'' => {
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("unterminated single quote")),
+                    }
+                }
+                in_word = true;
+                plain = false;
+            }
+            '"' => {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"') | Some('\\')) => {
+                            word.push(chars.next().unwrap());
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("unterminated double quote")),
+                    }
+                }
+                in_word = true;
+                plain = false;
+            }
+            '\\' => {
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err(String::from("backslash at the end of line")),
+                }
+                in_word = true;
+                plain = false;
+            }
+            '|' | '&' | '<' | '>' => {
+                // digits right before a redirection are the fd it applies to
+                let mut fd = None;
+                if in_word {
+                    if (c == '<' || c == '>') && plain && word.bytes().all(|b| b.is_ascii_digit()) {
+                        fd = Some(word.parse().map_err(|_| String::from("bad fd number"))?);
+                        word.clear();
+                    } else {
+                        tokens.push(Token::Word(core::mem::take(&mut word)));
+                    }
+                    in_word = false;
+                    plain = true;
+                }
+                let token = match c {
+                    '|' => Token::Pipe,
+                    '&' => Token::Background,
+                    '<' if chars.peek() == Some(&'<') => {
+                        chars.next();
+                        if chars.next() != Some('<') {
+                            return Err(String::from("here-documents are not supported"));
+                        }
+                        Token::Redirect(fd, RedirectOp::HereString)
+                    }
+                    '>' if chars.peek() == Some(&'>') => {
+                        chars.next();
+                        Token::Redirect(fd, RedirectOp::Append)
+                    }
+                    _ if chars.peek() == Some(&'&') => {
+                        chars.next();
+                        let fd = fd.or(if c == '<' { Some(0) } else { Some(1) });
+                        Token::Redirect(fd, RedirectOp::Dup)
+                    }
+                    '<' => Token::Redirect(fd, RedirectOp::Input),
+                    _ => Token::Redirect(fd, RedirectOp::Output),
+                };
+                tokens.push(token);
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
 
 #[derive(Debug)]
 struct ProcessArguments {
-    input: String,
-    output: String,
+    // applied in order, after the pipes are connected
+    redirects: Vec<Redirect>,
     args_copy: Vec<String>,
     args_addr: Vec<*const u8>,
 }
 
 impl ProcessArguments {
-    pub fn new(command: &str) -> Self {
-        let args: Vec<_> = command.split(' ').collect();
-        let mut args_copy: Vec<String> = args
-            .iter()
-            .filter(|&arg| !arg.is_empty())
-            .map(|&arg| {
-                let mut string = String::new();
-                string.push_str(arg);
-                string.push('\0');
-                string
-            })
-            .collect();
-
-        // redirect input
-        let mut input = String::new();
-        if let Some((idx, _)) = args_copy
-            .iter()
-            .enumerate()
-            .find(|(_, arg)| arg.as_str() == "<\0")
-        {
-            input = args_copy[idx + 1].clone();
-            args_copy.drain(idx..=idx + 1);
+    // a stage of a pipeline, its tokens contain no Pipe and no Background
+    pub fn new(tokens: &[Token]) -> Result<Self, String> {
+        let mut args_copy: Vec<String> = Vec::new();
+        let mut redirects: Vec<Redirect> = Vec::new();
+        let mut iter = tokens.iter();
+        while let Some(token) = iter.next() {
+            match token {
+                Token::Word(arg) => {
+                    let mut string = arg.clone();
+                    string.push('\0');
+                    args_copy.push(string);
+                }
+                Token::Redirect(fd, op) => {
+                    let target = match iter.next() {
+                        Some(Token::Word(target)) => target.clone(),
+                        _ => return Err(String::from("syntax error: missing redirection target")),
+                    };
+                    let redirect = match op {
+                        RedirectOp::Dup => {
+                            let from = target
+                                .parse()
+                                .map_err(|_| target.clone() + ": ambiguous redirect")?;
+                            Redirect::Dup {
+                                fd: fd.unwrap(),
+                                from,
+                            }
+                        }
+                        RedirectOp::HereString => Redirect::HereString {
+                            fd: fd.unwrap_or(0),
+                            text: target,
+                        },
+                        op => {
+                            let (default_fd, flags) = match op {
+                                RedirectOp::Input => (0, OpenFlags::RDONLY),
+                                RedirectOp::Output => (1, OpenFlags::CREATE | OpenFlags::WRONLY),
+                                _ => (1, OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::APPEND),
+                            };
+                            let mut path = target;
+                            path.push('\0');
+                            Redirect::File {
+                                fd: fd.unwrap_or(default_fd),
+                                path,
+                                flags,
+                            }
+                        }
+                    };
+                    redirects.push(redirect);
+                }
+                _ => unreachable!(),
+            }
         }
-
-        // redirect output
-        let mut output = String::new();
-        if let Some((idx, _)) = args_copy
-            .iter()
-            .enumerate()
-            .find(|(_, arg)| arg.as_str() == ">\0")
-        {
-            output = args_copy[idx + 1].clone();
-            args_copy.drain(idx..=idx + 1);
+        if args_copy.is_empty() {
+            return Err(String::from("syntax error: missing command"));
         }
 
         let mut args_addr: Vec<*const u8> = args_copy.iter().map(|arg| arg.as_ptr()).collect();
         args_addr.push(core::ptr::null::<u8>());
 
-        Self {
-            input,
-            output,
+        Ok(Self {
+            redirects,
             args_copy,
             args_addr,
+        })
+    }
+}
+
+// Parse a command line into the stages of a pipeline, and whether it runs
+// in the background.
+fn parse(tokens: &[Token]) -> Result<(Vec<ProcessArguments>, bool), String> {
+    let (tokens, background) = match tokens.split_last() {
+        Some((Token::Background, rest)) => (rest, true),
+        _ => (tokens, false),
+    };
+    if tokens.contains(&Token::Background) {
+        return Err(String::from("syntax error near `&'"));
+    }
+    let stages = tokens
+        .split(|token| *token == Token::Pipe)
+        .map(ProcessArguments::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((stages, background))
+}
+
+// Move the descriptor from to the number to, closing the one there.
+fn move_fd(from: usize, to: usize) -> Result<(), String> {
+    if from == to {
+        return Ok(());
+    }
+    if dup2(from, to) < 0 {
+        return Err(format!("{}: {}", to, strerror(errno())));
+    }
+    close(from);
+    Ok(())
+}
+
+// Apply the redirections of a stage in the child which runs it.
+fn apply_redirects(redirects: &[Redirect]) -> Result<(), String> {
+    for redirect in redirects.iter() {
+        match redirect {
+            Redirect::File { fd, path, flags } => {
+                let file = open(path.as_str(), *flags);
+                if file < 0 {
+                    return Err(format!(
+                        "{}: {}",
+                        path.trim_end_matches('\0'),
+                        strerror(errno())
+                    ));
+                }
+                move_fd(file as usize, *fd)?;
+            }
+            Redirect::Dup { fd, from } => {
+                if dup2(*from, *fd) < 0 {
+                    return Err(format!("{}: {}", from, strerror(errno())));
+                }
+            }
+            Redirect::HereString { fd, text } => {
+                let mut pipe_fd = [0usize; 2];
+                if pipe(&mut pipe_fd) < 0 {
+                    return Err(format!("<<<: {}", strerror(errno())));
+                }
+                // the pipe holds only a little, a writer of its own feeds it
+                if fork() == 0 {
+                    close(pipe_fd[0]);
+                    write(pipe_fd[1], text.as_bytes());
+                    write(pipe_fd[1], b"\n");
+                    exit(0);
+                }
+                close(pipe_fd[1]);
+                move_fd(pipe_fd[0], *fd)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// a pipeline run in a process group of its own
+struct Job {
+    id: usize,
+    pgid: usize,
+    // the processes which have not exited yet
+    pids: Vec<usize>,
+    command: String,
+    stopped: bool,
+}
+
+fn set_signal_handler(signum: i32, handler: usize) {
+    let action = SignalAction {
+        handler,
+        ..Default::default()
+    };
+    let old_action = SignalAction::default();
+    sigaction(signum, &action, &old_action);
+}
+
+// Wait for the job in the foreground until it exits or is stopped, and take
+// the console back then. Return whether the job has exited.
+fn wait_foreground(job: &mut Job) -> bool {
+    tcsetpgrp(0, job.pgid);
+    let mut status: i32 = 0;
+    while let Some(&pid) = job.pids.first() {
+        if waitpid_options(pid as isize, &mut status, WUNTRACED) < 0 {
+            // not our child any more
+            job.pids.remove(0);
+        } else if wifstopped(status) {
+            job.stopped = true;
+            break;
+        } else {
+            job.pids.remove(0);
         }
     }
+    tcsetpgrp(0, getpid() as usize);
+    if job.stopped {
+        println!("");
+        println!("[{}] Stopped\t{}", job.id, job.command);
+    }
+    job.pids.is_empty()
+}
+
+// Collect the background jobs which have exited or been stopped, without
+// waiting for the others.
+fn reap_jobs(jobs: &mut Vec<Job>) {
+    let mut status: i32 = 0;
+    for job in jobs.iter_mut() {
+        job.pids.retain(|&pid| {
+            match waitpid_options(pid as isize, &mut status, WNOHANG | WUNTRACED) {
+                // still running
+                n if n < 0 && errno() == user_lib::errno::EAGAIN => true,
+                n if n < 0 => false,
+                _ if wifstopped(status) => {
+                    job.stopped = true;
+                    true
+                }
+                _ => false,
+            }
+        });
+        if job.pids.is_empty() {
+            println!("[{}] Done\t{}", job.id, job.command);
+        }
+    }
+    jobs.retain(|job| !job.pids.is_empty());
+}
+
+// The job with the id in the argument, the latest one without an argument.
+fn find_job<'a>(jobs: &'a mut [Job], arg: Option<&&str>) -> Option<&'a mut Job> {
+    match arg {
+        Some(arg) => {
+            let id: usize = arg.trim_start_matches('%').parse().ok()?;
+            jobs.iter_mut().find(|job| job.id == id)
+        }
+        None => jobs.last_mut(),
+    }
 }
 
 #[no_mangle]
 pub fn main() -> i32 {
     println!("Rust user shell");
+    // lead a session with the console as its terminal, Ctrl-C and Ctrl-Z
+    // are for the jobs in the foreground
+    setsid();
+    ioctl(0, TIOCSCTTY, 0);
+    set_signal_handler(SIGINT, SIG_IGN);
+    set_signal_handler(SIGTSTP, SIG_IGN);
+    let mut jobs: Vec<Job> = Vec::new();
     let mut line: String = String::new();
     print!("{}", LINE_START);
     loop {
@@ -84,117 +409,143 @@ pub fn main() -> i32 {
         match c {
             LF | CR => {
                 println!("");
-                if !line.is_empty() {
-                    let splited: Vec<_> = line.as_str().split('|').collect();
-                    let process_arguments_list: Vec<_> = splited
-                        .iter()
-                        .map(|&cmd| ProcessArguments::new(cmd))
-                        .collect();
-                    let mut valid = true;
-                    for (i, process_args) in process_arguments_list.iter().enumerate() {
-                        if i == 0 {
-                            if !process_args.output.is_empty() {
-                                valid = false;
-                            }
-                        } else if i == process_arguments_list.len() - 1 {
-                            if !process_args.input.is_empty() {
-                                valid = false;
-                            }
-                        } else if !process_args.output.is_empty() || !process_args.input.is_empty()
-                        {
-                            valid = false;
-                        }
+                let tokens = match tokenize(line.as_str()) {
+                    Ok(tokens) => tokens,
+                    Err(err) => {
+                        println!("{}", err);
+                        line.clear();
+                        print!("{}", LINE_START);
+                        continue;
                     }
-                    if process_arguments_list.len() == 1 {
-                        valid = true;
+                };
+                let words: Vec<&str> = tokens
+                    .iter()
+                    .map_while(|token| match token {
+                        Token::Word(word) => Some(word.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                // cd must change the directory of the shell itself
+                if words.first() == Some(&"cd") {
+                    let mut path = String::from(*words.get(1).unwrap_or(&"/"));
+                    path.push('\0');
+                    if chdir(path.as_str()) != 0 {
+                        println!(
+                            "cd: {}: {}",
+                            words.get(1).unwrap_or(&"/"),
+                            strerror(errno())
+                        );
                     }
-                    if !valid {
-                        println!("Invalid command: Inputs/Outputs cannot be correctly binded!");
-                    } else {
-                        // create pipes
-                        let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
-                        if !process_arguments_list.is_empty() {
-                            for _ in 0..process_arguments_list.len() - 1 {
-                                let mut pipe_fd = [0usize; 2];
-                                pipe(&mut pipe_fd);
-                                pipes_fd.push(pipe_fd);
+                } else if words.first() == Some(&"jobs") {
+                    reap_jobs(&mut jobs);
+                    for job in jobs.iter() {
+                        let state = if job.stopped { "Stopped" } else { "Running" };
+                        println!("[{}] {}\t{}", job.id, state, job.command);
+                    }
+                } else if words.first() == Some(&"fg") || words.first() == Some(&"bg") {
+                    match find_job(&mut jobs, words.get(1)) {
+                        Some(job) => {
+                            println!("{}", job.command);
+                            job.stopped = false;
+                            if words[0] == "fg" {
+                                // in the foreground before it may read
+                                tcsetpgrp(0, job.pgid);
+                                killpg(job.pgid, SIGCONT);
+                                if wait_foreground(job) {
+                                    let id = job.id;
+                                    jobs.retain(|job| job.id != id);
+                                }
+                            } else {
+                                killpg(job.pgid, SIGCONT);
                             }
                         }
-                        let mut children: Vec<_> = Vec::new();
-                        for (i, process_argument) in process_arguments_list.iter().enumerate() {
-                            let pid = fork();
-                            if pid == 0 {
-                                let input = &process_argument.input;
-                                let output = &process_argument.output;
-                                let args_copy = &process_argument.args_copy;
-                                let args_addr = &process_argument.args_addr;
-                                // redirect input
-                                if !input.is_empty() {
-                                    let input_fd = open(input.as_str(), OpenFlags::RDONLY);
-                                    if input_fd == -1 {
-                                        println!("Error when opening file {}", input);
-                                        return -4;
-                                    }
-                                    let input_fd = input_fd as usize;
-                                    close(0);
-                                    assert_eq!(dup(input_fd), 0);
-                                    close(input_fd);
+                        None => println!("{}: no such job", words[0]),
+                    }
+                } else if !tokens.is_empty() {
+                    let command = String::from(line.trim().trim_end_matches('&').trim_end());
+                    match parse(&tokens) {
+                        Err(err) => println!("{}", err),
+                        Ok((process_arguments_list, background)) => {
+                            // create pipes
+                            let mut pipes_fd: Vec<[usize; 2]> = Vec::new();
+                            if !process_arguments_list.is_empty() {
+                                for _ in 0..process_arguments_list.len() - 1 {
+                                    let mut pipe_fd = [0usize; 2];
+                                    pipe(&mut pipe_fd);
+                                    pipes_fd.push(pipe_fd);
                                 }
-                                // redirect output
-                                if !output.is_empty() {
-                                    let output_fd = open(
-                                        output.as_str(),
-                                        OpenFlags::CREATE | OpenFlags::WRONLY,
-                                    );
-                                    if output_fd == -1 {
-                                        println!("Error when opening file {}", output);
+                            }
+                            let mut children: Vec<usize> = Vec::new();
+                            for (i, process_argument) in process_arguments_list.iter().enumerate() {
+                                let pid = fork();
+                                if pid == 0 {
+                                    // join the group of the job, led by its first process
+                                    setpgid(0, children.first().copied().unwrap_or(0));
+                                    set_signal_handler(SIGINT, SIG_DFL);
+                                    set_signal_handler(SIGTSTP, SIG_DFL);
+                                    let args_copy = &process_argument.args_copy;
+                                    let args_addr = &process_argument.args_addr;
+                                    // receive input from the previous process
+                                    if i > 0 {
+                                        let read_end = pipes_fd.get(i - 1).unwrap()[0];
+                                        assert_eq!(dup2(read_end, 0), 0);
+                                    }
+                                    // send output to the next process
+                                    if i < process_arguments_list.len() - 1 {
+                                        let write_end = pipes_fd.get(i).unwrap()[1];
+                                        assert_eq!(dup2(write_end, 1), 1);
+                                    }
+                                    // close all pipe ends inherited from the parent process
+                                    for pipe_fd in pipes_fd.iter() {
+                                        close(pipe_fd[0]);
+                                        close(pipe_fd[1]);
+                                    }
+                                    // the redirections apply on top of the pipes
+                                    if let Err(err) = apply_redirects(&process_argument.redirects) {
+                                        println!("{}", err);
                                         return -4;
                                     }
-                                    let output_fd = output_fd as usize;
-                                    close(1);
-                                    assert_eq!(dup(output_fd), 1);
-                                    close(output_fd);
-                                }
-                                // receive input from the previous process
-                                if i > 0 {
-                                    close(0);
-                                    let read_end = pipes_fd.get(i - 1).unwrap()[0];
-                                    assert_eq!(dup(read_end), 0);
-                                }
-                                // send output to the next process
-                                if i < process_arguments_list.len() - 1 {
-                                    close(1);
-                                    let write_end = pipes_fd.get(i).unwrap()[1];
-                                    assert_eq!(dup(write_end), 1);
-                                }
-                                // close all pipe ends inherited from the parent process
-                                for pipe_fd in pipes_fd.iter() {
-                                    close(pipe_fd[0]);
-                                    close(pipe_fd[1]);
-                                }
-                                // execute new application
-                                if exec(args_copy[0].as_str(), args_addr.as_slice()) == -1 {
-                                    println!("Error when executing!");
+                                    // execute new application
+                                    exec(args_copy[0].as_str(), args_addr.as_slice());
+                                    // apps live in the root directory, so look them up there as well
+                                    if !args_copy[0].contains('/') {
+                                        let path = String::from("/") + args_copy[0].as_str();
+                                        exec(path.as_str(), args_addr.as_slice());
+                                    }
+                                    println!(
+                                        "{}: {}",
+                                        args_copy[0].trim_end_matches('\0'),
+                                        strerror(errno())
+                                    );
                                     return -4;
+                                } else {
+                                    // also set here, the child may not have run yet
+                                    setpgid(pid as usize, children.first().copied().unwrap_or(0));
+                                    children.push(pid as usize);
                                 }
-                                unreachable!();
-                            } else {
-                                children.push(pid);
                             }
-                        }
-                        for pipe_fd in pipes_fd.iter() {
-                            close(pipe_fd[0]);
-                            close(pipe_fd[1]);
-                        }
-                        let mut exit_code: i32 = 0;
-                        for pid in children.into_iter() {
-                            let exit_pid = waitpid(pid as usize, &mut exit_code);
-                            assert_eq!(pid, exit_pid);
-                            //println!("Shell: Process {} exited with code {}", pid, exit_code);
+                            for pipe_fd in pipes_fd.iter() {
+                                close(pipe_fd[0]);
+                                close(pipe_fd[1]);
+                            }
+                            let mut job = Job {
+                                id: jobs.last().map_or(1, |job| job.id + 1),
+                                pgid: children[0],
+                                pids: children,
+                                command,
+                                stopped: false,
+                            };
+                            if background {
+                                println!("[{}] {}", job.id, job.pgid);
+                                jobs.push(job);
+                            } else if !wait_foreground(&mut job) {
+                                jobs.push(job);
+                            }
                         }
                     }
-                    line.clear();
                 }
+                line.clear();
+                reap_jobs(&mut jobs);
                 print!("{}", LINE_START);
             }
             BS | DL => {
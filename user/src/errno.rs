@@ -0,0 +1,90 @@
+//! Error numbers returned by the kernel, following the Linux values.
+
+use core::sync::atomic::{AtomicIsize, Ordering};
+
+pub const EPERM: isize = 1;
+pub const ENOENT: isize = 2;
+pub const ESRCH: isize = 3;
+pub const EINTR: isize = 4;
+pub const EIO: isize = 5;
+pub const E2BIG: isize = 7;
+pub const ENOEXEC: isize = 8;
+pub const EBADF: isize = 9;
+pub const ECHILD: isize = 10;
+pub const EAGAIN: isize = 11;
+pub const ENOMEM: isize = 12;
+pub const EACCES: isize = 13;
+pub const EFAULT: isize = 14;
+pub const EBUSY: isize = 16;
+pub const EEXIST: isize = 17;
+pub const ENOTDIR: isize = 20;
+pub const EISDIR: isize = 21;
+pub const EINVAL: isize = 22;
+pub const EMFILE: isize = 24;
+pub const ENOTTY: isize = 25;
+pub const EFBIG: isize = 27;
+pub const ENOSPC: isize = 28;
+pub const ESPIPE: isize = 29;
+pub const EPIPE: isize = 32;
+pub const ERANGE: isize = 34;
+pub const EDEADLK: isize = 35;
+pub const ENAMETOOLONG: isize = 36;
+pub const ENOSYS: isize = 38;
+pub const ENOTEMPTY: isize = 39;
+pub const ELOOP: isize = 40;
+
+/// Largest error number the kernel may return, as on Linux
+const MAX_ERRNO: isize = 4095;
+
+static ERRNO: AtomicIsize = AtomicIsize::new(0);
+
+/// Error number of the last failed library call
+pub fn errno() -> isize {
+    ERRNO.load(Ordering::Relaxed)
+}
+
+/// Turn a raw `-errno` syscall return into `-1`, saving the error for `errno()`.
+pub(crate) fn check(ret: isize) -> isize {
+    if ret < 0 && ret >= -MAX_ERRNO {
+        ERRNO.store(-ret, Ordering::Relaxed);
+        -1
+    } else {
+        ret
+    }
+}
+
+pub fn strerror(errno: isize) -> &'static str {
+    match errno {
+        EPERM => "Operation not permitted",
+        ENOENT => "No such file or directory",
+        ESRCH => "No such process",
+        EINTR => "Interrupted system call",
+        EIO => "Input/output error",
+        E2BIG => "Argument list too long",
+        ENOEXEC => "Exec format error",
+        EBADF => "Bad file descriptor",
+        ECHILD => "No child processes",
+        EAGAIN => "Resource temporarily unavailable",
+        ENOMEM => "Cannot allocate memory",
+        EACCES => "Permission denied",
+        EFAULT => "Bad address",
+        EBUSY => "Device or resource busy",
+        EEXIST => "File exists",
+        ENOTDIR => "Not a directory",
+        EISDIR => "Is a directory",
+        EINVAL => "Invalid argument",
+        EMFILE => "Too many open files",
+        ENOTTY => "Inappropriate ioctl for device",
+        EFBIG => "File too large",
+        ENOSPC => "No space left on device",
+        ESPIPE => "Illegal seek",
+        EPIPE => "Broken pipe",
+        ERANGE => "Numerical result out of range",
+        EDEADLK => "Resource deadlock avoided",
+        ENAMETOOLONG => "File name too long",
+        ENOSYS => "Function not implemented",
+        ENOTEMPTY => "Directory not empty",
+        ELOOP => "Too many levels of symbolic links",
+        _ => "Unknown error",
+    }
+}
@@ -5,6 +5,7 @@
 
 #[macro_use]
 pub mod console;
+pub mod errno;
 mod lang_items;
 mod syscall;
 
@@ -16,6 +17,8 @@ extern crate bitflags;
 use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
 pub use console::{flush, STDIN, STDOUT};
+use errno::check;
+pub use errno::*;
 pub use syscall::*;
 
 const USER_HEAP_SIZE: usize = 16384;
@@ -67,6 +70,11 @@ bitflags! {
         const RDWR = 1 << 1;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        const APPEND = 1 << 11;
+        const NONBLOCK = 1 << 12;
+        const DIRECTORY = 1 << 16;
+        const NOFOLLOW = 1 << 17;
+        const CLOEXEC = 1 << 19;
     }
 }
 
@@ -83,6 +91,58 @@ impl TimeVal {
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// Times of a process and its reaped children, in microseconds.
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+
+/// Resources used by a process, the times are in microseconds.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RUsage {
+    pub utime_us: usize,
+    pub stime_us: usize,
+    pub syscalls: usize,
+    pub page_faults: usize,
+    pub voluntary_switches: usize,
+    pub involuntary_switches: usize,
+}
+
+pub const SCHED_NORMAL: usize = 0;
+pub const SCHED_FIFO: usize = 1;
+pub const SCHED_DEADLINE: usize = 6;
+pub const MAX_RT_PRIORITY: usize = 99;
+
+/// Scheduling policy of a process, the times are in microseconds.
+/// A fifo or deadline process runs before the normal ones, for at most
+/// runtime_us in every period_us. Only root may set those.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SchedAttr {
+    pub policy: usize,
+    /// Fifo only, from 1 to MAX_RT_PRIORITY.
+    pub priority: usize,
+    pub runtime_us: usize,
+    /// Deadline only, relative to the start of a period, 0 for the end of the period.
+    pub deadline_us: usize,
+    pub period_us: usize,
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct Stat {
@@ -90,12 +150,31 @@ pub struct Stat {
     pub dev: u64,
     /// inode number
     pub ino: u64,
-    /// file type and mode
+    /// file type and permission bits
     pub mode: StatMode,
     /// number of hard links
     pub nlink: u32,
-    /// unused pad
-    pad: [u64; 7],
+    /// user ID of owner
+    pub uid: u32,
+    /// group ID of owner
+    pub gid: u32,
+    /// device ID, for special files
+    pub rdev: u64,
+    pad1: u64,
+    /// total size in bytes
+    pub size: u64,
+    /// block size for I/O
+    pub blksize: u32,
+    pad2: u32,
+    /// number of 512B blocks allocated
+    pub blocks: u64,
+    /// time of last access
+    pub atime: TimeSpec,
+    /// time of last modification
+    pub mtime: TimeSpec,
+    /// time of last status change
+    pub ctime: TimeSpec,
+    unused: [u32; 2],
 }
 
 impl Stat {
@@ -105,7 +184,18 @@ impl Stat {
             ino: 0,
             mode: StatMode::NULL,
             nlink: 0,
-            pad: [0; 7],
+            uid: 0,
+            gid: 0,
+            rdev: 0,
+            pad1: 0,
+            size: 0,
+            blksize: 0,
+            pad2: 0,
+            blocks: 0,
+            atime: TimeSpec::default(),
+            mtime: TimeSpec::default(),
+            ctime: TimeSpec::default(),
+            unused: [0; 2],
         }
     }
 }
@@ -119,56 +209,239 @@ impl Default for Stat {
 bitflags! {
     pub struct StatMode: u32 {
         const NULL  = 0;
+        /// bit mask for the file type
+        const IFMT  = 0o170000;
         /// directory
         const DIR   = 0o040000;
         /// ordinary regular file
         const FILE  = 0o100000;
+        /// symbolic link
+        const LNK   = 0o120000;
+        /// bit mask for the permission bits, with setuid, setgid and sticky
+        const PERM  = 0o007777;
+    }
+}
+
+impl StatMode {
+    /// One of DIR, FILE and LNK.
+    pub fn file_type(&self) -> StatMode {
+        *self & Self::IFMT
+    }
+
+    pub fn perm(&self) -> u32 {
+        (*self & Self::PERM).bits
+    }
+}
+
+/// d_type of a directory entry
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+pub const DT_LNK: u8 = 10;
+
+/// An entry of a directory, as filled in by getdents.
+pub struct DirEntry<'a> {
+    pub ino: u64,
+    pub file_type: u8,
+    pub name: &'a str,
+}
+
+/// The linux_dirent64 records in the buffer filled by getdents.
+pub struct DirEntries<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> DirEntries<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for DirEntries<'a> {
+    type Item = DirEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let ino = u64::from_ne_bytes(self.buf[0..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(self.buf[16..18].try_into().unwrap()) as usize;
+        let file_type = self.buf[18];
+        let name = &self.buf[19..reclen];
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let name = core::str::from_utf8(&name[..len]).unwrap();
+        self.buf = &self.buf[reclen..];
+        Some(DirEntry {
+            ino,
+            file_type,
+            name,
+        })
     }
 }
 
 const AT_FDCWD: isize = -100;
+const AT_REMOVEDIR: usize = 0x200;
+const AT_SYMLINK_NOFOLLOW: usize = 0x100;
 
 pub fn open(path: &str, flags: OpenFlags) -> isize {
-    sys_open(path, flags.bits)
+    check(sys_open(path, flags.bits))
 }
 
 pub fn openat(path: &str, flags: OpenFlags) -> isize {
-    sys_openat(AT_FDCWD as usize, path, flags.bits, OpenFlags::RDWR.bits)
+    check(sys_openat(AT_FDCWD as usize, path, flags.bits, 0o644))
 }
 
 pub fn close(fd: usize) -> isize {
     if fd == STDOUT {
         console::flush();
     }
-    sys_close(fd)
+    check(sys_close(fd))
 }
 
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
-    sys_read(fd, buf)
+    check(sys_read(fd, buf))
 }
 
 pub fn write(fd: usize, buf: &[u8]) -> isize {
-    sys_write(fd, buf)
+    check(sys_write(fd, buf))
+}
+
+/// Fill buf with the next entries of the directory fd, read them with
+/// DirEntries. Return 0 at the end of the directory.
+pub fn getdents(fd: usize, buf: &mut [u8]) -> isize {
+    check(sys_getdents64(fd, buf))
+}
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+/// Move the offset of fd as whence says, return the new offset.
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    check(sys_lseek(fd, offset, whence))
+}
+
+/// Read from offset of fd without moving the offset of the file.
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> isize {
+    check(sys_pread64(fd, buf, offset))
+}
+
+/// Write at offset of fd without moving the offset of the file.
+pub fn pwrite(fd: usize, buf: &[u8], offset: usize) -> isize {
+    check(sys_pwrite64(fd, buf, offset))
+}
+
+/// Cut the file fd to len bytes, or extend it with zeros.
+pub fn ftruncate(fd: usize, len: usize) -> isize {
+    check(sys_ftruncate(fd, len))
 }
 
 pub fn link(old_path: &str, new_path: &str) -> isize {
-    sys_linkat(AT_FDCWD as usize, old_path, AT_FDCWD as usize, new_path, 0)
+    check(sys_linkat(
+        AT_FDCWD as usize,
+        old_path,
+        AT_FDCWD as usize,
+        new_path,
+        0,
+    ))
+}
+
+/// Fail with EEXIST instead of replacing the new path.
+pub const RENAME_NOREPLACE: usize = 1;
+/// Swap the two paths, which must both exist.
+pub const RENAME_EXCHANGE: usize = 2;
+
+/// Move old_path to new_path, replacing what is there.
+pub fn rename(old_path: &str, new_path: &str) -> isize {
+    renameat2(old_path, new_path, 0)
+}
+
+pub fn renameat2(old_path: &str, new_path: &str, flags: usize) -> isize {
+    check(sys_renameat2(
+        AT_FDCWD as usize,
+        old_path,
+        AT_FDCWD as usize,
+        new_path,
+        flags,
+    ))
 }
 
 pub fn unlink(path: &str) -> isize {
-    sys_unlinkat(AT_FDCWD as usize, path, 0)
+    check(sys_unlinkat(AT_FDCWD as usize, path, 0))
+}
+
+pub fn mkdir(path: &str) -> isize {
+    check(sys_mkdirat(AT_FDCWD as usize, path, 0o755))
+}
+
+pub fn rmdir(path: &str) -> isize {
+    check(sys_unlinkat(AT_FDCWD as usize, path, AT_REMOVEDIR))
+}
+
+pub fn chdir(path: &str) -> isize {
+    check(sys_chdir(path))
+}
+
+/// Write the current working directory into buf, return the length with the trailing '\0'.
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    check(sys_getcwd(buf))
 }
 
 pub fn fstat(fd: usize, st: &Stat) -> isize {
-    sys_fstat(fd, st)
+    check(sys_fstat(fd, st))
+}
+
+/// fstat on path, following it if it is a symbolic link.
+pub fn stat(path: &str, st: &Stat) -> isize {
+    check(sys_fstatat(AT_FDCWD as usize, path, st, 0))
+}
+
+/// stat on the symbolic link itself.
+pub fn lstat(path: &str, st: &Stat) -> isize {
+    check(sys_fstatat(
+        AT_FDCWD as usize,
+        path,
+        st,
+        AT_SYMLINK_NOFOLLOW,
+    ))
+}
+
+/// Create link_path pointing to target, which need not exist.
+pub fn symlink(target: &str, link_path: &str) -> isize {
+    check(sys_symlinkat(target, AT_FDCWD as usize, link_path))
+}
+
+/// Copy the target of the symbolic link at path into buf, without a trailing
+/// '\0'. Return the size copied.
+pub fn readlink(path: &str, buf: &mut [u8]) -> isize {
+    check(sys_readlinkat(AT_FDCWD as usize, path, buf))
+}
+
+/// Set the permission bits of path, the owner or root only.
+pub fn chmod(path: &str, mode: u32) -> isize {
+    check(sys_fchmodat(AT_FDCWD as usize, path, mode, 0))
+}
+
+/// Set the owner and the group of path, u32::MAX leaves one as it is.
+pub fn chown(path: &str, uid: u32, gid: u32) -> isize {
+    check(sys_fchownat(AT_FDCWD as usize, path, uid, gid, 0))
+}
+
+/// nsec of a time given to utimensat, for the current time or none
+pub const UTIME_NOW: usize = (1 << 30) - 1;
+pub const UTIME_OMIT: usize = (1 << 30) - 2;
+
+/// Set the access and modification times of path, both to now if times is
+/// None.
+pub fn utimensat(path: &str, times: Option<&[TimeSpec; 2]>) -> isize {
+    check(sys_utimensat(AT_FDCWD as usize, path, times, 0))
 }
 
 pub fn mail_read(buf: &mut [u8]) -> isize {
-    sys_mail_read(buf)
+    check(sys_mail_read(buf))
 }
 
 pub fn mail_write(pid: usize, buf: &[u8]) -> isize {
-    sys_mail_write(pid, buf)
+    check(sys_mail_write(pid, buf))
 }
 
 pub fn exit(exit_code: i32) -> ! {
@@ -180,6 +453,24 @@ pub fn yield_() -> isize {
     sys_yield()
 }
 
+/// Return the microseconds since boot.
+pub fn times(tms: &mut Tms) -> isize {
+    check(sys_times(tms))
+}
+
+pub fn getrusage(who: isize, usage: &mut RUsage) -> isize {
+    check(sys_getrusage(who, usage))
+}
+
+/// Set the scheduling policy of the process pid, 0 for the caller.
+pub fn sched_setattr(pid: usize, attr: &SchedAttr) -> isize {
+    check(sys_sched_setattr(pid, attr, 0))
+}
+
+pub fn sched_getattr(pid: usize, attr: &mut SchedAttr) -> isize {
+    check(sys_sched_getattr(pid, attr))
+}
+
 pub fn get_time() -> isize {
     let time = TimeVal::new();
     match sys_get_time(&time, 0) {
@@ -192,26 +483,105 @@ pub fn getpid() -> isize {
     sys_getpid()
 }
 
+pub fn getuid() -> u32 {
+    sys_getuid() as u32
+}
+
+pub fn geteuid() -> u32 {
+    sys_geteuid() as u32
+}
+
+pub fn getgid() -> u32 {
+    sys_getgid() as u32
+}
+
+pub fn getegid() -> u32 {
+    sys_getegid() as u32
+}
+
+/// Set the real and the effective user id as root, or only the effective
+/// one back to the real one otherwise.
+pub fn setuid(uid: u32) -> isize {
+    check(sys_setuid(uid))
+}
+
+/// Set the group ids, the same way as setuid.
+pub fn setgid(gid: u32) -> isize {
+    check(sys_setgid(gid))
+}
+
+/// Move the process pid into the group pgid, 0 for either means the caller.
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    check(sys_setpgid(pid, pgid))
+}
+
+pub fn getpgid(pid: usize) -> isize {
+    check(sys_getpgid(pid))
+}
+
+pub fn getsid(pid: usize) -> isize {
+    check(sys_getsid(pid))
+}
+
+/// Start a new session and group led by the caller.
+pub fn setsid() -> isize {
+    check(sys_setsid())
+}
+
 pub fn fork() -> isize {
-    sys_fork()
+    check(sys_fork())
 }
 
 pub fn exec(path: &str, args: &[*const u8]) -> isize {
-    sys_exec(path, args)
+    check(sys_exec(path, args))
 }
 
+/// Target of getpriority and setpriority: the process whose pid is who, 0 for the caller.
+pub const PRIO_PROCESS: usize = 0;
+/// Priorities range from MIN_PRIORITY to MAX_PRIORITY, a higher one gets more cpu time.
+pub const MIN_PRIORITY: isize = 2;
+pub const MAX_PRIORITY: isize = 1024;
+pub const DEFAULT_PRIORITY: isize = 16;
+
+/// Return the priority of a process.
+pub fn getpriority(which: usize, who: usize) -> isize {
+    check(sys_getpriority(which, who))
+}
+
+/// Set the priority of all the threads of a process, only root may raise it.
+pub fn setpriority(which: usize, who: usize, prio: isize) -> isize {
+    check(sys_setpriority(which, who, prio))
+}
+
+/// Set the priority of the calling process, return prio.
 pub fn set_priority(prio: isize) -> isize {
-    sys_set_priority(prio)
+    match setpriority(PRIO_PROCESS, 0, prio) {
+        0 => prio,
+        err => err,
+    }
+}
+
+/// Option of sys_waitpid, fail with EAGAIN instead of waiting for a running child.
+pub const WNOHANG: usize = 1;
+pub const WUNTRACED: usize = 2;
+
+/// Whether the status from waitpid_options is of a stopped child.
+pub fn wifstopped(status: i32) -> bool {
+    status & 0xff == 0x7f
+}
+
+/// The signal which stopped the child.
+pub fn wstopsig(status: i32) -> i32 {
+    (status >> 8) & 0xff
 }
 
 pub fn wait(exit_code: &mut i32) -> isize {
     loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                sys_yield();
-            }
+        match sys_waitpid(-1, exit_code as *mut _, 0) {
+            // the signal has been handled on the way back, wait again
+            n if n == -errno::EINTR => {}
             n => {
-                return n;
+                return check(n);
             }
         }
     }
@@ -219,45 +589,190 @@ pub fn wait(exit_code: &mut i32) -> isize {
 
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                sys_yield();
-            }
+        match sys_waitpid(pid as isize, exit_code as *mut _, 0) {
+            n if n == -errno::EINTR => {}
             n => {
-                return n;
+                return check(n);
             }
         }
     }
 }
 
+/// Wait for the child pid without sleeping. Unlike Linux, which returns 0,
+/// this fails with EAGAIN while the child is still running.
 pub fn waitpid_nb(pid: usize, exit_code: &mut i32) -> isize {
-    sys_waitpid(pid as isize, exit_code as *mut _)
+    check(sys_waitpid(pid as isize, exit_code as *mut _, WNOHANG))
 }
 
-pub fn sleep(period_ms: usize) {
-    let start = get_time();
-    while get_time() < start + period_ms as isize {
-        sys_yield();
+/// Wait for the child pid, any child if pid is -1, with WNOHANG and WUNTRACED
+/// in options. WNOHANG fails with EAGAIN if no child is ready, where Linux
+/// returns 0.
+pub fn waitpid_options(pid: isize, status: &mut i32, options: usize) -> isize {
+    loop {
+        match sys_waitpid(pid, status as *mut _, options) {
+            n if n == -errno::EINTR => {}
+            n => {
+                return check(n);
+            }
+        }
     }
 }
 
+/// Sleep for at least the time in req.
+pub fn nanosleep(req: &TimeSpec) -> isize {
+    check(sys_nanosleep(req, core::ptr::null_mut()))
+}
+
+pub fn sleep(period_ms: usize) {
+    let req = TimeSpec {
+        sec: period_ms / 1000,
+        nsec: period_ms % 1000 * 1_000_000,
+    };
+    nanosleep(&req);
+}
+
 pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
-    sys_mmap(start, len, prot)
+    check(sys_mmap(start, len, prot))
 }
 
 pub fn munmap(start: usize, len: usize) -> isize {
-    sys_munmap(start, len)
+    check(sys_munmap(start, len))
 }
 
 pub fn spawn(path: &str) -> isize {
-    sys_spawn(path)
+    check(sys_spawn(path))
+}
+
+/// Run entry(arg) in a new thread of the current process, return the tid.
+/// The thread must end with `exit`, which only terminates the whole process
+/// when called from the main thread.
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    check(sys_thread_create(entry, arg))
+}
+
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+
+pub fn waittid(tid: usize, exit_code: &mut i32) -> isize {
+    loop {
+        match sys_waittid(tid, exit_code as *mut _) {
+            n if n == -errno::EAGAIN => {
+                sys_yield();
+            }
+            n => {
+                return check(n);
+            }
+        }
+    }
+}
+
+/// Create a mutex that yields the cpu while waiting for the lock, return its id.
+pub fn mutex_create() -> isize {
+    check(sys_mutex_create(false))
+}
+
+/// Create a mutex that sleeps while waiting for the lock, return its id.
+pub fn mutex_blocking_create() -> isize {
+    check(sys_mutex_create(true))
+}
+
+pub fn mutex_lock(mutex_id: usize) -> isize {
+    check(sys_mutex_lock(mutex_id))
+}
+
+pub fn mutex_unlock(mutex_id: usize) -> isize {
+    check(sys_mutex_unlock(mutex_id))
+}
+
+pub fn semaphore_create(res_count: usize) -> isize {
+    check(sys_semaphore_create(res_count))
+}
+
+pub fn semaphore_up(sem_id: usize) -> isize {
+    check(sys_semaphore_up(sem_id))
+}
+
+pub fn semaphore_down(sem_id: usize) -> isize {
+    check(sys_semaphore_down(sem_id))
+}
+
+pub fn condvar_create() -> isize {
+    check(sys_condvar_create())
+}
+
+pub fn condvar_signal(condvar_id: usize) -> isize {
+    check(sys_condvar_signal(condvar_id))
+}
+
+/// Unlock the mutex and sleep until signaled, the mutex is locked again on return.
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    check(sys_condvar_wait(condvar_id, mutex_id))
+}
+
+/// Once enabled, mutex_lock and semaphore_down fail with EDEADLK instead of
+/// waiting when the wait may deadlock the threads of this process.
+pub fn enable_deadlock_detect(enabled: bool) -> isize {
+    check(sys_enable_deadlock_detect(enabled as usize))
 }
 
 pub fn dup(fd: usize) -> isize {
-    sys_dup(fd)
+    check(sys_dup(fd))
+}
+
+/// Make newfd a copy of oldfd, closing what newfd was. Nothing is done if
+/// they are the same valid descriptor.
+pub fn dup2(oldfd: usize, newfd: usize) -> isize {
+    if oldfd == newfd {
+        return match check(sys_fcntl(oldfd, F_GETFD, 0)) {
+            -1 => -1,
+            _ => newfd as isize,
+        };
+    }
+    check(sys_dup3(oldfd, newfd, 0))
+}
+
+/// dup2 with O_CLOEXEC in flags for newfd.
+pub fn dup3(oldfd: usize, newfd: usize, flags: OpenFlags) -> isize {
+    check(sys_dup3(oldfd, newfd, flags.bits))
+}
+
+pub const F_DUPFD: usize = 0;
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const F_DUPFD_CLOEXEC: usize = 1030;
+pub const FD_CLOEXEC: usize = 1;
+
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    check(sys_fcntl(fd, cmd, arg))
+}
+
+pub const TIOCSCTTY: usize = 0x540E;
+pub const TIOCGPGRP: usize = 0x540F;
+pub const TIOCSPGRP: usize = 0x5410;
+
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    check(sys_ioctl(fd, cmd, arg))
+}
+
+/// Move the group pgid to the foreground of the console behind fd.
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> isize {
+    let pgid = pgid as i32;
+    ioctl(fd, TIOCSPGRP, &pgid as *const i32 as usize)
+}
+
+/// The foreground group of the console behind fd.
+pub fn tcgetpgrp(fd: usize) -> isize {
+    let mut pgid = 0i32;
+    match ioctl(fd, TIOCGPGRP, &mut pgid as *mut i32 as usize) {
+        0 => pgid as isize,
+        err => err,
+    }
 }
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
+    check(sys_pipe(pipe_fd))
 }
 
 /// Action for a signal
@@ -268,6 +783,10 @@ pub struct SignalAction {
     pub mask: SignalFlags,
 }
 
+/// Handlers with a special meaning
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
 impl Default for SignalAction {
     fn default() -> Self {
         Self {
@@ -348,7 +867,12 @@ bitflags! {
 }
 
 pub fn kill(pid: usize, signal: i32) -> isize {
-    sys_kill(pid, signal)
+    check(sys_kill(pid as isize, signal))
+}
+
+/// Send the signal to every process in the group pgid.
+pub fn killpg(pgid: usize, signal: i32) -> isize {
+    check(sys_kill(-(pgid as isize), signal))
 }
 
 pub fn sigaction(
@@ -356,13 +880,13 @@ pub fn sigaction(
     action: *const SignalAction,
     old_action: *const SignalAction,
 ) -> isize {
-    sys_sigaction(signum, action, old_action)
+    check(sys_sigaction(signum, action, old_action))
 }
 
 pub fn sigprocmask(mask: u32) -> isize {
-    sys_sigprocmask(mask)
+    check(sys_sigprocmask(mask))
 }
 
 pub fn sigreturn() -> isize {
-    sys_sigreturn()
+    check(sys_sigreturn())
 }